@@ -0,0 +1,89 @@
+//! Per-handle audit trail of mutating commands.
+//!
+//! Opt-in with `strata_set_audit`. Entries are kept in a fixed-capacity ring
+//! buffer: once full, the oldest entry is dropped for each new one.
+
+use std::collections::VecDeque;
+
+use serde_json::{json, Value};
+
+use crate::handle::unix_millis;
+
+/// Tags of commands that modify the database.
+const MUTATING_TAGS: &[&str] = &[
+    "KvPut",
+    "KvBatchPut",
+    "KvDelete",
+    "KvRename",
+    "KvSwap",
+    "KvGetOrPut",
+    "KvUpdate",
+    "JsonSet",
+    "JsonBatchSet",
+    "JsonDelete",
+    "JsonMerge",
+    "JsonRename",
+    "JsonCanonicalize",
+    "EventAppend",
+    "EventBatchAppend",
+    "StateSet",
+    "StateInit",
+    "StateCas",
+    "StateDelete",
+    "StateBatchSet",
+    "VectorUpsert",
+    "VectorBatchUpsert",
+    "VectorDelete",
+    "VectorCreateCollection",
+    "VectorDeleteCollection",
+    "BranchCreate",
+    "BranchDelete",
+    "BranchFork",
+    "BranchMerge",
+    "SpaceCreate",
+    "SpaceDelete",
+    "RetentionApply",
+];
+
+/// Fields naming what a command modifies, in order of preference.
+const TARGET_FIELDS: &[&str] = &["key", "cell", "to", "collection", "branch_id", "branch", "space"];
+
+pub fn is_mutation(tag: &str) -> bool {
+    MUTATING_TAGS.contains(&tag)
+}
+
+/// The key (or cell, collection, branch, ...) a command body targets, if any.
+pub fn target_key(body: &Value) -> Option<&str> {
+    TARGET_FIELDS.iter().find_map(|f| body.get(*f)).and_then(Value::as_str)
+}
+
+pub struct AuditLog {
+    capacity: usize,
+    entries: VecDeque<Value>,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity.min(1024)),
+        }
+    }
+
+    /// Record a mutation as `{"timestamp", "command_tag", "key"}`.
+    pub fn record(&mut self, tag: &str, key: Option<&str>) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(json!({
+            "timestamp": unix_millis(),
+            "command_tag": tag,
+            "key": key,
+        }));
+    }
+
+    /// Entries oldest first.
+    pub fn entries(&self) -> Vec<Value> {
+        self.entries.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,75 @@
+//! Backup-on-open (`backup_on_open` in `config_json`).
+//!
+//! Before a database directory is opened, it is copied to a timestamped
+//! sibling, `<name>.backup-<unix millis>`, and backups beyond the retention
+//! count are deleted, oldest first.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::handle::unix_millis;
+
+/// Copy `dir` to a new backup beside it and prune all but the newest
+/// `retain`. Returns `None`, without pruning, if `dir` does not exist yet.
+pub fn backup(dir: &Path, retain: u32) -> io::Result<Option<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let prefix = backup_prefix(dir);
+    let mut millis = unix_millis();
+    // Two opens within a millisecond still get distinct backups.
+    let target = loop {
+        let target = dir.with_file_name(format!("{prefix}{millis}"));
+        if !target.exists() {
+            break target;
+        }
+        millis += 1;
+    };
+    copy_dir(dir, &target)?;
+    prune(dir, retain)?;
+    Ok(Some(target))
+}
+
+/// Existing backups of `dir`, oldest first.
+pub fn list(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let prefix = backup_prefix(dir);
+    let parent = dir.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut backups: Vec<(u64, PathBuf)> = std::fs::read_dir(parent)?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let millis = name.to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            Some((millis, entry.path()))
+        })
+        .collect();
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+fn prune(dir: &Path, retain: u32) -> io::Result<()> {
+    let backups = list(dir)?;
+    let excess = backups.len().saturating_sub(retain as usize);
+    for old in &backups[..excess] {
+        std::fs::remove_dir_all(old)?;
+    }
+    Ok(())
+}
+
+fn backup_prefix(dir: &Path) -> String {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    format!("{name}.backup-")
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Command batches for `strata_execute_batch`.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::{Session, Strata};
+
+use crate::commands;
+use crate::error::BridgeError;
+use crate::limits::Limits;
+
+/// A batch as sent by Swift: `{"commands": [...], "atomic": false, "branch": null}`,
+/// or a bare array of commands for a best-effort batch.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Batch {
+    Options {
+        commands: Vec<Value>,
+        #[serde(default)]
+        atomic: bool,
+        /// Branch the atomic batch's transaction runs on.
+        branch: Option<String>,
+    },
+    Commands(Vec<Value>),
+}
+
+impl Batch {
+    pub fn parse(json: &str) -> Result<Self, BridgeError> {
+        serde_json::from_str(json).map_err(|e| BridgeError::from(format!("invalid batch JSON: {e}")))
+    }
+}
+
+/// Run every command in one transaction, rolling all of them back if any
+/// fails. The error names the failing command's index.
+///
+/// Only stratadb commands can join the transaction; bridge commands run
+/// their own and are rejected here.
+pub fn run_atomic(
+    strata: &Strata,
+    branch: Option<&str>,
+    commands: &[Value],
+    limits: Limits,
+) -> Result<Vec<Value>, BridgeError> {
+    commands::in_transaction(strata, branch, |txn| {
+        let mut outputs = Vec::with_capacity(commands.len());
+        for (index, command) in commands.iter().enumerate() {
+            let output = run_one(txn, command, limits).map_err(|e| {
+                BridgeError::Kind("BatchFailed", json!({ "index": index, "error": e.to_json() }))
+            })?;
+            outputs.push(output);
+        }
+        Ok(outputs)
+    })
+}
+
+fn run_one(txn: &mut Session, command: &Value, limits: Limits) -> Result<Value, BridgeError> {
+    let tag = command.as_object().and_then(|m| m.keys().next());
+    if tag.is_some_and(|tag| commands::is_bridge_command(tag)) {
+        return Err(BridgeError::from("bridge commands cannot run in an atomic batch"));
+    }
+    limits.check_command(command)?;
+    commands::call(txn, command.clone())
+}
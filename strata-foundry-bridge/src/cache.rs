@@ -0,0 +1,400 @@
+//! Size cap for in-memory handles used as caches (`max_bytes` / `eviction`).
+//!
+//! stratadb does not report memory use, so the bridge accounts for KV entries
+//! itself: each key costs its length plus its value's JSON length. KV writes
+//! (`ACCOUNTED_TAGS`) that would exceed the cap either evict the least
+//! recently used keys first (`"lru"`) or are refused with `CacheFull`
+//! (`"reject"`). Reads through `KvGet` count as a use, and `KvGetOrPut` is
+//! a read of a present key or a put of its `default`. The bridge commands
+//! that move or patch values (`KvRename`, `KvSwap`, `KvUpdate`) have their
+//! results worked out by reading the values first. Transactions and atomic
+//! batches are accounted for as a whole through `run_commands`, and puts
+//! outside `strata_execute` (`strata_kv_put_bytes`, `strata_copy_kv`)
+//! through `put`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use crate::commands::{self, patch};
+use crate::error::BridgeError;
+use crate::handle::command_tag;
+
+/// KV commands a cap accounts for; anything else passes straight through.
+const ACCOUNTED_TAGS: &[&str] = &[
+    "KvPut",
+    "KvBatchPut",
+    "KvDelete",
+    "KvGet",
+    "KvGetOrPut",
+    "KvRename",
+    "KvSwap",
+    "KvUpdate",
+];
+
+/// What to do when a write would exceed `max_bytes`.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Eviction {
+    /// Evict least recently used keys until the write fits.
+    Lru,
+    /// Refuse the write with `CacheFull`.
+    #[default]
+    Reject,
+}
+
+/// A key in a given branch and space.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    branch: Option<String>,
+    space: Option<String>,
+    key: String,
+}
+
+impl CacheKey {
+    /// `key` in the branch and space `body` names, or `branch` if it names
+    /// none.
+    fn new(body: &Value, branch: Option<&str>, key: &str) -> Self {
+        Self {
+            branch: body["branch"].as_str().or(branch).map(String::from),
+            space: body["space"].as_str().map(String::from),
+            key: key.to_string(),
+        }
+    }
+
+    fn command(&self, tag: &str) -> Value {
+        let mut fields = json!({ "key": self.key });
+        if let Some(branch) = &self.branch {
+            fields["branch"] = json!(branch);
+        }
+        if let Some(space) = &self.space {
+            fields["space"] = json!(space);
+        }
+        json!({ tag: fields })
+    }
+
+    /// The key's current value, `None` if absent.
+    fn read(&self, strata: &Strata) -> Result<Option<Value>, BridgeError> {
+        let output = commands::call(&mut strata.executor(), self.command("KvGet"))?;
+        Ok(commands::maybe_versioned(output).map(|mut record| record["value"].take()))
+    }
+}
+
+/// What a run of commands does to the keys a cap accounts for.
+#[derive(Default)]
+struct Plan {
+    /// Each key's size once the commands ran, `None` if deleted.
+    changes: HashMap<CacheKey, Option<u64>>,
+    reads: Vec<CacheKey>,
+}
+
+impl Plan {
+    fn set(&mut self, key: CacheKey, size: u64) {
+        self.changes.insert(key, Some(size));
+    }
+
+    fn write(&mut self, key: CacheKey, value: &Value) {
+        let size = key.key.len() + value.to_string().len();
+        self.set(key, size as u64);
+    }
+
+    fn delete(&mut self, key: CacheKey) {
+        self.changes.insert(key, None);
+    }
+
+    /// Add what `command` does, on `branch` if it names none. A command
+    /// that will fail, such as a rename of a missing key, adds nothing.
+    fn add(
+        &mut self,
+        strata: &Strata,
+        branch: Option<&str>,
+        command: &Value,
+    ) -> Result<(), BridgeError> {
+        let Some((tag, body)) = command.as_object().and_then(|m| m.iter().next()) else {
+            return Ok(());
+        };
+        let key = |field: &str| Some(CacheKey::new(body, branch, body[field].as_str()?));
+        match tag.as_str() {
+            "KvPut" => {
+                if let Some(key) = key("key") {
+                    self.write(key, &body["value"]);
+                }
+            }
+            "KvBatchPut" => {
+                let entries = body["entries"].as_array().map(Vec::as_slice).unwrap_or_default();
+                for entry in entries {
+                    if let Some(key) = entry["key"].as_str() {
+                        self.write(CacheKey::new(body, branch, key), &entry["value"]);
+                    }
+                }
+            }
+            "KvDelete" => {
+                if let Some(key) = key("key") {
+                    self.delete(key);
+                }
+            }
+            "KvGet" => self.reads.extend(key("key")),
+            "KvGetOrPut" => {
+                let Some(key) = key("key") else {
+                    return Ok(());
+                };
+                match key.read(strata)? {
+                    Some(_) => self.reads.push(key),
+                    None => self.write(key, &body["default"]),
+                }
+            }
+            "KvRename" => {
+                let (Some(from), Some(to)) = (key("from"), key("to")) else {
+                    return Ok(());
+                };
+                if let Some(value) = from.read(strata)? {
+                    self.delete(from);
+                    self.write(to, &value);
+                }
+            }
+            "KvSwap" => {
+                let (Some(a), Some(b)) = (key("key_a"), key("key_b")) else {
+                    return Ok(());
+                };
+                let (value_a, value_b) = (a.read(strata)?, b.read(strata)?);
+                for (key, value) in [(a, value_b), (b, value_a)] {
+                    match value {
+                        Some(value) => self.write(key, &value),
+                        None => self.delete(key),
+                    }
+                }
+            }
+            "KvUpdate" => {
+                let Some(key) = key("key") else {
+                    return Ok(());
+                };
+                let Some(mut value) = key.read(strata)?.or_else(|| body.get("upsert").cloned())
+                else {
+                    return Ok(());
+                };
+                let ops = serde_json::from_value::<Vec<patch::Op>>(body["ops"].clone());
+                if ops.is_ok_and(|ops| patch::apply(&mut value, &ops).is_ok()) {
+                    self.write(key, &value);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Usage {
+    bytes: u64,
+    /// Each key's size and the tick it was last used at.
+    entries: HashMap<CacheKey, (u64, u64)>,
+    /// Keys by last use, oldest first.
+    by_use: BTreeMap<u64, CacheKey>,
+    tick: u64,
+    evictions: u64,
+}
+
+impl Usage {
+    fn touch(&mut self, key: &CacheKey) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some((_, used)) = self.entries.get_mut(key) {
+            self.by_use.remove(used);
+            *used = tick;
+            self.by_use.insert(tick, key.clone());
+        }
+    }
+
+    fn set(&mut self, key: CacheKey, size: u64) {
+        self.remove(&key);
+        self.tick += 1;
+        self.bytes += size;
+        self.by_use.insert(self.tick, key.clone());
+        self.entries.insert(key, (size, self.tick));
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some((size, used)) = self.entries.remove(key) {
+            self.bytes -= size;
+            self.by_use.remove(&used);
+        }
+    }
+
+    fn size_of(&self, key: &CacheKey) -> u64 {
+        self.entries.get(key).map_or(0, |(size, _)| *size)
+    }
+}
+
+/// A handle's size cap and its current usage.
+pub struct MemoryCap {
+    max_bytes: u64,
+    eviction: Eviction,
+    usage: Mutex<Usage>,
+}
+
+impl MemoryCap {
+    /// A cap with room reserved for `expected_entries` keys.
+    pub fn new(max_bytes: u64, eviction: Eviction, expected_entries: usize) -> Self {
+        let usage = Usage {
+            entries: HashMap::with_capacity(expected_entries),
+            ..Usage::default()
+        };
+        Self { max_bytes, eviction, usage: Mutex::new(usage) }
+    }
+
+    /// `{"max_bytes", "used_bytes", "entries", "evictions", "eviction"}`
+    pub fn stats(&self) -> Value {
+        let usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        json!({
+            "max_bytes": self.max_bytes,
+            "used_bytes": usage.bytes,
+            "entries": usage.entries.len(),
+            "evictions": usage.evictions,
+            "eviction": self.eviction,
+        })
+    }
+
+    /// Under memory pressure, evict the least recently used keys until
+    /// `percent` of the bytes in use are freed, and release spare capacity.
+    /// Caps that `"reject"` writes hold data the host did not agree to lose,
+    /// so they only release capacity. Returns the keys and bytes evicted.
+    pub fn shed(&self, strata: &Strata, percent: u64) -> Result<(u64, u64), BridgeError> {
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut keys, mut freed) = (0, 0);
+        if self.eviction == Eviction::Lru {
+            let target = usage.bytes * percent / 100;
+            let oldest: Vec<CacheKey> = usage.by_use.values().cloned().collect();
+            for key in oldest {
+                if freed >= target {
+                    break;
+                }
+                commands::call(&mut strata.executor(), key.command("KvDelete"))?;
+                freed += usage.size_of(&key);
+                usage.remove(&key);
+                usage.evictions += 1;
+                keys += 1;
+            }
+        }
+        usage.entries.shrink_to_fit();
+        Ok((keys, freed))
+    }
+
+    /// Run `command_json` with `run`, making room for it first and
+    /// accounting for it once it succeeds. Commands outside `ACCOUNTED_TAGS`
+    /// pass straight through.
+    pub fn run(
+        &self,
+        strata: &Strata,
+        command_json: &str,
+        run: impl FnOnce(&Strata) -> Result<String, BridgeError>,
+    ) -> Result<String, BridgeError> {
+        if !command_tag(command_json).is_some_and(|tag| ACCOUNTED_TAGS.contains(&tag.as_str())) {
+            return run(strata);
+        }
+        let command: Value =
+            serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+        self.run_commands(strata, None, std::slice::from_ref(&command), run)
+    }
+
+    /// Run `run`, which carries out `commands` together (a transaction or
+    /// atomic batch) on `branch` where they name none, making room for all of
+    /// them first and accounting for them once it succeeds.
+    pub fn run_commands<T>(
+        &self,
+        strata: &Strata,
+        branch: Option<&str>,
+        commands: &[Value],
+        run: impl FnOnce(&Strata) -> Result<T, BridgeError>,
+    ) -> Result<T, BridgeError> {
+        // Held across the commands so concurrent writes cannot overshoot the cap.
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let mut plan = Plan::default();
+        for command in commands {
+            plan.add(strata, branch, command)?;
+        }
+        self.make_room(strata, &mut usage, &plan)?;
+
+        let output = run(strata)?;
+        for (key, size) in plan.changes {
+            match size {
+                Some(size) => usage.set(key, size),
+                None => usage.remove(&key),
+            }
+        }
+        for key in &plan.reads {
+            usage.touch(key);
+        }
+        Ok(output)
+    }
+
+    /// Run a put of `size` value bytes to `key` in the default branch and
+    /// space with `put`, making room for it first and accounting for it once
+    /// it succeeds.
+    pub fn put<T>(
+        &self,
+        strata: &Strata,
+        key: &str,
+        size: u64,
+        put: impl FnOnce(&Strata) -> Result<T, BridgeError>,
+    ) -> Result<T, BridgeError> {
+        let (key, size) = (CacheKey::new(&Value::Null, None, key), key.len() as u64 + size);
+        let mut plan = Plan::default();
+        plan.set(key.clone(), size);
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        self.make_room(strata, &mut usage, &plan)?;
+        let output = put(strata)?;
+        usage.set(key, size);
+        Ok(output)
+    }
+
+    /// Ensure `plan`'s writes fit under the cap, evicting or refusing as
+    /// configured.
+    fn make_room(
+        &self,
+        strata: &Strata,
+        usage: &mut Usage,
+        plan: &Plan,
+    ) -> Result<(), BridgeError> {
+        let mut replaced = 0;
+        let mut added = 0;
+        for (key, size) in &plan.changes {
+            replaced += usage.size_of(key);
+            added += size.unwrap_or_default();
+        }
+        let needed = (usage.bytes - replaced + added).saturating_sub(self.max_bytes);
+        if needed == 0 {
+            return Ok(());
+        }
+
+        if self.eviction == Eviction::Reject || added > self.max_bytes {
+            return Err(BridgeError::Kind(
+                "CacheFull",
+                json!({
+                    "max_bytes": self.max_bytes,
+                    "used_bytes": usage.bytes,
+                    "requested_bytes": added,
+                    "eviction": self.eviction,
+                }),
+            ));
+        }
+
+        let mut freed = 0;
+        let oldest: Vec<CacheKey> = usage.by_use.values().cloned().collect();
+        for key in oldest {
+            if freed >= needed {
+                break;
+            }
+            if plan.changes.contains_key(&key) {
+                continue;
+            }
+            commands::call(&mut strata.executor(), key.command("KvDelete"))?;
+            freed += usage.size_of(&key);
+            usage.remove(&key);
+            usage.evictions += 1;
+        }
+        Ok(())
+    }
+}
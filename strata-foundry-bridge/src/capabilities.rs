@@ -0,0 +1,32 @@
+//! Features of the linked stratadb build (`strata_capabilities`).
+//!
+//! stratadb has no feature query, so each capability is probed once by
+//! running a representative command against a throwaway in-memory database.
+//! stratadb has no encryption commands at all, so `encryption` is always false.
+
+use std::sync::LazyLock;
+
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use crate::commands;
+
+static CAPABILITIES: LazyLock<Value> = LazyLock::new(probe);
+
+/// `{"vectors", "branches", "encryption", "time_travel"}`, each a bool.
+pub fn capabilities() -> &'static Value {
+    &CAPABILITIES
+}
+
+fn probe() -> Value {
+    let strata = Strata::cache().ok();
+    let supports = |command: Value| {
+        strata.as_ref().is_some_and(|s| commands::call(&mut s.executor(), command).is_ok())
+    };
+    json!({
+        "vectors": supports(json!({ "VectorListCollections": {} })),
+        "branches": supports(json!({ "BranchList": {} })),
+        "encryption": false,
+        "time_travel": supports(json!({ "KvGet": { "key": "capabilities", "as_of": 1 } })),
+    })
+}
@@ -0,0 +1,186 @@
+//! Coalesced `StateSet`s for high-frequency cells (`strata_set_state_coalesce`).
+//!
+//! A `StateSet` to a coalesced cell on the default branch and space is held
+//! in memory instead of written; a background thread writes the latest
+//! held value once per interval, and closing the handle writes any value
+//! still held. `StateGet` of the cell returns the held value, so readers see
+//! the last set whether or not it has been written yet. Any other state
+//! command, bridge command or whole-database read writes held values first,
+//! as do exports, snapshots and transactions (`HandleRegistry::flush_coalesced`),
+//! so none of them sees a stale value.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use crate::commands::{self, version};
+use crate::error::BridgeError;
+use crate::handle::unix_millis;
+use crate::limits::Limits;
+
+/// stratadb commands outside the `State` family that read state cells.
+const READS_STATE_TAGS: &[&str] =
+    &["BranchExport", "BranchDiff", "BranchMerge", "BranchFork", "Search", "TimeRange"];
+
+/// Whether a command tagged `tag` may read or write a coalesced cell's value.
+fn touches_state(tag: &str) -> bool {
+    tag.starts_with("State") || READS_STATE_TAGS.contains(&tag) || commands::is_bridge_command(tag)
+}
+
+/// One coalesced cell.
+struct Cell {
+    /// Distinguishes this registration from an earlier one of the same
+    /// cell, whose timer thread should stop.
+    generation: u64,
+    /// The latest set not yet written, and when it was set (microseconds).
+    pending: Option<(Value, u64)>,
+    /// Version of the last write, reported for held sets.
+    version: Option<u64>,
+    sets: u64,
+    writes: u64,
+}
+
+/// A handle's coalesced cells.
+#[derive(Default)]
+pub struct Coalescer {
+    cells: Mutex<HashMap<String, Cell>>,
+    next_generation: AtomicU64,
+}
+
+impl Coalescer {
+    /// Coalesce sets to `cell`. Returns the registration's generation for
+    /// its timer thread to pass to `flush_cell`.
+    pub fn enable(&self, cell: &str) -> u64 {
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let mut cells = self.cells.lock().unwrap_or_else(|e| e.into_inner());
+        let cell = cells.entry(cell.to_string()).or_insert(Cell {
+            generation,
+            pending: None,
+            version: None,
+            sets: 0,
+            writes: 0,
+        });
+        cell.generation = generation;
+        generation
+    }
+
+    /// Stop coalescing `cell`, writing any value it holds.
+    pub fn disable(&self, strata: &Strata, cell: &str) -> Result<(), BridgeError> {
+        let mut cells = self.cells.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = cells.get_mut(cell) {
+            write_pending(strata, cell, state)?;
+        }
+        cells.remove(cell);
+        Ok(())
+    }
+
+    /// Handle a state command against the coalesced cells. Returns its
+    /// output if it was answered from memory, or `None` to run it as usual.
+    pub fn intercept(
+        &self,
+        strata: &Strata,
+        command_json: &str,
+        limits: Limits,
+    ) -> Result<Option<String>, BridgeError> {
+        if !commands::peek_tag(command_json).is_some_and(touches_state) {
+            return Ok(None);
+        }
+        let mut cells = self.cells.lock().unwrap_or_else(|e| e.into_inner());
+        if cells.is_empty() {
+            return Ok(None);
+        }
+        let command: Value =
+            serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+        let Some((tag, body)) = command.as_object().and_then(|m| m.iter().next()) else {
+            return Ok(None);
+        };
+        let scoped = !body["branch"].is_null() || !body["space"].is_null();
+        let cell = body["cell"].as_str().filter(|_| !scoped).and_then(|c| cells.get_mut(c));
+
+        match (tag.as_str(), cell) {
+            ("StateSet", Some(cell)) => {
+                limits.check_command(&command)?;
+                let set_at = unix_millis() * 1000;
+                cell.pending = Some((body["value"].clone(), set_at));
+                cell.sets += 1;
+                Ok(Some(json!({ "Version": cell.version.unwrap_or_default() }).to_string()))
+            }
+            ("StateGet", Some(cell)) if body["as_of"].is_null() => {
+                let Some((value, set_at)) = &cell.pending else {
+                    return Ok(None);
+                };
+                let output = json!({ "MaybeVersioned": {
+                    "value": value,
+                    "version": cell.version.unwrap_or_default(),
+                    "timestamp": set_at,
+                }});
+                Ok(Some(output.to_string()))
+            }
+            _ => {
+                for (name, cell) in cells.iter_mut() {
+                    write_pending(strata, name, cell)?;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Write `cell`'s held value, for its timer thread. Returns false once
+    /// the registration with `generation` is gone, ending the thread.
+    pub fn flush_cell(&self, strata: &Strata, cell: &str, generation: u64) -> bool {
+        let mut cells = self.cells.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(state) = cells.get_mut(cell).filter(|c| c.generation == generation) else {
+            return false;
+        };
+        if let Err(e) = write_pending(strata, cell, state) {
+            crate::log::warn(&format!("coalesced write of {cell} failed: {}", e.to_json()));
+        }
+        true
+    }
+
+    /// Write every held value, as the handle closes.
+    pub fn flush_all(&self, strata: &Strata) -> Result<(), BridgeError> {
+        let mut cells = self.cells.lock().unwrap_or_else(|e| e.into_inner());
+        for (name, cell) in cells.iter_mut() {
+            write_pending(strata, name, cell)?;
+        }
+        Ok(())
+    }
+
+    /// `{"<cell>": {"sets", "writes", "pending"}}`
+    pub fn stats(&self) -> Value {
+        let cells = self.cells.lock().unwrap_or_else(|e| e.into_inner());
+        let stats: serde_json::Map<String, Value> = cells
+            .iter()
+            .map(|(name, cell)| {
+                let pending = cell.pending.is_some();
+                (name.clone(), json!({ "sets": cell.sets, "writes": cell.writes, "pending": pending }))
+            })
+            .collect();
+        Value::Object(stats)
+    }
+}
+
+/// Write a cell's held value, if any, while the cells are locked so a
+/// newer set cannot be overwritten by an older one.
+fn write_pending(strata: &Strata, name: &str, cell: &mut Cell) -> Result<(), BridgeError> {
+    let Some((value, set_at)) = cell.pending.take() else {
+        return Ok(());
+    };
+    let set = json!({ "StateSet": { "cell": name, "value": value.clone() } });
+    match commands::call(&mut strata.executor(), set) {
+        Ok(output) => {
+            cell.version = version(&output).or(cell.version);
+            cell.writes += 1;
+            Ok(())
+        }
+        Err(e) => {
+            // Keep it for the next attempt unless a newer set replaces it.
+            cell.pending = Some((value, set_at));
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! `BranchTree` — branches nested under their parents.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use super::call;
+use crate::error::BridgeError;
+
+/// The branch every tree is rooted at.
+const ROOT: &str = "default";
+
+#[derive(Deserialize)]
+pub(crate) struct TreeArgs {}
+
+/// `BranchTree {}` — every branch as a tree of `{"id", "created_at",
+/// "children": [...]}` nodes rooted at the default branch, children ordered
+/// by id.
+///
+/// A branch whose parent is unset or no longer exists is attached to the
+/// root with `"orphaned": true`, as is any branch only reachable through a
+/// parent cycle.
+pub(crate) fn tree(strata: &Strata, _args: TreeArgs) -> Result<Value, BridgeError> {
+    let output = call(&mut strata.executor(), json!({ "BranchList": {} }))?;
+    let mut created_at = BTreeMap::new();
+    let mut parents = BTreeMap::new();
+    for branch in output["BranchInfoList"].as_array().into_iter().flatten() {
+        let info = &branch["info"];
+        let Some(id) = info["id"].as_str() else {
+            continue;
+        };
+        created_at.insert(id, info["created_at"].clone());
+        parents.insert(id, info["parent_id"].as_str());
+    }
+    let mut children: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (&id, parent) in &parents {
+        if let Some(parent) = parent.filter(|_| id != ROOT) {
+            children.entry(parent).or_default().push(id);
+        }
+    }
+
+    let tree = Tree { created_at: &created_at, children: &children };
+    let mut visited = BTreeSet::new();
+    let mut root = tree.node(ROOT, &mut visited);
+    let detached = |id: &&str| {
+        *id != ROOT && parents[id].is_none_or(|parent| !parents.contains_key(parent))
+    };
+    let orphans: Vec<&str> = parents.keys().copied().filter(detached).collect();
+    // Then whatever a cycle kept out of reach.
+    let unreached: Vec<&str> = parents.keys().copied().collect();
+    for id in orphans.into_iter().chain(unreached) {
+        if !visited.contains(id) {
+            let mut orphan = tree.node(id, &mut visited);
+            orphan["orphaned"] = json!(true);
+            if let Some(children) = root["children"].as_array_mut() {
+                children.push(orphan);
+            }
+        }
+    }
+    Ok(root)
+}
+
+struct Tree<'a> {
+    created_at: &'a BTreeMap<&'a str, Value>,
+    children: &'a BTreeMap<&'a str, Vec<&'a str>>,
+}
+
+impl Tree<'_> {
+    /// `id`'s node with its children not yet visited.
+    fn node<'a>(&'a self, id: &'a str, visited: &mut BTreeSet<&'a str>) -> Value {
+        visited.insert(id);
+        let mut children = Vec::new();
+        for &child in self.children.get(id).into_iter().flatten() {
+            if !visited.contains(child) {
+                children.push(self.node(child, visited));
+            }
+        }
+        let created_at = self.created_at.get(id).cloned().unwrap_or(Value::Null);
+        json!({ "id": id, "created_at": created_at, "children": children })
+    }
+}
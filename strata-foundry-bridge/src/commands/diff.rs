@@ -0,0 +1,128 @@
+//! `StateDiffBetween` — what a primitive looked like after one event versus another —
+//! and `ChangesSince`, the keys written after an event.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::{Executor, Strata};
+
+use super::{call, maybe_versioned, scan, Scope};
+use crate::capabilities;
+use crate::error::BridgeError;
+
+#[derive(Deserialize)]
+pub(crate) struct BetweenArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    from: u64,
+    to: u64,
+    primitive: String,
+}
+
+/// `StateDiffBetween {"from": A, "to": B, "primitive": "kv"}` — the changes
+/// to a primitive between event sequences `A` and `B`:
+/// `{"added": [{"key", "new"}], "changed": [{"key", "old", "new"}], "removed": [{"key", "old"}]}`.
+///
+/// Each side is read as of its event's timestamp, so it includes every
+/// write made up to and including that event. `primitive` is `kv`, `state`
+/// or `json`. Keys are listed in order.
+pub(crate) fn between(strata: &Strata, args: BetweenArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let before = snapshot(&mut executor, &args.scope, args.from, &args.primitive)?;
+    let mut after = snapshot(&mut executor, &args.scope, args.to, &args.primitive)?;
+
+    let (mut changed, mut removed) = (Vec::new(), Vec::new());
+    for (key, old) in before {
+        match after.remove(&key) {
+            Some(new) if new != old => changed.push(json!({ "key": key, "old": old, "new": new })),
+            Some(_) => {}
+            None => removed.push(json!({ "key": key, "old": old })),
+        }
+    }
+    let added: Vec<Value> =
+        after.into_iter().map(|(key, new)| json!({ "key": key, "new": new })).collect();
+
+    Ok(json!({ "added": added, "changed": changed, "removed": removed }))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SinceArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    sequence: u64,
+    #[serde(default = "all_primitives")]
+    primitives: Vec<String>,
+}
+
+fn all_primitives() -> Vec<String> {
+    ["kv", "json", "state"].map(String::from).to_vec()
+}
+
+/// `ChangesSince {"sequence": N, "primitives": ["kv", "json", "state"]}` —
+/// the keys written or deleted after event `N`, per primitive:
+/// `{"kv": {"changed": [{"key", "value"}], "removed": [key]}, ...}`.
+///
+/// stratadb has no change feed, so each primitive as of event `N`'s
+/// timestamp is compared with its current content; a key rewritten with the
+/// same value is not reported. `primitives` defaults to all three. Fails
+/// with `Unsupported` if the linked stratadb cannot read as of a timestamp.
+pub(crate) fn since(strata: &Strata, args: SinceArgs) -> Result<Value, BridgeError> {
+    if capabilities::capabilities()["time_travel"] != true {
+        return Err(BridgeError::Kind(
+            "Unsupported",
+            json!({ "reason": "this stratadb build cannot read as of a timestamp" }),
+        ));
+    }
+    let mut executor = strata.executor();
+    let mut result = serde_json::Map::new();
+    for primitive in &args.primitives {
+        let mut before = snapshot(&mut executor, &args.scope, args.sequence, primitive)?;
+        let mut changed = Vec::new();
+        for (key, value) in entries(&mut executor, &args.scope, primitive)? {
+            if before.remove(&key).as_ref() != Some(&value) {
+                changed.push(json!({ "key": key, "value": value }));
+            }
+        }
+        let removed: Vec<String> = before.into_keys().collect();
+        result.insert(primitive.clone(), json!({ "changed": changed, "removed": removed }));
+    }
+    Ok(Value::Object(result))
+}
+
+/// A primitive's content as of event `sequence`'s timestamp, which includes
+/// every write made up to and including that event.
+fn snapshot(
+    executor: &mut Executor,
+    scope: &Scope,
+    sequence: u64,
+    primitive: &str,
+) -> Result<BTreeMap<String, Value>, BridgeError> {
+    let get = scope.command("EventGet", json!({ "sequence": sequence }));
+    let timestamp = maybe_versioned(call(executor, get)?)
+        .and_then(|record| record["timestamp"].as_u64())
+        .ok_or_else(|| {
+            BridgeError::Kind(
+                "InvalidInput",
+                json!({ "reason": "no event at sequence", "sequence": sequence }),
+            )
+        })?;
+    Ok(entries(executor, &scope.at(timestamp), primitive)?.into_iter().collect())
+}
+
+/// Every entry of `primitive` (`kv`, `state` or `json`), sorted by key.
+fn entries(
+    executor: &mut Executor,
+    scope: &Scope,
+    primitive: &str,
+) -> Result<Vec<(String, Value)>, BridgeError> {
+    match primitive {
+        "kv" => scan::kv(executor, scope),
+        "state" => scan::state(executor, scope),
+        "json" => scan::json(executor, scope),
+        other => {
+            let reason = format!("unsupported primitive {other:?}: expected kv, state or json");
+            Err(BridgeError::Kind("InvalidInput", json!({ "reason": reason })))
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Database content digest.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest as _, Sha256};
+use stratadb::Strata;
+
+use super::{scan, Scope};
+use crate::error::BridgeError;
+
+const PRIMITIVES: &[&str] = &["events", "json", "kv", "state"];
+
+fn all_primitives() -> Vec<String> {
+    PRIMITIVES.iter().map(|p| p.to_string()).collect()
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DigestArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    #[serde(default = "all_primitives")]
+    primitives: Vec<String>,
+}
+
+/// `Digest {"primitives": ["kv", "json", "state", "events"]}` — a SHA-256
+/// fingerprint of the selected primitives' logical content.
+///
+/// Only keys, values and event types are hashed — not versions or
+/// timestamps — so two databases holding the same data produce the same
+/// digest however it was written. Keys are hashed in sorted order; events in
+/// sequence order. The primitives are read one after another, not under a
+/// single snapshot.
+pub(crate) fn digest(strata: &Strata, args: DigestArgs) -> Result<Value, BridgeError> {
+    let mut primitives = args.primitives;
+    if let Some(unknown) = primitives.iter().find(|p| !PRIMITIVES.contains(&p.as_str())) {
+        return Err(BridgeError::Kind(
+            "InvalidInput",
+            json!({ "reason": format!("unknown primitive '{unknown}', expected one of {PRIMITIVES:?}") }),
+        ));
+    }
+    primitives.sort();
+    primitives.dedup();
+
+    let scope = &args.scope;
+    let mut executor = strata.executor();
+    let mut hasher = Sha256::new();
+    for primitive in &primitives {
+        update(&mut hasher, primitive.as_bytes());
+        match primitive.as_str() {
+            "events" => {
+                for event in scan::events(&mut executor, scope)? {
+                    update(&mut hasher, canonical(&event).as_bytes());
+                }
+            }
+            name => {
+                let entries = match name {
+                    "json" => scan::json(&mut executor, scope)?,
+                    "kv" => scan::kv(&mut executor, scope)?,
+                    _ => scan::state(&mut executor, scope)?,
+                };
+                for (key, value) in entries {
+                    update(&mut hasher, key.as_bytes());
+                    update(&mut hasher, canonical(&value).as_bytes());
+                }
+            }
+        }
+    }
+
+    let digest: String = hasher.finalize().iter().map(|b| format!("{b:02x}")).collect();
+    Ok(json!({ "digest": digest, "algorithm": "sha256", "primitives": primitives }))
+}
+
+/// Hash a length-prefixed field, so adjacent fields cannot run together.
+fn update(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// JSON text with object keys sorted, so field order never affects the digest.
+fn canonical(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<_> = map.iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(b.0));
+            let body: Vec<String> = fields
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::from(k.as_str()), canonical(v)))
+                .collect();
+            format!("{{{}}}", body.join(","))
+        }
+        Value::Array(items) => {
+            let body: Vec<String> = items.iter().map(canonical).collect();
+            format!("[{}]", body.join(","))
+        }
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,195 @@
+//! Human-readable JSON dumps of a whole database.
+//!
+//! A dump is one JSON object with a section per primitive, holding values in
+//! stratadb's tagged `Value` encoding so types survive a round trip:
+//!
+//! ```json
+//! {"branches": [...], "events": [...], "json": {...}, "kv": {...}, "state": {...}}
+//! ```
+//!
+//! Sections cover the default branch; `branches` lists branch names only.
+//! Map keys are emitted in sorted order so dumps diff cleanly.
+
+use serde_json::{json, Map, Value};
+use stratadb::Strata;
+
+use super::{call, scan, Scope};
+use crate::error::BridgeError;
+use crate::log;
+
+/// Read every primitive into a dump document.
+pub(crate) fn export(strata: &Strata) -> Result<Value, BridgeError> {
+    let mut dump = Map::new();
+    for section in SECTIONS {
+        dump.insert(section.to_string(), export_primitive(strata, section)?);
+    }
+    Ok(Value::Object(dump))
+}
+
+/// Primitives `export_primitive` takes: the dump sections, plus `vectors`.
+pub(crate) const PRIMITIVES: &[&str] = &["branches", "events", "json", "kv", "state", "vectors"];
+
+/// Read one primitive as it appears in a dump. `vectors`, which dumps leave
+/// out, is `{"<collection>": {"dimension", "metric", "vectors": {...}}}`.
+pub(crate) fn export_primitive(strata: &Strata, primitive: &str) -> Result<Value, BridgeError> {
+    let scope = Scope::default();
+    let mut executor = strata.executor();
+    Ok(match primitive {
+        "branches" => {
+            let mut branches: Vec<String> = call(&mut executor, json!({ "BranchList": {} }))?
+                ["BranchInfoList"]
+                .as_array()
+                .map(|list| {
+                    list.iter()
+                        .filter_map(|b| b["info"]["id"].as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            branches.sort();
+            json!(branches)
+        }
+        "events" => json!(scan::events(&mut executor, &scope)?),
+        "json" => Value::Object(to_map(scan::json(&mut executor, &scope)?)),
+        "kv" => Value::Object(to_map(scan::kv(&mut executor, &scope)?)),
+        "state" => Value::Object(to_map(scan::state(&mut executor, &scope)?)),
+        "vectors" => Value::Object(to_map(scan::vectors(&mut executor, &scope)?)),
+        _ => {
+            let reason = json!({
+                "reason": "unknown primitive",
+                "primitive": primitive,
+                "expected": PRIMITIVES,
+            });
+            return Err(BridgeError::Kind("InvalidInput", reason));
+        }
+    })
+}
+
+fn to_map(entries: Vec<(String, Value)>) -> Map<String, Value> {
+    entries.into_iter().collect()
+}
+
+/// How `import` treats data already in the database.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImportMode {
+    /// Overlay the dump: its entries overwrite, everything else is kept.
+    Merge,
+    /// Delete every KV key, state cell and JSON document first.
+    Replace,
+}
+
+impl ImportMode {
+    pub(crate) fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "merge" => Some(Self::Merge),
+            "replace" => Some(Self::Replace),
+            _ => None,
+        }
+    }
+}
+
+const SECTIONS: &[&str] = &["branches", "events", "json", "kv", "state"];
+
+/// Load a dump written by `export` into the database.
+///
+/// Missing branches are created first; they are never deleted. The data
+/// sections are then written in one transaction, so a failure leaves the
+/// database unchanged. The event log is append-only: events are appended
+/// after any existing ones, even in `Replace` mode. Unknown top-level
+/// sections are skipped and reported under `ignored`.
+pub(crate) fn import(strata: &Strata, dump: &Value, mode: ImportMode) -> Result<Value, BridgeError> {
+    let sections = dump
+        .as_object()
+        .ok_or_else(|| invalid_input("dump must be a JSON object"))?;
+    let ignored: Vec<&String> = sections
+        .keys()
+        .filter(|name| !SECTIONS.contains(&name.as_str()))
+        .collect();
+    for name in &ignored {
+        log::warn(&format!("import: ignoring unknown section '{name}'"));
+    }
+
+    let mut executor = strata.executor();
+    let mut created = 0;
+    for branch in section_array(dump, "branches")? {
+        let name = branch
+            .as_str()
+            .ok_or_else(|| invalid_input("branches must be strings"))?;
+        let exists = call(&mut executor, json!({ "BranchExists": { "branch": name } }))?;
+        if exists["Bool"] != true {
+            call(&mut executor, json!({ "BranchCreate": { "branch_id": name } }))?;
+            created += 1;
+        }
+    }
+
+    let scope = Scope::default();
+    let counts = super::in_transaction(strata, None, |txn| {
+        if mode == ImportMode::Replace {
+            for (key, _) in scan::kv(txn, &scope)? {
+                call(txn, json!({ "KvDelete": { "key": key } }))?;
+            }
+            for (cell, _) in scan::state(txn, &scope)? {
+                call(txn, json!({ "StateDelete": { "cell": cell } }))?;
+            }
+            for (key, _) in scan::json(txn, &scope)? {
+                call(txn, json!({ "JsonDelete": { "key": key, "path": "$" } }))?;
+            }
+        }
+
+        let kv = section_map(dump, "kv")?;
+        for (key, value) in kv {
+            call(txn, json!({ "KvPut": { "key": key, "value": value } }))?;
+        }
+        let state = section_map(dump, "state")?;
+        for (cell, value) in state {
+            call(txn, json!({ "StateSet": { "cell": cell, "value": value } }))?;
+        }
+        let docs = section_map(dump, "json")?;
+        for (key, value) in docs {
+            call(txn, json!({ "JsonSet": { "key": key, "path": "$", "value": value } }))?;
+        }
+        let events = section_array(dump, "events")?;
+        for event in events {
+            let event_type = event["event_type"]
+                .as_str()
+                .ok_or_else(|| invalid_input("events need a string event_type"))?;
+            call(
+                txn,
+                json!({ "EventAppend": { "event_type": event_type, "payload": event["value"] } }),
+            )?;
+        }
+        Ok(json!({
+            "kv": kv.len(),
+            "state": state.len(),
+            "events": events.len(),
+            "json": docs.len(),
+        }))
+    })?;
+
+    let mut result = counts;
+    result["branches"] = json!(created);
+    result["ignored"] = json!(ignored);
+    Ok(result)
+}
+
+/// An object section of the dump; absent sections are empty.
+fn section_map<'a>(dump: &'a Value, name: &str) -> Result<&'a Map<String, Value>, BridgeError> {
+    static EMPTY: std::sync::LazyLock<Map<String, Value>> = std::sync::LazyLock::new(Map::new);
+    match dump.get(name) {
+        None | Some(Value::Null) => Ok(&EMPTY),
+        Some(Value::Object(map)) => Ok(map),
+        Some(_) => Err(invalid_input(&format!("section '{name}' must be an object"))),
+    }
+}
+
+/// An array section of the dump; absent sections are empty.
+fn section_array<'a>(dump: &'a Value, name: &str) -> Result<&'a [Value], BridgeError> {
+    match dump.get(name) {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(Value::Array(items)) => Ok(items),
+        Some(_) => Err(invalid_input(&format!("section '{name}' must be an array"))),
+    }
+}
+
+fn invalid_input(reason: &str) -> BridgeError {
+    BridgeError::Kind("InvalidInput", json!({ "reason": reason }))
+}
@@ -0,0 +1,111 @@
+//! Bridge-level event log commands.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use super::{call, maybe_versioned, Scope};
+use crate::error::BridgeError;
+
+#[derive(Deserialize)]
+pub(crate) struct StatsArgs {
+    #[serde(flatten)]
+    scope: Scope,
+}
+
+/// `EventStats {}` — count events per kind, e.g.
+/// `{"kinds": [{"kind": "tool_call", "count": 8}], "total": 20, "min_sequence": 0, "max_sequence": 19}`.
+///
+/// Events are read one at a time and only their type is kept, so memory
+/// stays flat however large the log is. Kinds are ordered by name; the
+/// sequence bounds are null for an empty log.
+pub(crate) fn stats(strata: &Strata, args: StatsArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let len = call(&mut executor, args.scope.command("EventLen", json!({})))?["Uint"]
+        .as_u64()
+        .unwrap_or_default();
+
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    let (mut total, mut min_sequence, mut max_sequence) = (0u64, None, None);
+    for sequence in 0..len {
+        let output =
+            call(&mut executor, args.scope.command("EventGet", json!({ "sequence": sequence })))?;
+        let Some(record) = maybe_versioned(output) else {
+            continue;
+        };
+        let kind = record["event_type"].as_str().unwrap_or_default();
+        *counts.entry(kind.to_string()).or_default() += 1;
+        total += 1;
+        min_sequence.get_or_insert(sequence);
+        max_sequence = Some(sequence);
+    }
+
+    let kinds: Vec<Value> = counts
+        .into_iter()
+        .map(|(kind, count)| json!({ "kind": kind, "count": count }))
+        .collect();
+    Ok(json!({
+        "kinds": kinds,
+        "total": total,
+        "min_sequence": min_sequence,
+        "max_sequence": max_sequence,
+    }))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct KindsArgs {
+    #[serde(flatten)]
+    scope: Scope,
+}
+
+/// `EventKinds {}` — the distinct event kinds in the log, sorted:
+/// `["auth", "decision", "error", ...]`.
+///
+/// stratadb keeps no index or set of event types, so this scans the whole
+/// log like `EventStats`: one `EventGet` per event.
+pub(crate) fn kinds(strata: &Strata, args: KindsArgs) -> Result<Value, BridgeError> {
+    let stats = stats(strata, StatsArgs { scope: args.scope })?;
+    let kinds: Vec<Value> = stats["kinds"]
+        .as_array()
+        .map(|kinds| kinds.iter().map(|k| k["kind"].clone()).collect())
+        .unwrap_or_default();
+    Ok(Value::Array(kinds))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LastArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    kind: Option<String>,
+}
+
+/// `EventLast {"kind": "error"}` — the highest-sequence event of `kind`, or
+/// the last event of any kind without one, as
+/// `{"sequence", "event_type", "value", "timestamp"}`; null if none match.
+///
+/// Reads backwards from the tail and stops at the first match.
+pub(crate) fn last(strata: &Strata, args: LastArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let len = call(&mut executor, args.scope.command("EventLen", json!({})))?["Uint"]
+        .as_u64()
+        .unwrap_or_default();
+
+    for sequence in (0..len).rev() {
+        let output =
+            call(&mut executor, args.scope.command("EventGet", json!({ "sequence": sequence })))?;
+        let Some(record) = maybe_versioned(output) else {
+            continue;
+        };
+        if args.kind.as_deref().is_none_or(|kind| record["event_type"] == kind) {
+            return Ok(json!({
+                "sequence": sequence,
+                "event_type": record["event_type"],
+                "value": record["value"],
+                "timestamp": record["timestamp"],
+            }));
+        }
+    }
+    Ok(Value::Null)
+}
@@ -0,0 +1,81 @@
+//! Command plans — a rough preview of what a command will do, without running it.
+
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use super::{call, is_bridge_command, Scope};
+use crate::error::BridgeError;
+
+/// Vector index types that are a linear scan rather than an index.
+const FLAT_INDEX_TYPES: &[&str] = &["brute_force", "flat", "none"];
+
+/// Describe how `command` (`{"Tag": {...}}`) would execute:
+/// `{"command", "estimated_rows", "uses_index", "notes"}`.
+///
+/// Estimates come from collection and log metadata, never from running the
+/// command. Commands without a meaningful plan get just their tag, with
+/// `estimated_rows` and `uses_index` null.
+pub(crate) fn explain(strata: &Strata, command: &Value) -> Result<Value, BridgeError> {
+    let (tag, body) = match command {
+        Value::Object(map) if map.len() == 1 => map.iter().next().expect("one entry"),
+        _ => return Err("invalid command JSON: expected a single tagged command".into()),
+    };
+    let scope: Scope = serde_json::from_value(body.clone()).unwrap_or_default();
+    let mut executor = strata.executor();
+
+    let (estimated_rows, uses_index, notes): (Option<u64>, Option<bool>, Vec<String>) =
+        match tag.as_str() {
+            "VectorSearch" => {
+                let collection = body["collection"].as_str().unwrap_or_default();
+                let k = body["k"].as_u64().unwrap_or(10);
+                let stats = call(
+                    &mut executor,
+                    scope.command("VectorCollectionStats", json!({ "collection": collection })),
+                )?;
+                let info = &stats["VectorCollectionList"][0];
+                let count = info["count"].as_u64().unwrap_or_default();
+                let index_type = info["index_type"].as_str().unwrap_or("unknown");
+                let indexed = !FLAT_INDEX_TYPES.contains(&index_type);
+                let mut notes = vec![format!(
+                    "{index_type} index over {count} vectors of dimension {}",
+                    info["dimension"]
+                )];
+                if !indexed {
+                    notes.push("every vector is compared with the query".into());
+                }
+                if !body["filter"].is_null() {
+                    notes.push("metadata filter may return fewer than k matches".into());
+                }
+                (Some(k.min(count)), Some(indexed), notes)
+            }
+            "KvGet" | "StateGet" | "JsonGet" | "EventGet" | "VectorGet" => {
+                (Some(1), Some(true), vec!["point lookup".into()])
+            }
+            "KvList" | "StateList" | "JsonList" => {
+                let mut notes = vec!["ordered key scan".into()];
+                if let Some(prefix) = body["prefix"].as_str() {
+                    notes.push(format!("limited to keys under prefix {prefix:?}"));
+                }
+                (body["limit"].as_u64(), Some(true), notes)
+            }
+            "EventGetByType" => {
+                let len = call(&mut executor, scope.command("EventLen", json!({})))?["Uint"]
+                    .as_u64()
+                    .unwrap_or_default();
+                let mut notes = vec![format!("scans up to {len} events to match the type")];
+                if let Some(limit) = body["limit"].as_u64() {
+                    notes.push(format!("stops after {limit} matches"));
+                }
+                (Some(body["limit"].as_u64().map_or(len, |l| l.min(len))), Some(false), notes)
+            }
+            tag if is_bridge_command(tag) => (None, None, vec!["bridge command".into()]),
+            _ => (None, None, Vec::new()),
+        };
+
+    Ok(json!({
+        "command": tag,
+        "estimated_rows": estimated_rows,
+        "uses_index": uses_index,
+        "notes": notes,
+    }))
+}
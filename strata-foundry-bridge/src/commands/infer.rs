@@ -0,0 +1,92 @@
+//! `KvInferSchema`: the structure of the values under a KV prefix.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use stratadb::Strata;
+
+use super::{kv, Scope};
+use crate::error::BridgeError;
+
+fn default_sample() -> usize {
+    100
+}
+
+#[derive(Deserialize)]
+pub(crate) struct InferSchemaArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    #[serde(default)]
+    prefix: String,
+    #[serde(default = "default_sample")]
+    sample: usize,
+}
+
+/// The shape observed at one position across the sampled values.
+#[derive(Default)]
+struct Shape {
+    /// Value tags seen here, e.g. `"String"`, `"Int"`, `"Object"`.
+    types: BTreeSet<String>,
+    /// How many sampled values had this position.
+    seen: usize,
+    /// How many of them were objects.
+    objects: usize,
+    /// Object fields, merged over every object seen here.
+    fields: BTreeMap<String, Shape>,
+}
+
+impl Shape {
+    fn observe(&mut self, tagged: &Value) {
+        self.seen += 1;
+        let Some((tag, inner)) = tagged.as_object().and_then(|m| m.iter().next()) else {
+            // Only "Null" is encoded as a bare string.
+            self.types.insert("Null".to_string());
+            return;
+        };
+        self.types.insert(tag.clone());
+        if let ("Object", Some(fields)) = (tag.as_str(), inner.as_object()) {
+            self.objects += 1;
+            for (name, value) in fields {
+                self.fields.entry(name.clone()).or_default().observe(value);
+            }
+        }
+    }
+
+    /// `{"types", "fields"}`, with `fields` present only if objects were seen.
+    fn render(&self) -> Value {
+        let mut out = json!({ "types": self.types });
+        if self.objects > 0 {
+            let fields: Map<String, Value> = self
+                .fields
+                .iter()
+                .map(|(name, field)| {
+                    let mut rendered = field.render();
+                    rendered["optional"] = json!(field.seen < self.objects);
+                    (name.clone(), rendered)
+                })
+                .collect();
+            out["fields"] = Value::Object(fields);
+        }
+        out
+    }
+}
+
+/// `KvInferSchema {"prefix": "user:", "sample": 100}` — sample up to
+/// `sample` values under `prefix` and merge their structure:
+/// `{"sampled": 3, "types": ["Object"],
+/// "fields": {"age": {"types": ["Int"], "optional": false}}}`.
+///
+/// Types are stratadb `Value` tags. A field missing from some sampled
+/// objects is `optional`; nested objects carry their own `fields`.
+pub(crate) fn infer_schema(strata: &Strata, args: InferSchemaArgs) -> Result<Value, BridgeError> {
+    let entries =
+        kv::sample_entries(&mut strata.executor(), &args.scope, &args.prefix, args.sample)?;
+    let mut root = Shape::default();
+    for (_, value) in &entries {
+        root.observe(value);
+    }
+    let mut out = root.render();
+    out["sampled"] = json!(entries.len());
+    Ok(out)
+}
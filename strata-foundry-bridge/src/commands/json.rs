@@ -0,0 +1,310 @@
+//! JSON document bridge commands.
+
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::{json, Map, Value};
+use stratadb::Strata;
+
+use super::{call, call_raw, in_transaction, maybe_versioned, to_plain, version, Scope};
+use crate::error::BridgeError;
+
+fn default_root() -> String {
+    "$".to_string()
+}
+
+fn default_deep() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RenameArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    from: String,
+    to: String,
+    #[serde(default)]
+    overwrite: bool,
+}
+
+/// `JsonRename {"from", "to", "overwrite": false}` — move a document to a
+/// new key in one transaction, like `KvRename`.
+///
+/// Errors with `KeyNotFound` if `from` is absent, and with `KeyExists` if `to`
+/// is present and `overwrite` is false.
+pub(crate) fn rename(strata: &Strata, args: RenameArgs) -> Result<Value, BridgeError> {
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let root = |key: &str| scope.command("JsonGet", json!({ "key": key, "path": "$" }));
+        let document = match maybe_versioned(call(txn, root(&args.from))?) {
+            Some(record) => record["value"].clone(),
+            None => return Err(BridgeError::Kind("KeyNotFound", json!({ "key": args.from }))),
+        };
+
+        let overwritten = maybe_versioned(call(txn, root(&args.to))?).is_some();
+        if overwritten && !args.overwrite {
+            return Err(BridgeError::Kind("KeyExists", json!({ "key": args.to })));
+        }
+
+        let set = json!({ "key": args.to, "path": "$", "value": document });
+        let set = call(txn, scope.command("JsonSet", set))?;
+        call(txn, scope.command("JsonDelete", json!({ "key": args.from, "path": "$" })))?;
+
+        Ok(json!({ "version": version(&set), "overwritten": overwritten }))
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct MergeArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key: String,
+    #[serde(default = "default_root")]
+    path: String,
+    value: Value,
+    #[serde(default = "default_deep")]
+    deep: bool,
+}
+
+/// `JsonMerge {"key", "path": "$", "value": {"Object": {...}}, "deep": true}` —
+/// merge an object into the document at `path` in one transaction.
+///
+/// Merge rule: fields in `value` overwrite fields in the document. With
+/// `deep`, nested objects present on both sides are merged recursively;
+/// without it, each top-level field is replaced wholesale. Arrays and scalars
+/// always replace — arrays are never concatenated. A missing document (or
+/// path) is treated as an empty object.
+pub(crate) fn merge(strata: &Strata, args: MergeArgs) -> Result<Value, BridgeError> {
+    let patch = object_fields(&args.value)
+        .ok_or_else(|| invalid_input("JsonMerge value must be an Object"))?
+        .clone();
+
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let current = call(
+            txn,
+            scope.command("JsonGet", json!({ "key": args.key, "path": args.path })),
+        )?;
+        let mut target = match maybe_versioned(current) {
+            Some(record) => record["value"].clone(),
+            None => json!({ "Object": {} }),
+        };
+        let fields = target
+            .get_mut("Object")
+            .and_then(Value::as_object_mut)
+            .ok_or_else(|| invalid_input("JsonMerge target is not an Object"))?;
+
+        merge_fields(fields, patch, args.deep);
+
+        let set = call(
+            txn,
+            scope.command(
+                "JsonSet",
+                json!({ "key": args.key, "path": args.path, "value": target }),
+            ),
+        )?;
+        Ok(json!({ "version": version(&set) }))
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetInlineArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key: String,
+    #[serde(default = "default_root")]
+    path: String,
+    as_of: Option<u64>,
+}
+
+/// `JsonGetInline {"key", "path": "$"}` — read a document as plain JSON.
+///
+/// Same as `JsonGet`, but the value is the document itself rather than its
+/// tagged `Value` encoding, so Swift can decode it directly:
+/// `{"value": {...}, "version": N, "timestamp": N}`, or `null` if absent.
+pub(crate) fn get_inline(strata: &Strata, args: GetInlineArgs) -> Result<Value, BridgeError> {
+    let output = call(
+        &mut strata.executor(),
+        args.scope.command(
+            "JsonGet",
+            json!({ "key": args.key, "path": args.path, "as_of": args.as_of }),
+        ),
+    )?;
+    Ok(match maybe_versioned(output) {
+        Some(record) => json!({
+            "value": to_plain(&record["value"]),
+            "version": record["version"],
+            "timestamp": record["timestamp"],
+        }),
+        None => Value::Null,
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetPathsArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key: String,
+    paths: Vec<String>,
+    as_of: Option<u64>,
+}
+
+/// `JsonGet {"key", "paths": ["$.title", "$.summary"]}` — read several
+/// paths of one document in one call and one transaction:
+/// `{"$.title": {"String": "..."}, "$.summary": null}`.
+///
+/// A path that does not resolve, or any path of an absent document, maps
+/// to null.
+pub(crate) fn get_paths(strata: &Strata, args: GetPathsArgs) -> Result<Value, BridgeError> {
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let mut values = Map::new();
+        for path in &args.paths {
+            let get = json!({ "key": args.key, "path": path, "as_of": args.as_of });
+            let record = maybe_versioned(call(txn, scope.command("JsonGet", get))?);
+            values.insert(path.clone(), record.map_or(Value::Null, |r| r["value"].clone()));
+        }
+        Ok(Value::Object(values))
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CanonicalizeArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key: String,
+}
+
+/// `JsonCanonicalize {"key"}` — rewrite a document with every object's
+/// keys sorted and `-0.0` written as `0.0`, in one transaction:
+/// `{"changed": true, "version": N}`.
+///
+/// The document is compared as stratadb serializes it; one already
+/// canonical is left alone and reported as `{"changed": false, "version": null}`.
+/// Floats are always written in their shortest round-trip form. Errors with
+/// `KeyNotFound` if the document is absent.
+pub(crate) fn canonicalize(strata: &Strata, args: CanonicalizeArgs) -> Result<Value, BridgeError> {
+    #[derive(Deserialize)]
+    struct Stored<'a> {
+        #[serde(borrow, rename = "MaybeVersioned")]
+        record: Option<Record<'a>>,
+    }
+    #[derive(Deserialize)]
+    struct Record<'a> {
+        #[serde(borrow)]
+        value: &'a RawValue,
+    }
+
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let get = scope.command("JsonGet", json!({ "key": args.key, "path": "$" }));
+        let output = call_raw(txn, get)?;
+        let stored: Stored = serde_json::from_str(&output)
+            .map_err(|e| format!("unexpected JsonGet output: {e}"))?;
+        let Some(record) = stored.record else {
+            return Err(BridgeError::Kind("KeyNotFound", json!({ "key": args.key })));
+        };
+        let document: Value = serde_json::from_str(record.value.get())
+            .map_err(|e| format!("unexpected JsonGet output: {e}"))?;
+        let canonical = canonical(document);
+        let text = serde_json::to_string(&canonical).unwrap_or_default();
+        if text == record.value.get() {
+            return Ok(json!({ "changed": false, "version": null }));
+        }
+
+        let set = json!({ "key": args.key, "path": "$", "value": canonical });
+        let set = call(txn, scope.command("JsonSet", set))?;
+        Ok(json!({ "changed": true, "version": version(&set) }))
+    })
+}
+
+/// `value` with object keys sorted and negative zero made positive.
+fn canonical(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut fields: Vec<(String, Value)> = fields.into_iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(fields.into_iter().map(|(k, v)| (k, canonical(v))).collect())
+        }
+        Value::Array(items) => items.into_iter().map(canonical).collect(),
+        Value::Number(n) if n.as_f64() == Some(0.0) && n.is_f64() => json!(0.0),
+        other => other,
+    }
+}
+
+fn default_catalog_limit() -> u64 {
+    100
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CatalogArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    prefix: Option<String>,
+    #[serde(default = "default_catalog_limit")]
+    limit: u64,
+}
+
+/// `JsonCatalog {"prefix": "doc:", "limit": 100}` — a summary of each
+/// document without its content: `[{"key", "bytes", "fields": [...]}]`.
+///
+/// `bytes` is the length of the document as plain JSON; `fields` are its
+/// top-level field names, empty for a document that is not an object.
+pub(crate) fn catalog(strata: &Strata, args: CatalogArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let list = args.scope.command(
+        "JsonList",
+        json!({ "prefix": args.prefix, "limit": args.limit }),
+    );
+    let output = call(&mut executor, list)?;
+    let keys = output["JsonListResult"]["keys"].as_array().cloned().unwrap_or_default();
+
+    let mut entries = Vec::new();
+    for key in keys.iter().filter_map(Value::as_str) {
+        let get = args.scope.command("JsonGet", json!({ "key": key, "path": "$" }));
+        let Some(record) = maybe_versioned(call(&mut executor, get)?) else {
+            continue;
+        };
+        let fields: Vec<&String> = object_fields(&record["value"])
+            .map(|fields| fields.keys().collect())
+            .unwrap_or_default();
+        entries.push(json!({
+            "key": key,
+            "bytes": to_plain(&record["value"]).to_string().len(),
+            "fields": fields,
+        }));
+    }
+    Ok(Value::Array(entries))
+}
+
+/// Merge `patch` into `fields` (both maps of stratadb `Value`s).
+fn merge_fields(fields: &mut Map<String, Value>, patch: Map<String, Value>, deep: bool) {
+    for (name, incoming) in patch {
+        match (deep, fields.get_mut(&name)) {
+            (true, Some(existing)) if object_fields(existing).is_some() => {
+                match incoming {
+                    Value::Object(mut tagged) if tagged.contains_key("Object") => {
+                        let nested = match tagged.remove("Object") {
+                            Some(Value::Object(nested)) => nested,
+                            _ => Map::new(),
+                        };
+                        let existing = existing["Object"].as_object_mut().expect("checked above");
+                        merge_fields(existing, nested, deep);
+                    }
+                    other => *existing = other,
+                }
+            }
+            _ => {
+                fields.insert(name, incoming);
+            }
+        }
+    }
+}
+
+/// The fields of a stratadb `{"Object": {...}}` value.
+fn object_fields(value: &Value) -> Option<&Map<String, Value>> {
+    value.get("Object").and_then(Value::as_object)
+}
+
+fn invalid_input(reason: &str) -> BridgeError {
+    BridgeError::Kind("InvalidInput", json!({ "reason": reason }))
+}
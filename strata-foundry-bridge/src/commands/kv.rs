@@ -0,0 +1,396 @@
+//! KV bridge commands.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::{Executor, Strata};
+
+use std::collections::BTreeMap;
+
+use super::{call, in_transaction, maybe_versioned, patch, scan, version, Scope};
+use crate::error::BridgeError;
+
+#[derive(Deserialize)]
+pub(crate) struct RenameArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    from: String,
+    to: String,
+    #[serde(default)]
+    overwrite: bool,
+}
+
+/// `KvRename {"from", "to", "overwrite": false}` — move a value to a new key
+/// in one transaction, so no reader sees both or neither key.
+///
+/// Errors with `KeyNotFound` if `from` is absent, and with `KeyExists` if `to`
+/// is present and `overwrite` is false.
+pub(crate) fn rename(strata: &Strata, args: RenameArgs) -> Result<Value, BridgeError> {
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let source = call(txn, scope.command("KvGet", json!({ "key": args.from })))?;
+        let value = match maybe_versioned(source) {
+            Some(record) => record["value"].clone(),
+            None => return Err(BridgeError::Kind("KeyNotFound", json!({ "key": args.from }))),
+        };
+
+        let target = call(txn, scope.command("KvGet", json!({ "key": args.to })))?;
+        let overwritten = maybe_versioned(target).is_some();
+        if overwritten && !args.overwrite {
+            return Err(BridgeError::Kind("KeyExists", json!({ "key": args.to })));
+        }
+
+        let put = call(txn, scope.command("KvPut", json!({ "key": args.to, "value": value })))?;
+        call(txn, scope.command("KvDelete", json!({ "key": args.from })))?;
+
+        Ok(json!({ "version": version(&put), "overwritten": overwritten }))
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SwapArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key_a: String,
+    key_b: String,
+    #[serde(default)]
+    allow_missing: bool,
+}
+
+/// `KvSwap {"key_a", "key_b", "allow_missing": false}` — exchange two keys'
+/// values in one transaction: `{"version": 7}`.
+///
+/// Errors with `KeyNotFound` if either key is absent, unless `allow_missing`
+/// is set: an absent key then counts as null, so the other key is deleted.
+/// `version` is null if both keys were absent.
+pub(crate) fn swap(strata: &Strata, args: SwapArgs) -> Result<Value, BridgeError> {
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let mut read = |key: &String| -> Result<Option<Value>, BridgeError> {
+            let record = maybe_versioned(call(txn, scope.command("KvGet", json!({ "key": key })))?);
+            if record.is_none() && !args.allow_missing {
+                return Err(BridgeError::Kind("KeyNotFound", json!({ "key": key })));
+            }
+            Ok(record.map(|record| record["value"].clone()))
+        };
+        let (value_a, value_b) = (read(&args.key_a)?, read(&args.key_b)?);
+
+        let mut swapped = None;
+        for (key, value) in [(&args.key_a, value_b), (&args.key_b, value_a)] {
+            match value {
+                Some(value) => {
+                    let put = scope.command("KvPut", json!({ "key": key, "value": value }));
+                    swapped = version(&call(txn, put)?);
+                }
+                None => {
+                    call(txn, scope.command("KvDelete", json!({ "key": key })))?;
+                }
+            }
+        }
+        Ok(json!({ "version": swapped }))
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetOrPutArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key: String,
+    default: Value,
+}
+
+/// `KvGetOrPut {"key", "default"}` — read a key, writing `default` first if
+/// it is absent, in one transaction: `{"value": {...}, "created": bool}`.
+pub(crate) fn get_or_put(strata: &Strata, args: GetOrPutArgs) -> Result<Value, BridgeError> {
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let existing = call(txn, scope.command("KvGet", json!({ "key": args.key })))?;
+        if let Some(record) = maybe_versioned(existing) {
+            return Ok(json!({ "value": record["value"], "created": false }));
+        }
+        let put = json!({ "key": args.key, "value": args.default });
+        call(txn, scope.command("KvPut", put))?;
+        Ok(json!({ "value": args.default, "created": true }))
+    })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct UpdateArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key: String,
+    ops: Vec<patch::Op>,
+    upsert: Option<Value>,
+}
+
+/// `KvUpdate {"key", "ops": [{"op", "path", "value"}], "upsert": null}` —
+/// apply a JSON Patch (see `patch`) to a value in one transaction:
+/// `{"value": {...}, "version": N}`.
+///
+/// Errors with `KeyNotFound` if the key is absent, unless `upsert` gives a
+/// base value to patch, and with `PatchFailed` if an op fails; nothing is
+/// written then.
+pub(crate) fn update(strata: &Strata, args: UpdateArgs) -> Result<Value, BridgeError> {
+    let scope = &args.scope;
+    in_transaction(strata, scope.branch.as_deref(), |txn| {
+        let existing = call(txn, scope.command("KvGet", json!({ "key": args.key })))?;
+        let mut value = match (maybe_versioned(existing), args.upsert) {
+            (Some(record), _) => record["value"].clone(),
+            (None, Some(base)) => base,
+            (None, None) => {
+                return Err(BridgeError::Kind("KeyNotFound", json!({ "key": args.key })));
+            }
+        };
+        patch::apply(&mut value, &args.ops)?;
+        let put = call(txn, scope.command("KvPut", json!({ "key": args.key, "value": value })))?;
+        Ok(json!({ "value": value, "version": version(&put) }))
+    })
+}
+
+/// Keys listed per `KvList` page while sampling or aggregating.
+const LIST_PAGE_SIZE: u64 = 1000;
+
+#[derive(Deserialize)]
+pub(crate) struct SampleArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    n: usize,
+    #[serde(default)]
+    prefix: String,
+}
+
+/// `KvSample {"n": 5, "prefix": "user:"}` — up to `n` entries chosen
+/// uniformly at random among the keys with `prefix`:
+/// `[{"key": "user:7", "value": {...}}]`, ordered by key.
+///
+/// Keys are listed a page at a time and reservoir-sampled, so only `n` of
+/// them are held at once. With `n` or fewer matches, all are returned.
+pub(crate) fn sample(strata: &Strata, args: SampleArgs) -> Result<Value, BridgeError> {
+    let entries = sample_entries(&mut strata.executor(), &args.scope, &args.prefix, args.n)?;
+    let entries = entries
+        .into_iter()
+        .map(|(key, value)| json!({ "key": key, "value": value }))
+        .collect();
+    Ok(Value::Array(entries))
+}
+
+/// Up to `n` random `(key, value)` entries with `prefix`, ordered by key.
+pub(crate) fn sample_entries(
+    executor: &mut Executor,
+    scope: &Scope,
+    prefix: &str,
+    n: usize,
+) -> Result<Vec<(String, Value)>, BridgeError> {
+    let mut reservoir: Vec<String> = Vec::with_capacity(n.min(LIST_PAGE_SIZE as usize));
+    let mut seen = 0usize;
+    let mut cursor: Option<String> = None;
+    loop {
+        let list = json!({ "prefix": prefix, "cursor": cursor, "limit": LIST_PAGE_SIZE });
+        let output = call(executor, scope.command("KvList", list))?;
+        let page = scan::strings(&output["Keys"]);
+        let done = (page.len() as u64) < LIST_PAGE_SIZE;
+        cursor = page.last().cloned();
+        for key in page {
+            if reservoir.len() < n {
+                reservoir.push(key);
+            } else {
+                let slot = fastrand::usize(..=seen);
+                if slot < n {
+                    reservoir[slot] = key;
+                }
+            }
+            seen += 1;
+        }
+        if done {
+            break;
+        }
+    }
+
+    reservoir.sort();
+    let mut entries = Vec::with_capacity(reservoir.len());
+    for key in reservoir {
+        // Skip keys deleted since they were listed.
+        let get = scope.command("KvGet", json!({ "key": key }));
+        if let Some(record) = maybe_versioned(call(executor, get)?) {
+            entries.push((key, record["value"].clone()));
+        }
+    }
+    Ok(entries)
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum AggregateOp {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AggregateArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    #[serde(default)]
+    prefix: String,
+    op: AggregateOp,
+}
+
+/// `KvAggregate {"prefix": "counter:", "op": "sum"}` — the sum, average,
+/// minimum, maximum or count of the numeric values with `prefix`:
+/// `{"value": 201175, "count": 3, "skipped": 0}`.
+///
+/// `Int` and `Float` values are aggregated; any other value is skipped and
+/// counted in `skipped`. A sum of `Int`s stays an integer unless it
+/// overflows; anything involving a `Float`, and every average, is a float.
+/// With no numeric values the sum and count are 0 and the rest are null.
+pub(crate) fn aggregate(strata: &Strata, args: AggregateArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let mut int_sum = Some(0i64);
+    let (mut sum, mut min, mut max) = (0.0, None::<Value>, None::<Value>);
+    let (mut count, mut skipped) = (0u64, 0u64);
+    let mut cursor: Option<String> = None;
+    loop {
+        let list = json!({ "prefix": args.prefix, "cursor": cursor, "limit": LIST_PAGE_SIZE });
+        let output = call(&mut executor, args.scope.command("KvList", list))?;
+        let page = scan::strings(&output["Keys"]);
+        let done = (page.len() as u64) < LIST_PAGE_SIZE;
+        cursor = page.last().cloned();
+        for key in page {
+            let get = args.scope.command("KvGet", json!({ "key": key }));
+            // Deleted since the listing.
+            let Some(record) = maybe_versioned(call(&mut executor, get)?) else {
+                continue;
+            };
+            let (number, int) = match &record["value"] {
+                Value::Object(tagged) => match (tagged.get("Int"), tagged.get("Float")) {
+                    (Some(int), _) => (int.clone(), int.as_i64()),
+                    (_, Some(float)) => (float.clone(), None),
+                    _ => (Value::Null, None),
+                },
+                _ => (Value::Null, None),
+            };
+            let Some(as_f64) = number.as_f64() else {
+                skipped += 1;
+                continue;
+            };
+            count += 1;
+            sum += as_f64;
+            int_sum = int_sum.zip(int).and_then(|(total, int)| total.checked_add(int));
+            if min.as_ref().and_then(Value::as_f64).is_none_or(|min| as_f64 < min) {
+                min = Some(number.clone());
+            }
+            if max.as_ref().and_then(Value::as_f64).is_none_or(|max| as_f64 > max) {
+                max = Some(number);
+            }
+        }
+        if done {
+            break;
+        }
+    }
+
+    let value = match args.op {
+        AggregateOp::Sum => int_sum.map_or_else(|| json!(sum), |sum| json!(sum)),
+        AggregateOp::Avg if count == 0 => Value::Null,
+        AggregateOp::Avg => json!(sum / count as f64),
+        AggregateOp::Min => min.unwrap_or_default(),
+        AggregateOp::Max => max.unwrap_or_default(),
+        AggregateOp::Count => json!(count),
+    };
+    Ok(json!({ "value": value, "count": count, "skipped": skipped }))
+}
+
+fn default_separator() -> String {
+    ":".to_string()
+}
+
+#[derive(Deserialize)]
+pub(crate) struct NamespacesArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    #[serde(default = "default_separator")]
+    separator: String,
+}
+
+/// `KvNamespaces {"separator": ":"}` — group keys by the text before the
+/// first separator and count each group, e.g. `[{"prefix": "user", "count": 3}]`.
+///
+/// Keys without the separator are counted under the empty prefix `""`.
+/// Groups are ordered by prefix.
+pub(crate) fn namespaces(strata: &Strata, args: NamespacesArgs) -> Result<Value, BridgeError> {
+    if args.separator.is_empty() {
+        return Err(BridgeError::Kind(
+            "InvalidInput",
+            json!({ "reason": "separator must not be empty" }),
+        ));
+    }
+
+    let keys = scan::kv_keys(&mut strata.executor(), &args.scope)?;
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for key in &keys {
+        let prefix = key.split_once(args.separator.as_str()).map_or("", |(prefix, _)| prefix);
+        *counts.entry(prefix).or_default() += 1;
+    }
+
+    let namespaces: Vec<Value> = counts
+        .into_iter()
+        .map(|(prefix, count)| json!({ "prefix": prefix, "count": count }))
+        .collect();
+    Ok(Value::Array(namespaces))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetMetaArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    key: String,
+    as_of: Option<u64>,
+}
+
+/// `KvGetMeta {"key"}` — a value with its version and when it was written:
+/// `{"value": {...}, "version": N, "timestamp": N, "updated_at": "2026-02-20T14:30:00.000000Z"}`,
+/// or `null` if absent.
+///
+/// `timestamp` is stratadb's write time in microseconds since the Unix
+/// epoch; `updated_at` is the same instant in RFC 3339 UTC.
+pub(crate) fn get_meta(strata: &Strata, args: GetMetaArgs) -> Result<Value, BridgeError> {
+    let get = args.scope.command("KvGet", json!({ "key": args.key, "as_of": args.as_of }));
+    let output = call(&mut strata.executor(), get)?;
+    Ok(match maybe_versioned(output) {
+        Some(record) => {
+            let updated_at = record["timestamp"].as_u64().map(rfc3339_micros);
+            json!({
+                "value": record["value"],
+                "version": record["version"],
+                "timestamp": record["timestamp"],
+                "updated_at": updated_at,
+            })
+        }
+        None => Value::Null,
+    })
+}
+
+/// Format microseconds since the Unix epoch as RFC 3339 UTC.
+fn rfc3339_micros(micros: u64) -> String {
+    let secs = micros / 1_000_000;
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+        micros % 1_000_000,
+    )
+}
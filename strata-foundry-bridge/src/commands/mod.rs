@@ -0,0 +1,257 @@
+//! Bridge-level commands — composite operations built on top of stratadb.
+//!
+//! These use the same externally-tagged envelope as stratadb commands
+//! (`{"KvRename": {...}}`) and are dispatched before the stratadb `Command`
+//! parse, so Swift sends them through `strata_execute` like any other command.
+//! Their output is externally tagged by the command name: `{"KvRename": {...}}`.
+
+mod branch;
+pub(crate) mod csv;
+mod diff;
+mod digest;
+pub(crate) mod dump;
+mod event;
+pub(crate) mod explain;
+mod infer;
+mod json;
+mod kv;
+mod multi;
+pub(crate) mod patch;
+mod scan;
+mod validate;
+mod vector;
+pub(crate) mod verify;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::{Command, Executor, Output, Session, Strata};
+
+use crate::error::BridgeError;
+
+/// Handler for one bridge-level command: takes the command body.
+type Handler = fn(&Strata, &Value) -> Result<Value, BridgeError>;
+
+/// Dispatch table of bridge-level commands, keyed by tag.
+const HANDLERS: &[(&str, Handler)] = &[
+    ("KvRename", |strata, body| args(body).and_then(|a| kv::rename(strata, a))),
+    ("KvSwap", |strata, body| args(body).and_then(|a| kv::swap(strata, a))),
+    ("KvUpdate", |strata, body| args(body).and_then(|a| kv::update(strata, a))),
+    ("KvGetOrPut", |strata, body| args(body).and_then(|a| kv::get_or_put(strata, a))),
+    ("KvGetMeta", |strata, body| args(body).and_then(|a| kv::get_meta(strata, a))),
+    ("KvSample", |strata, body| args(body).and_then(|a| kv::sample(strata, a))),
+    ("KvAggregate", |strata, body| args(body).and_then(|a| kv::aggregate(strata, a))),
+    ("KvInferSchema", |strata, body| args(body).and_then(|a| infer::infer_schema(strata, a))),
+    ("KvNamespaces", |strata, body| args(body).and_then(|a| kv::namespaces(strata, a))),
+    ("JsonRename", |strata, body| args(body).and_then(|a| json::rename(strata, a))),
+    ("JsonMerge", |strata, body| args(body).and_then(|a| json::merge(strata, a))),
+    ("JsonCanonicalize", |strata, body| args(body).and_then(|a| json::canonicalize(strata, a))),
+    ("JsonCatalog", |strata, body| args(body).and_then(|a| json::catalog(strata, a))),
+    ("JsonValidate", |strata, body| args(body).and_then(|a| validate::validate(strata, a))),
+    ("JsonGetInline", |strata, body| args(body).and_then(|a| json::get_inline(strata, a))),
+    ("VectorCollectionInfo", |strata, body| {
+        args(body).and_then(|a| vector::collection_info(strata, a))
+    }),
+    ("VectorSearchById", |strata, body| {
+        args(body).and_then(|a| vector::search_by_id(strata, a))
+    }),
+    ("VectorReindex", |strata, body| args(body).and_then(|a| vector::reindex(strata, a))),
+    ("BranchTree", |strata, body| args(body).and_then(|a| branch::tree(strata, a))),
+    ("MultiGet", |strata, body| args(body).and_then(|a| multi::multi_get(strata, a))),
+    ("StateDiffBetween", |strata, body| args(body).and_then(|a| diff::between(strata, a))),
+    ("ChangesSince", |strata, body| args(body).and_then(|a| diff::since(strata, a))),
+    ("EventStats", |strata, body| args(body).and_then(|a| event::stats(strata, a))),
+    ("EventKinds", |strata, body| args(body).and_then(|a| event::kinds(strata, a))),
+    ("EventLast", |strata, body| args(body).and_then(|a| event::last(strata, a))),
+    ("Digest", |strata, body| args(body).and_then(|a| digest::digest(strata, a))),
+];
+
+/// stratadb commands the bridge runs itself to add checks. Their handlers
+/// return stratadb's own output shape, so it is not wrapped in the tag.
+const INTERCEPTED: &[(&str, Handler)] = &[("VectorCreateCollection", |strata, body| {
+    args(body).and_then(|a| vector::create_collection(strata, a))
+})];
+
+/// stratadb commands the bridge extends with another form, recognized by a
+/// field stratadb does not take. Without the field the command goes to
+/// stratadb unchanged; with it, the output is tagged like a bridge command's.
+const EXTENDED: &[(&str, &str, Handler)] =
+    &[("JsonGet", "paths", |strata, body| args(body).and_then(|a| json::get_paths(strata, a)))];
+
+/// Whether `tag` is handled by the bridge rather than passed to stratadb.
+pub fn is_bridge_command(tag: &str) -> bool {
+    HANDLERS.iter().chain(INTERCEPTED).any(|(name, _)| *name == tag)
+}
+
+/// Whether commands tagged `tag` may take a bridge form (see `EXTENDED`),
+/// so their body must be inspected before handing them to stratadb.
+pub fn is_extended(tag: &str) -> bool {
+    EXTENDED.iter().any(|(name, _, _)| *name == tag)
+}
+
+/// Execute `command` if the bridge handles it.
+///
+/// Returns `None` for anything else, which the caller hands to stratadb.
+pub fn dispatch(strata: &Strata, command: &Value) -> Option<Result<Value, BridgeError>> {
+    let (tag, body) = command.as_object()?.iter().next()?;
+    if let Some((_, handler)) = HANDLERS.iter().find(|(name, _)| name == tag) {
+        return Some(handler(strata, body).map(|output| json!({ tag: output })));
+    }
+    let extended =
+        EXTENDED.iter().find(|(name, field, _)| name == tag && body.get(field).is_some());
+    if let Some((_, _, handler)) = extended {
+        return Some(handler(strata, body).map(|output| json!({ tag: output })));
+    }
+    let (_, handler) = INTERCEPTED.iter().find(|(name, _)| name == tag)?;
+    Some(handler(strata, body))
+}
+
+/// Read a command's tag without parsing the whole envelope.
+///
+/// Recognizes `{"Tag": ...}` and the bare `"Tag"` form. Returns `None` for
+/// anything else, including tags with escape sequences, so callers fall back
+/// to a full parse.
+pub fn peek_tag(command_json: &str) -> Option<&str> {
+    let rest = command_json.trim_start();
+    let (rest, bare) = match rest.strip_prefix('{') {
+        Some(rest) => (rest.trim_start(), false),
+        None => (rest, true),
+    };
+    let rest = rest.strip_prefix('"')?;
+    let end = rest.find(['"', '\\'])?;
+    let (tag, after) = rest.split_at(end);
+    let after = after.strip_prefix('"')?.trim_start();
+    let well_formed = if bare { after.is_empty() } else { after.starts_with(':') };
+    well_formed.then_some(tag)
+}
+
+/// Deserialize a command body, treating `null` as an empty object.
+fn args<T: DeserializeOwned>(body: &Value) -> Result<T, BridgeError> {
+    let body = if body.is_null() { json!({}) } else { body.clone() };
+    serde_json::from_value(body).map_err(|e| BridgeError::from(format!("invalid command JSON: {e}")))
+}
+
+/// The optional `branch`/`space` pair carried by most commands.
+#[derive(Deserialize, Default, Clone)]
+pub(crate) struct Scope {
+    branch: Option<String>,
+    space: Option<String>,
+    /// Read as of this timestamp; set with `at`, never sent by Swift.
+    #[serde(skip)]
+    as_of: Option<u64>,
+}
+
+impl Scope {
+    /// This scope reading as of `timestamp` (microseconds since the epoch).
+    pub(crate) fn at(&self, timestamp: u64) -> Scope {
+        Scope { as_of: Some(timestamp), ..self.clone() }
+    }
+
+    /// Build a stratadb command `{tag: fields}` targeting this branch/space.
+    pub(crate) fn command(&self, tag: &str, mut fields: Value) -> Value {
+        if let Some(map) = fields.as_object_mut() {
+            if let Some(branch) = &self.branch {
+                map.insert("branch".into(), json!(branch));
+            }
+            if let Some(space) = &self.space {
+                map.insert("space".into(), json!(space));
+            }
+            if let Some(as_of) = self.as_of {
+                map.insert("as_of".into(), json!(as_of));
+            }
+        }
+        json!({ tag: fields })
+    }
+}
+
+/// Anything that can run a stratadb command: the stateless executor or a session.
+pub(crate) trait Runner {
+    fn run(&mut self, cmd: Command) -> Result<Output, stratadb::Error>;
+}
+
+impl Runner for Executor {
+    fn run(&mut self, cmd: Command) -> Result<Output, stratadb::Error> {
+        self.execute(cmd)
+    }
+}
+
+impl Runner for Session {
+    fn run(&mut self, cmd: Command) -> Result<Output, stratadb::Error> {
+        self.execute(cmd)
+    }
+}
+
+/// Run a stratadb command given as JSON and return its Output as JSON.
+pub(crate) fn call(runner: &mut impl Runner, command: Value) -> Result<Value, BridgeError> {
+    serde_json::to_value(&run(runner, command)?)
+        .map_err(|e| BridgeError::from(format!("failed to serialize output: {e}")))
+}
+
+/// `call`, returning the Output as stratadb serializes it, byte for byte.
+pub(crate) fn call_raw(runner: &mut impl Runner, command: Value) -> Result<String, BridgeError> {
+    serde_json::to_string(&run(runner, command)?)
+        .map_err(|e| BridgeError::from(format!("failed to serialize output: {e}")))
+}
+
+fn run(runner: &mut impl Runner, command: Value) -> Result<Output, BridgeError> {
+    let cmd: Command = serde_json::from_value(command)
+        .map_err(|e| BridgeError::from(format!("invalid command JSON: {e}")))?;
+    runner.run(cmd).map_err(|e| {
+        // Same shape as the plain `strata_execute` error path.
+        BridgeError::from(
+            serde_json::to_string(&e)
+                .unwrap_or_else(|_| format!(r#"{{"Internal":{{"reason":"{e}"}}}}"#)),
+        )
+    })
+}
+
+/// Run `f` inside a transaction on `branch`, committing on success and
+/// rolling back if `f` fails.
+pub(crate) fn in_transaction<T>(
+    strata: &Strata,
+    branch: Option<&str>,
+    f: impl FnOnce(&mut Session) -> Result<T, BridgeError>,
+) -> Result<T, BridgeError> {
+    let mut session = strata.session();
+    call(&mut session, json!({ "TxnBegin": { "branch": branch } }))?;
+    match f(&mut session) {
+        Ok(value) => {
+            call(&mut session, json!({ "TxnCommit": null }))?;
+            Ok(value)
+        }
+        Err(e) => {
+            let _ = call(&mut session, json!({ "TxnRollback": null }));
+            Err(e)
+        }
+    }
+}
+
+/// Unwrap a `{"MaybeVersioned": ...}` output into the versioned record, if present.
+pub(crate) fn maybe_versioned(output: Value) -> Option<Value> {
+    match output {
+        Value::Object(mut map) => map.remove("MaybeVersioned").filter(|v| !v.is_null()),
+        _ => None,
+    }
+}
+
+/// Unwrap a `{"Version": N}` output.
+pub(crate) fn version(output: &Value) -> Option<u64> {
+    output.get("Version").and_then(Value::as_u64)
+}
+
+/// Convert a stratadb tagged `Value` (`{"Int": 1}`, `{"Object": {...}}`, ...)
+/// to plain JSON. `Bytes` become an array of numbers.
+pub(crate) fn to_plain(tagged: &Value) -> Value {
+    let Some((tag, inner)) = tagged.as_object().and_then(|m| m.iter().next()) else {
+        // Only "Null" is encoded as a bare string.
+        return Value::Null;
+    };
+    match (tag.as_str(), inner) {
+        ("Array", Value::Array(items)) => items.iter().map(to_plain).collect(),
+        ("Object", Value::Object(fields)) => {
+            Value::Object(fields.iter().map(|(k, v)| (k.clone(), to_plain(v))).collect())
+        }
+        // Int, Float, String, Bool and Bytes carry their plain value already.
+        _ => inner.clone(),
+    }
+}
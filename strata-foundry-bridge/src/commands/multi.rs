@@ -0,0 +1,84 @@
+//! `MultiGet` — read several primitives in one round trip.
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use stratadb::Strata;
+
+use super::{call, maybe_versioned, Scope};
+use crate::error::BridgeError;
+
+fn default_event_limit() -> u64 {
+    10
+}
+
+#[derive(Deserialize)]
+pub(crate) struct EventsSection {
+    #[serde(default = "default_event_limit")]
+    limit: u64,
+    /// Newest first instead of oldest first.
+    #[serde(default)]
+    reverse: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct MultiGetArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    kv: Option<Vec<String>>,
+    state: Option<Vec<String>>,
+    events: Option<EventsSection>,
+}
+
+/// `MultiGet {"kv": ["k"], "state": ["cell"], "events": {"limit": 5, "reverse": true}}`
+/// — read KV keys, state cells and events together.
+///
+/// Only the requested sections are returned. `kv` and `state` map each name
+/// to its `{"value", "version", "timestamp"}` record, or null if missing;
+/// `events` is a list of `{"sequence", "event_type", "value", "timestamp"}`.
+pub(crate) fn multi_get(strata: &Strata, args: MultiGetArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let mut result = Map::new();
+
+    for (section, names, tag, field) in [
+        ("kv", &args.kv, "KvGet", "key"),
+        ("state", &args.state, "StateGet", "cell"),
+    ] {
+        let Some(names) = names else {
+            continue;
+        };
+        let mut records = Map::new();
+        for name in names {
+            let output = call(&mut executor, args.scope.command(tag, json!({ field: name })))?;
+            records.insert(name.clone(), maybe_versioned(output).unwrap_or(Value::Null));
+        }
+        result.insert(section.into(), Value::Object(records));
+    }
+
+    if let Some(events) = &args.events {
+        let len = call(&mut executor, args.scope.command("EventLen", json!({})))?["Uint"]
+            .as_u64()
+            .unwrap_or_default();
+        let count = events.limit.min(len);
+        let sequences: Box<dyn Iterator<Item = u64>> = if events.reverse {
+            Box::new((len - count..len).rev())
+        } else {
+            Box::new(0..count)
+        };
+
+        let mut rows = Vec::new();
+        for sequence in sequences {
+            let get = args.scope.command("EventGet", json!({ "sequence": sequence }));
+            if let Some(record) = maybe_versioned(call(&mut executor, get)?) {
+                rows.push(json!({
+                    "sequence": sequence,
+                    "event_type": record["event_type"],
+                    "value": record["value"],
+                    "timestamp": record["timestamp"],
+                }));
+            }
+        }
+        result.insert("events".into(), Value::Array(rows));
+    }
+
+    Ok(Value::Object(result))
+}
@@ -0,0 +1,147 @@
+//! JSON Patch (RFC 6902) over stratadb's tagged `Value` encoding, for `KvUpdate`.
+//!
+//! Paths are JSON Pointers into the value's structure (`/limits/max_retries`),
+//! and op values are tagged like any other value (`{"Int": 3}`). Besides
+//! `add`, `remove`, `replace` and `test`, `increment` adds an `Int` or `Float`
+//! to a number of the same type, so a counter needs no read by the caller.
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::error::BridgeError;
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub(crate) enum Op {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Test { path: String, value: Value },
+    Increment { path: String, value: Value },
+}
+
+impl Op {
+    fn path(&self) -> &str {
+        match self {
+            Op::Add { path, .. }
+            | Op::Remove { path }
+            | Op::Replace { path, .. }
+            | Op::Test { path, .. }
+            | Op::Increment { path, .. } => path,
+        }
+    }
+}
+
+/// Apply `ops` to `target` in order. On failure, `PatchFailed` names the op
+/// by index; `target` may then be partly patched, so callers discard it.
+pub(crate) fn apply(target: &mut Value, ops: &[Op]) -> Result<(), BridgeError> {
+    for (index, op) in ops.iter().enumerate() {
+        apply_op(target, op).map_err(|reason| {
+            BridgeError::Kind(
+                "PatchFailed",
+                json!({ "index": index, "path": op.path(), "reason": reason }),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn apply_op(target: &mut Value, op: &Op) -> Result<(), &'static str> {
+    let tokens = pointer(op.path())?;
+    match op {
+        Op::Test { value, .. } => {
+            if resolve(target, &tokens).ok_or("path not found")? != value {
+                return Err("test failed: the value differs");
+            }
+        }
+        Op::Replace { value, .. } => {
+            *resolve(target, &tokens).ok_or("path not found")? = value.clone();
+        }
+        Op::Increment { value, .. } => {
+            let found = resolve(target, &tokens).ok_or("path not found")?;
+            *found = add(found, value)?;
+        }
+        Op::Add { value, .. } => {
+            let Some((last, parent)) = tokens.split_last() else {
+                *target = value.clone();
+                return Ok(());
+            };
+            match container(resolve(target, parent).ok_or("parent not found")?)? {
+                Container::Object(fields) => {
+                    fields.insert(last.clone(), value.clone());
+                }
+                Container::Array(items) => {
+                    let index = match last.as_str() {
+                        "-" => items.len(),
+                        _ => index(last).filter(|&i| i <= items.len()).ok_or("index out of range")?,
+                    };
+                    items.insert(index, value.clone());
+                }
+            }
+        }
+        Op::Remove { .. } => {
+            let (last, parent) = tokens.split_last().ok_or("cannot remove the whole value")?;
+            match container(resolve(target, parent).ok_or("parent not found")?)? {
+                Container::Object(fields) => {
+                    fields.remove(last).ok_or("path not found")?;
+                }
+                Container::Array(items) => {
+                    let index = index(last).filter(|&i| i < items.len()).ok_or("index out of range")?;
+                    items.remove(index);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split a JSON Pointer into unescaped reference tokens; `""` is the root.
+fn pointer(path: &str) -> Result<Vec<String>, &'static str> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rest = path.strip_prefix('/').ok_or("path must be a JSON Pointer starting with /")?;
+    Ok(rest.split('/').map(|t| t.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+fn index(token: &str) -> Option<usize> {
+    token.parse().ok()
+}
+
+fn resolve<'a>(node: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(node, |node, token| match container(node).ok()? {
+        Container::Object(fields) => fields.get_mut(token),
+        Container::Array(items) => items.get_mut(index(token)?),
+    })
+}
+
+enum Container<'a> {
+    Object(&'a mut Map<String, Value>),
+    Array(&'a mut Vec<Value>),
+}
+
+/// The fields or items of a tagged `Object` or `Array`.
+fn container(node: &mut Value) -> Result<Container<'_>, &'static str> {
+    let inner = node.as_object_mut().and_then(|m| m.iter_mut().next());
+    match inner {
+        Some((tag, Value::Object(fields))) if tag == "Object" => Ok(Container::Object(fields)),
+        Some((tag, Value::Array(items))) if tag == "Array" => Ok(Container::Array(items)),
+        _ => Err("path goes through a value that is not an object or array"),
+    }
+}
+
+/// `found + delta` for two tagged numbers of the same type.
+fn add(found: &Value, delta: &Value) -> Result<Value, &'static str> {
+    let mismatch = "increment needs a number and a delta of the same type";
+    match (found.get("Int"), delta.get("Int"), found.get("Float"), delta.get("Float")) {
+        (Some(a), Some(b), ..) => {
+            let sum = a.as_i64().zip(b.as_i64()).ok_or(mismatch)?;
+            Ok(json!({ "Int": sum.0.checked_add(sum.1).ok_or("increment overflows Int")? }))
+        }
+        (.., Some(a), Some(b)) => {
+            let sum = a.as_f64().zip(b.as_f64()).ok_or(mismatch)?;
+            Ok(json!({ "Float": sum.0 + sum.1 }))
+        }
+        _ => Err(mismatch),
+    }
+}
@@ -0,0 +1,171 @@
+//! Full-primitive reads shared by the whole-database bridge commands.
+//!
+//! Each scan pages through a primitive's keys and returns `(key, value)`
+//! pairs sorted by key, with values in stratadb's tagged `Value` encoding.
+
+use serde_json::{json, Value};
+
+use super::{call, maybe_versioned, Runner, Scope};
+use crate::error::BridgeError;
+
+/// Keys fetched per `KvList` / `JsonList` page.
+const PAGE_SIZE: u64 = 1000;
+
+/// Every live KV entry.
+pub(crate) fn kv(runner: &mut impl Runner, scope: &Scope) -> Result<Vec<(String, Value)>, BridgeError> {
+    let keys = kv_keys(runner, scope)?;
+    values(runner, scope, keys, |key| ("KvGet", json!({ "key": key })))
+}
+
+/// Every live KV key, in order, without reading values.
+pub(crate) fn kv_keys(runner: &mut impl Runner, scope: &Scope) -> Result<Vec<String>, BridgeError> {
+    let mut keys = Vec::new();
+    loop {
+        let cursor = keys.last().cloned();
+        let output = call(
+            runner,
+            scope.command("KvList", json!({ "cursor": cursor, "limit": PAGE_SIZE })),
+        )?;
+        let page = strings(&output["Keys"]);
+        let done = (page.len() as u64) < PAGE_SIZE;
+        keys.extend(page);
+        if done {
+            break;
+        }
+    }
+    Ok(keys)
+}
+
+/// Every live state cell.
+pub(crate) fn state(runner: &mut impl Runner, scope: &Scope) -> Result<Vec<(String, Value)>, BridgeError> {
+    let output = call(runner, scope.command("StateList", json!({})))?;
+    let cells = strings(&output["Keys"]);
+    values(runner, scope, cells, |cell| ("StateGet", json!({ "cell": cell })))
+}
+
+/// Every JSON document, read at its root.
+pub(crate) fn json(runner: &mut impl Runner, scope: &Scope) -> Result<Vec<(String, Value)>, BridgeError> {
+    let mut keys = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let output = call(
+            runner,
+            scope.command("JsonList", json!({ "cursor": cursor, "limit": PAGE_SIZE })),
+        )?;
+        let result = &output["JsonListResult"];
+        let page = strings(&result["keys"]);
+        let empty = page.is_empty();
+        keys.extend(page);
+        cursor = result["cursor"].as_str().map(String::from);
+        if cursor.is_none() || empty {
+            break;
+        }
+    }
+    values(runner, scope, keys, |key| ("JsonGet", json!({ "key": key, "path": "$" })))
+}
+
+/// Every event in sequence order, as `{"event_type", "value"}` records.
+pub(crate) fn events(runner: &mut impl Runner, scope: &Scope) -> Result<Vec<Value>, BridgeError> {
+    let len = call(runner, scope.command("EventLen", json!({})))?["Uint"]
+        .as_u64()
+        .unwrap_or_default();
+    let mut events = Vec::new();
+    for sequence in 0..len {
+        let output = call(runner, scope.command("EventGet", json!({ "sequence": sequence })))?;
+        if let Some(record) = maybe_versioned(output) {
+            events.push(json!({
+                "event_type": record["event_type"],
+                "value": record["value"],
+            }));
+        }
+    }
+    Ok(events)
+}
+
+/// Every vector collection with its vectors, sorted by name:
+/// `{"dimension", "metric", "vectors": {"<key>": {"embedding", "metadata"}}}`.
+pub(crate) fn vectors(runner: &mut impl Runner, scope: &Scope) -> Result<Vec<(String, Value)>, BridgeError> {
+    let output = call(runner, scope.command("VectorListCollections", json!({})))?;
+    let mut collections = Vec::new();
+    for info in output["VectorCollectionList"].as_array().into_iter().flatten() {
+        let Some(name) = info["name"].as_str() else {
+            continue;
+        };
+        let dimension = info["dimension"].as_u64().unwrap_or_default();
+        let count = info["count"].as_u64().unwrap_or_default();
+        let mut vectors = serde_json::Map::new();
+        for key in vector_keys(runner, scope, name, dimension, count)? {
+            let get = scope.command("VectorGet", json!({ "collection": name, "key": key }));
+            let output = call(runner, get)?;
+            // Deleted since the search.
+            let Some(data) = output["VectorData"].get("data") else {
+                continue;
+            };
+            vectors.insert(
+                key,
+                json!({ "embedding": data["embedding"], "metadata": data["metadata"] }),
+            );
+        }
+        let collection = json!({
+            "dimension": info["dimension"],
+            "metric": info["metric"],
+            "vectors": vectors,
+        });
+        collections.push((name.to_string(), collection));
+    }
+    collections.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(collections)
+}
+
+/// Every key in a vector collection of `count` vectors, sorted.
+///
+/// stratadb has no way to list a collection's keys, so this searches it
+/// with k = count.
+pub(crate) fn vector_keys(
+    runner: &mut impl Runner,
+    scope: &Scope,
+    collection: &str,
+    dimension: u64,
+    count: u64,
+) -> Result<Vec<String>, BridgeError> {
+    let mut query = vec![0.0_f32; dimension as usize];
+    if let Some(first) = query.first_mut() {
+        *first = 1.0;
+    }
+    let search = scope.command(
+        "VectorSearch",
+        json!({ "collection": collection, "query": query, "k": count }),
+    );
+    let output = call(runner, search)?;
+    let mut keys: Vec<String> = output["VectorMatches"]
+        .as_array()
+        .map(|hits| hits.iter().filter_map(|hit| hit["key"].as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    keys.sort();
+    Ok(keys)
+}
+
+/// Read each key with the command built by `get`, skipping keys deleted since listing.
+fn values(
+    runner: &mut impl Runner,
+    scope: &Scope,
+    mut keys: Vec<String>,
+    get: impl Fn(&str) -> (&'static str, Value),
+) -> Result<Vec<(String, Value)>, BridgeError> {
+    keys.sort();
+    keys.dedup();
+    let mut entries = Vec::with_capacity(keys.len());
+    for key in keys {
+        let (tag, fields) = get(&key);
+        if let Some(record) = maybe_versioned(call(runner, scope.command(tag, fields))?) {
+            entries.push((key, record["value"].clone()));
+        }
+    }
+    Ok(entries)
+}
+
+pub(crate) fn strings(list: &Value) -> Vec<String> {
+    list.as_array()
+        .map(|items| items.iter().filter_map(|k| k.as_str().map(String::from)).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,76 @@
+//! `JsonValidate` — check JSON documents against a JSON Schema stored as another document.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use super::{call, maybe_versioned, to_plain, Scope};
+use crate::error::BridgeError;
+
+#[derive(Deserialize)]
+pub(crate) struct ValidateArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    schema_key: String,
+    targets: Vec<String>,
+}
+
+/// `JsonValidate {"schema_key": "doc:schema", "targets": ["doc:report"]}` —
+/// validate each target document against the JSON Schema stored at
+/// `schema_key`: `{"results": [{"key", "valid", "errors": [{"path",
+/// "schema_path", "message"}]}]}`, in target order.
+///
+/// Paths are JSON Pointers, `""` for the document root. A missing target
+/// fails with one error at the root. Errors with `KeyNotFound` if the schema
+/// document is absent, `InvalidInput` if it is not a valid schema, and
+/// `Unsupported` if the bridge was built without the `json-schema` feature.
+pub(crate) fn validate(strata: &Strata, args: ValidateArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let mut read = |key: &str| -> Result<Option<Value>, BridgeError> {
+        let get = args.scope.command("JsonGet", json!({ "key": key, "path": "$" }));
+        Ok(maybe_versioned(call(&mut executor, get)?).map(|record| to_plain(&record["value"])))
+    };
+    let schema = read(&args.schema_key)?
+        .ok_or_else(|| BridgeError::Kind("KeyNotFound", json!({ "key": args.schema_key })))?;
+    let validator = compile(&schema)?;
+
+    let mut results = Vec::with_capacity(args.targets.len());
+    for key in &args.targets {
+        let errors = match read(key)? {
+            Some(document) => validator(&document),
+            None => vec![json!({ "path": "", "schema_path": "", "message": "document not found" })],
+        };
+        results.push(json!({ "key": key, "valid": errors.is_empty(), "errors": errors }));
+    }
+    Ok(json!({ "results": results }))
+}
+
+/// A document's validation errors.
+type Check = Box<dyn Fn(&Value) -> Vec<Value>>;
+
+#[cfg(feature = "json-schema")]
+fn compile(schema: &Value) -> Result<Check, BridgeError> {
+    let validator = jsonschema::validator_for(schema).map_err(|e| {
+        BridgeError::Kind("InvalidInput", json!({ "reason": format!("invalid JSON Schema: {e}") }))
+    })?;
+    Ok(Box::new(move |document| {
+        validator
+            .iter_errors(document)
+            .map(|e| {
+                json!({
+                    "path": e.instance_path.as_str(),
+                    "schema_path": e.schema_path.as_str(),
+                    "message": e.to_string(),
+                })
+            })
+            .collect()
+    }))
+}
+
+#[cfg(not(feature = "json-schema"))]
+fn compile(_schema: &Value) -> Result<Check, BridgeError> {
+    Err(BridgeError::Kind(
+        "Unsupported",
+        json!({ "reason": "built without the json-schema feature" }),
+    ))
+}
@@ -0,0 +1,209 @@
+//! Bridge-level vector commands.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use super::{call, scan, Scope};
+use crate::error::BridgeError;
+
+#[derive(Deserialize)]
+pub(crate) struct CollectionInfoArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    collection: String,
+}
+
+/// `VectorCollectionInfo {"collection": "docs"}` — a collection's shape:
+/// `{"dimension": 1536, "count": 42, "metric": "cosine"}`.
+///
+/// A collection that does not exist is a `NotFound` error.
+pub(crate) fn collection_info(
+    strata: &Strata,
+    args: CollectionInfoArgs,
+) -> Result<Value, BridgeError> {
+    let info = find_collection(strata, &args.scope, &args.collection)?;
+    Ok(json!({
+        "dimension": info["dimension"],
+        "count": info["count"],
+        "metric": info["metric"],
+    }))
+}
+
+/// A collection's entry in `VectorListCollections`, or `NotFound`.
+fn find_collection(
+    strata: &Strata,
+    scope: &Scope,
+    collection: &str,
+) -> Result<Value, BridgeError> {
+    let list = scope.command("VectorListCollections", json!({}));
+    let mut output = call(&mut strata.executor(), list)?;
+    output["VectorCollectionList"]
+        .as_array_mut()
+        .and_then(|list| {
+            let index = list.iter().position(|c| c["name"] == collection)?;
+            Some(list.swap_remove(index))
+        })
+        .ok_or_else(|| BridgeError::Kind("NotFound", json!({ "collection": collection })))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ReindexArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    collection: String,
+}
+
+/// `VectorReindex {"collection": "docs"}` — rebuild a collection's index
+/// entries by rewriting every vector in it, unchanged:
+/// `{"elapsed_ms": 12, "vectors": 42, "segments": null}`.
+///
+/// stratadb has no index maintenance command, so rewriting each vector is
+/// how the bridge makes it re-insert them into the index. Each rewrite stores
+/// the same embedding and metadata, so concurrent reads see the same results
+/// throughout. stratadb does not report index segments, so `segments` is
+/// always null. A collection that does not exist is a `NotFound` error.
+pub(crate) fn reindex(strata: &Strata, args: ReindexArgs) -> Result<Value, BridgeError> {
+    let started = std::time::Instant::now();
+    let info = find_collection(strata, &args.scope, &args.collection)?;
+    let dimension = info["dimension"].as_u64().unwrap_or_default();
+    let count = info["count"].as_u64().unwrap_or_default();
+
+    let mut executor = strata.executor();
+    let keys = scan::vector_keys(&mut executor, &args.scope, &args.collection, dimension, count)?;
+
+    let mut rewritten = 0;
+    for key in keys {
+        let get = json!({ "collection": args.collection, "key": key });
+        let get = args.scope.command("VectorGet", get);
+        let output = call(&mut executor, get)?;
+        // Deleted since the search: nothing to rewrite.
+        let Some(data) = output["VectorData"].get("data") else {
+            continue;
+        };
+        let mut fields = json!({
+            "collection": args.collection,
+            "key": key,
+            "vector": data["embedding"],
+        });
+        if !data["metadata"].is_null() {
+            fields["metadata"] = data["metadata"].clone();
+        }
+        call(&mut executor, args.scope.command("VectorUpsert", fields))?;
+        rewritten += 1;
+    }
+
+    Ok(json!({
+        "elapsed_ms": started.elapsed().as_millis() as u64,
+        "vectors": rewritten,
+        "segments": null,
+    }))
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SearchByIdArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    collection: String,
+    id: String,
+    k: usize,
+    #[serde(default = "default_true")]
+    exclude_self: bool,
+    filter: Option<Value>,
+    metric: Option<String>,
+}
+
+/// `VectorSearchById {"collection": "docs", "id": "doc-1", "k": 10, "exclude_self": true}`
+/// — `VectorSearch` with the stored embedding of `id` as the query, giving
+/// its matches (`[{"key", "score", "metadata"}]`, best first).
+///
+/// With `exclude_self` (the default), `id` itself is left out and `k` other
+/// vectors are returned. `filter` and `metric` are passed to the search. An
+/// `id` not in the collection is a `NotFound` error.
+pub(crate) fn search_by_id(strata: &Strata, args: SearchByIdArgs) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let get = json!({ "collection": args.collection, "key": args.id });
+    let output = call(&mut executor, args.scope.command("VectorGet", get))?;
+    let Some(data) = output["VectorData"].get("data") else {
+        return Err(BridgeError::Kind(
+            "NotFound",
+            json!({ "collection": args.collection, "id": args.id }),
+        ));
+    };
+
+    // One extra match makes up for the query vector, found by its own search.
+    let k = if args.exclude_self { args.k + 1 } else { args.k };
+    let mut search = json!({ "collection": args.collection, "query": data["embedding"], "k": k });
+    if let Some(filter) = &args.filter {
+        search["filter"] = filter.clone();
+    }
+    if let Some(metric) = &args.metric {
+        search["metric"] = json!(metric);
+    }
+    let mut output = call(&mut executor, args.scope.command("VectorSearch", search))?;
+    let mut matches = match output["VectorMatches"].take() {
+        Value::Array(matches) => matches,
+        _ => Vec::new(),
+    };
+    if args.exclude_self {
+        matches.retain(|m| m["key"] != args.id.as_str());
+    }
+    matches.truncate(args.k);
+    Ok(Value::Array(matches))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CreateCollectionArgs {
+    #[serde(flatten)]
+    scope: Scope,
+    #[serde(alias = "name")]
+    collection: String,
+    dimension: u64,
+    metric: Option<String>,
+}
+
+/// `VectorCreateCollection {"collection": "docs", "dimension": 1536, "metric": "cosine"}`
+/// — stratadb's command, checked against any existing collection first.
+///
+/// `name` is accepted in place of `collection`. Re-creating a collection
+/// with the same dimension and metric is a no-op returning `{"Version": 0}`;
+/// a different spec is a `CollectionConflict` error. Returns stratadb's
+/// output unchanged otherwise.
+pub(crate) fn create_collection(
+    strata: &Strata,
+    args: CreateCollectionArgs,
+) -> Result<Value, BridgeError> {
+    let mut executor = strata.executor();
+    let list = args.scope.command("VectorListCollections", json!({}));
+    let output = call(&mut executor, list)?;
+    let existing = output["VectorCollectionList"]
+        .as_array()
+        .and_then(|list| list.iter().find(|c| c["name"] == args.collection.as_str()));
+
+    if let Some(existing) = existing {
+        let same_metric = args.metric.as_deref().is_none_or(|metric| {
+            existing["metric"].as_str().is_some_and(|m| m.eq_ignore_ascii_case(metric))
+        });
+        if existing["dimension"] == args.dimension && same_metric {
+            return Ok(json!({ "Version": 0 }));
+        }
+        return Err(BridgeError::Kind(
+            "CollectionConflict",
+            json!({
+                "collection": args.collection,
+                "existing": { "dimension": existing["dimension"], "metric": existing["metric"] },
+                "requested": { "dimension": args.dimension, "metric": args.metric },
+            }),
+        ));
+    }
+
+    let mut fields = json!({ "collection": args.collection, "dimension": args.dimension });
+    if let Some(metric) = &args.metric {
+        fields["metric"] = json!(metric);
+    }
+    call(&mut executor, args.scope.command("VectorCreateCollection", fields))
+}
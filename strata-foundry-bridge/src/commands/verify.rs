@@ -0,0 +1,74 @@
+//! Open-time consistency checks (`verify_on_open`).
+//!
+//! stratadb verifies checksums and replays its WAL inside `open` but exposes
+//! no integrity check of its own, so the bridge probes what it can reach:
+//! that every primitive on every branch can still be read, and that the
+//! event log's last sequence resolves to an event. Any probe that fails is
+//! an issue; every issue is treated as critical.
+
+use serde_json::{json, Value};
+use stratadb::{Executor, Strata};
+
+use super::{call, maybe_versioned, Scope};
+
+/// `{"ok": bool, "issues": [{"check", "branch", "reason"}]}`
+pub(crate) fn verify(strata: &Strata) -> Value {
+    let mut executor = strata.executor();
+    let mut issues = Vec::new();
+
+    // If the branch list is unreadable, still check the default branch.
+    let branches: Vec<Option<String>> = match call(&mut executor, json!({ "BranchList": {} })) {
+        Ok(output) => output["BranchInfoList"]
+            .as_array()
+            .map(|list| {
+                list.iter().map(|b| b["info"]["id"].as_str().map(String::from)).collect()
+            })
+            .unwrap_or_default(),
+        Err(e) => {
+            issues.push(json!({ "check": "branches", "branch": null, "reason": e.to_json() }));
+            vec![None]
+        }
+    };
+
+    for branch in branches {
+        let scope = Scope { branch: branch.clone(), ..Scope::default() };
+        for (check, result) in probe(&mut executor, &scope) {
+            if let Err(reason) = result {
+                issues.push(json!({ "check": check, "branch": branch, "reason": reason }));
+            }
+        }
+    }
+    json!({ "ok": issues.is_empty(), "issues": issues })
+}
+
+/// Run each check on one branch.
+fn probe(executor: &mut Executor, scope: &Scope) -> Vec<(&'static str, Result<(), Value>)> {
+    let mut read = |tag: &str, fields: Value| {
+        call(executor, scope.command(tag, fields)).map(drop).map_err(|e| e.to_json())
+    };
+    let mut results = vec![
+        ("kv", read("KvList", json!({ "limit": 1 }))),
+        ("state", read("StateList", json!({}))),
+        ("json", read("JsonList", json!({ "limit": 1 }))),
+        ("vectors", read("VectorListCollections", json!({}))),
+    ];
+    results.push(("events", event_tail(executor, scope)));
+    results
+}
+
+/// The event log's length should match its last readable event.
+fn event_tail(executor: &mut Executor, scope: &Scope) -> Result<(), Value> {
+    let len = call(executor, scope.command("EventLen", json!({})))
+        .map_err(|e| e.to_json())?["Uint"]
+        .as_u64()
+        .unwrap_or_default();
+    let Some(last) = len.checked_sub(1) else {
+        return Ok(());
+    };
+    let output = call(executor, scope.command("EventGet", json!({ "sequence": last })))
+        .map_err(|e| e.to_json())?;
+    match maybe_versioned(output) {
+        Some(_) => Ok(()),
+        None => Err(json!(format!("event log has {len} events but sequence {last} is missing"))),
+    }
+}
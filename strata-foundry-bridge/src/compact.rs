@@ -0,0 +1,63 @@
+//! Compact positional commands.
+//!
+//! For hot, simple calls Swift can send `["KvGet", "user:alice"]` instead of
+//! `{"KvGet": {"key": "user:alice"}}`: element 0 is the tag and the rest fill
+//! that command's fields in the order listed in `FORMS`. Trailing fields may
+//! be left out. Only the commands below have a compact form; values stay in
+//! stratadb's tagged form (`{"Int": 1}`).
+
+use serde_json::{Map, Value};
+
+use crate::error::BridgeError;
+
+/// Commands with a compact form, and their positional fields in order.
+const FORMS: &[(&str, &[&str])] = &[
+    ("KvGet", &["key"]),
+    ("KvPut", &["key", "value"]),
+    ("KvDelete", &["key"]),
+    ("KvList", &["prefix", "limit"]),
+    ("StateGet", &["cell"]),
+    ("StateSet", &["cell", "value"]),
+    ("JsonGet", &["key", "path"]),
+    ("JsonSet", &["key", "path", "value"]),
+    ("EventAppend", &["event_type", "payload"]),
+];
+
+/// Expand a compact command into the object envelope.
+///
+/// Returns `None` if `command_json` is not an array, so the caller uses it
+/// as is.
+pub fn expand(command_json: &str) -> Option<Result<String, BridgeError>> {
+    if !command_json.trim_start().starts_with('[') {
+        return None;
+    }
+    Some(expand_array(command_json))
+}
+
+fn expand_array(command_json: &str) -> Result<String, BridgeError> {
+    let items: Vec<Value> =
+        serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+    let (tag, args) = match items.split_first() {
+        Some((Value::String(tag), args)) => (tag, args),
+        _ => return Err("invalid command JSON: a compact command starts with its tag".into()),
+    };
+    let Some((_, fields)) = FORMS.iter().find(|(name, _)| name == tag) else {
+        return Err(BridgeError::Kind(
+            "InvalidInput",
+            serde_json::json!({ "reason": "command has no compact form", "command": tag }),
+        ));
+    };
+    if args.len() > fields.len() {
+        return Err(BridgeError::Kind(
+            "InvalidInput",
+            serde_json::json!({
+                "reason": format!("{tag} takes at most {} positional arguments", fields.len()),
+                "command": tag,
+            }),
+        ));
+    }
+
+    let body: Map<String, Value> =
+        fields.iter().zip(args).map(|(field, arg)| (field.to_string(), arg.clone())).collect();
+    Ok(serde_json::json!({ tag: body }).to_string())
+}
@@ -0,0 +1,66 @@
+//! Framing for compressed command output, and gzip command input.
+//!
+//! Every output buffer starts with a one-byte header naming its encoding,
+//! followed by the payload. Outputs too small to benefit are stored
+//! uncompressed.
+
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use serde_json::json;
+
+use crate::error::BridgeError;
+
+/// Header byte: the payload is the raw UTF-8 JSON.
+pub const FORMAT_RAW: u8 = 0;
+/// Header byte: the payload is a zstd frame holding the UTF-8 JSON.
+pub const FORMAT_ZSTD: u8 = 1;
+
+/// Outputs shorter than this are never compressed.
+const MIN_COMPRESS_LEN: usize = 1024;
+
+/// Bound on a gzip command's decompressed size when the handle sets no
+/// `max_command_bytes`.
+pub const DEFAULT_MAX_GUNZIP_BYTES: u64 = 256 * 1024 * 1024;
+
+/// zstd level: favours speed, since this sits on the command hot path.
+const LEVEL: i32 = 3;
+
+/// Frame `output`, compressing it when that makes it smaller.
+pub fn encode(output: &[u8]) -> Vec<u8> {
+    if output.len() >= MIN_COMPRESS_LEN {
+        if let Ok(compressed) = zstd::bulk::compress(output, LEVEL) {
+            if compressed.len() < output.len() {
+                return framed(FORMAT_ZSTD, &compressed);
+            }
+        }
+    }
+    framed(FORMAT_RAW, output)
+}
+
+fn framed(format: u8, payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(payload.len() + 1);
+    buf.push(format);
+    buf.extend_from_slice(payload);
+    buf
+}
+
+/// Decompress a gzip-compressed command into its JSON text, refusing to
+/// inflate more than `max_bytes`.
+pub fn gunzip(data: &[u8], max_bytes: u64) -> Result<String, BridgeError> {
+    let invalid = |reason: String| BridgeError::Kind("InvalidInput", json!({ "reason": reason }));
+    let mut command = Vec::new();
+    GzDecoder::new(data)
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut command)
+        .map_err(|e| invalid(format!("invalid gzip data: {e}")))?;
+    if command.len() as u64 > max_bytes {
+        return Err(too_large(max_bytes));
+    }
+    String::from_utf8(command).map_err(|_| invalid("decompressed command is not UTF-8".into()))
+}
+
+/// `CommandTooLarge` for a command over `max_command_bytes`.
+pub fn too_large(max_bytes: u64) -> BridgeError {
+    BridgeError::Kind("CommandTooLarge", json!({ "max_command_bytes": max_bytes }))
+}
@@ -0,0 +1,245 @@
+//! Open options passed as `config_json` to the `strata_open*` functions.
+
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::cache::Eviction;
+use crate::error::BridgeError;
+
+/// Resource options for opening a database. Unset fields use stratadb's defaults.
+///
+/// stratadb's `open` does not take tuning options yet, so `max_open_files`,
+/// `cache_bytes` and `lazy` are recorded per handle only: `strata_get_config`
+/// reports them as given and lists them under `unapplied` (see `unapplied`).
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct OpenConfig {
+    /// Upper bound on file descriptors the database may hold open. Recorded only.
+    pub max_open_files: Option<u64>,
+    /// Block cache size in bytes. Recorded only.
+    pub cache_bytes: Option<u64>,
+    /// When writes are made durable: `"always"`, `"interval:<ms>"` or `"never"`.
+    pub wal_sync: Option<WalSync>,
+    /// Expected read pattern: `"sequential"` prefetches the database's files
+    /// when it opens, `"random"` does not (see `readahead`).
+    pub access_pattern: Option<AccessPattern>,
+    /// Defer loading indexes and segments until first use. Unsupported:
+    /// stratadb loads them while opening and has no deferred mode, so this
+    /// is recorded only.
+    #[serde(default)]
+    pub lazy: bool,
+    /// Refuse every write through the handle with `ReadOnly`.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Refuse to open unless the database's on-disk format is this version.
+    pub require_format_version: Option<u32>,
+    /// Most commands that may run at once on the handle; zero is unlimited.
+    pub max_concurrent_commands: Option<u32>,
+    /// At `max_concurrent_commands`, wait for a slot instead of failing with `Busy`.
+    #[serde(default)]
+    pub block: bool,
+    /// Back the database directory up before opening it, keeping this many
+    /// backups; zero or unset takes none.
+    pub backup_on_open: Option<u32>,
+    /// Check the database is readable right after opening it.
+    #[serde(default)]
+    pub verify_on_open: bool,
+    /// Open even if `verify_on_open` finds issues, reporting them instead of failing.
+    #[serde(default)]
+    pub allow_degraded: bool,
+    /// Largest command JSON the handle accepts, in bytes, checked after any
+    /// decompression; unset is unlimited for plain commands.
+    pub max_command_bytes: Option<u64>,
+    /// Bound on each `strata_execute` call, in milliseconds; zero or unset
+    /// is unbounded. `strata_set_default_timeout_ms` changes it later.
+    pub default_timeout_ms: Option<u64>,
+    /// Append every successful mutating command to this file, one JSON line each.
+    pub journal_path: Option<String>,
+    /// fsync the journal after each line.
+    #[serde(default)]
+    pub journal_fsync: bool,
+    /// Seed for the random choices commands make, such as `KvSample`'s, so
+    /// the same commands on the same data choose the same way on every run.
+    /// stratadb takes no seed, so its own randomness is unaffected.
+    pub seed: Option<u64>,
+    /// In-memory handles only: cap on the bytes of KV entries held.
+    pub max_bytes: Option<u64>,
+    /// At `max_bytes`, `"lru"` evicts least recently used keys and
+    /// `"reject"` (the default) refuses the write with `CacheFull`.
+    pub eviction: Option<Eviction>,
+}
+
+/// How a workload mostly reads: `Sequential` scans benefit from aggressive
+/// readahead, `Random` point reads from none.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessPattern {
+    Sequential,
+    Random,
+}
+
+/// WAL sync policy, applied by the bridge with `Strata::flush`.
+///
+/// `Always` flushes after every write command, `Interval` flushes from a
+/// background autoflush thread, and `Never` leaves flushing to stratadb and
+/// explicit `strata_flush_all` calls.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub enum WalSync {
+    Always,
+    Interval { ms: u64 },
+    Never,
+}
+
+impl TryFrom<String> for WalSync {
+    type Error = String;
+
+    fn try_from(policy: String) -> Result<Self, Self::Error> {
+        match policy.as_str() {
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => match policy.strip_prefix("interval:").map(str::parse) {
+                Some(Ok(ms)) if ms > 0 => Ok(Self::Interval { ms }),
+                _ => Err(format!(
+                    "invalid wal_sync {policy:?}: expected \"always\", \"interval:<ms>\" or \"never\""
+                )),
+            },
+        }
+    }
+}
+
+impl From<WalSync> for String {
+    fn from(policy: WalSync) -> Self {
+        match policy {
+            WalSync::Always => "always".into(),
+            WalSync::Interval { ms } => format!("interval:{ms}"),
+            WalSync::Never => "never".into(),
+        }
+    }
+}
+
+/// Process-wide defaults from `strata_set_default_config`, as the fields set.
+static DEFAULTS: RwLock<Option<Map<String, Value>>> = RwLock::new(None);
+
+/// Set (or clear, with null) the defaults every open's config is merged over.
+pub fn set_defaults(config_json: Option<&str>) -> Result<(), BridgeError> {
+    let defaults = match fields(config_json)? {
+        Some(fields) => {
+            // Reject defaults no open could use.
+            from_fields(fields.clone())?;
+            Some(fields)
+        }
+        None => None,
+    };
+    *DEFAULTS.write().unwrap_or_else(|e| e.into_inner()) = defaults;
+    Ok(())
+}
+
+impl OpenConfig {
+    /// Parse `config_json` over the process-wide defaults: each field it
+    /// sets overrides the default's, field by field. Null or an empty string
+    /// gives the defaults. Fields this bridge does not know are ignored.
+    pub fn parse(config_json: Option<&str>) -> Result<Self, BridgeError> {
+        let defaults = DEFAULTS.read().unwrap_or_else(|e| e.into_inner()).clone();
+        Self::parse_over(defaults, config_json)
+    }
+
+    /// `parse` over `defaults` given as their fields, rather than the
+    /// process-wide ones.
+    pub fn parse_over(
+        defaults: Option<Map<String, Value>>,
+        config_json: Option<&str>,
+    ) -> Result<Self, BridgeError> {
+        let mut merged = defaults;
+        if let Some(fields) = fields(config_json)? {
+            merged.get_or_insert_with(Map::new).extend(fields);
+        }
+        match merged {
+            Some(fields) => from_fields(fields),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// The fields of a config object, or `None` for null or an empty string.
+fn fields(config_json: Option<&str>) -> Result<Option<Map<String, Value>>, BridgeError> {
+    match config_json.map(str::trim) {
+        None | Some("") | Some("null") => Ok(None),
+        Some(json) => serde_json::from_str(json).map(Some).map_err(invalid_config),
+    }
+}
+
+fn from_fields(fields: Map<String, Value>) -> Result<OpenConfig, BridgeError> {
+    serde_json::from_value(Value::Object(fields)).map_err(invalid_config)
+}
+
+fn invalid_config(e: serde_json::Error) -> BridgeError {
+    BridgeError::Kind("InvalidInput", json!({ "reason": format!("invalid config JSON: {e}") }))
+}
+
+impl OpenConfig {
+    /// The options set here that nothing applies, because stratadb's `open`
+    /// takes no tuning options: reported by `strata_get_config` so hosts do
+    /// not mistake a recorded value for one in effect.
+    pub fn unapplied(&self) -> Vec<&'static str> {
+        let recorded_only = [
+            ("max_open_files", self.max_open_files.is_some()),
+            ("cache_bytes", self.cache_bytes.is_some()),
+            ("lazy", self.lazy),
+        ];
+        recorded_only.into_iter().filter(|(_, set)| *set).map(|(name, _)| name).collect()
+    }
+}
+
+impl OpenConfig {
+    /// Check `require_format_version` before opening, so stratadb never
+    /// gets the chance to migrate an unexpected format.
+    ///
+    /// stratadb does not report the on-disk format version, so it cannot be
+    /// compared: a required version is refused as `FormatMismatch` with
+    /// `found: null` rather than silently opening an unverified database.
+    pub fn check_format(&self) -> Result<(), BridgeError> {
+        match self.require_format_version {
+            None => Ok(()),
+            Some(required) => Err(BridgeError::Kind(
+                "FormatMismatch",
+                json!({
+                    "found": null,
+                    "required": required,
+                    "reason": "stratadb does not expose the on-disk format version",
+                }),
+            )),
+        }
+    }
+}
+
+impl OpenConfig {
+    /// Refuse options that only apply to in-memory handles.
+    pub fn check_file_backed(&self) -> Result<(), BridgeError> {
+        if self.max_bytes.is_some() || self.eviction.is_some() {
+            return Err(BridgeError::Kind(
+                "InvalidInput",
+                json!({ "reason": "max_bytes and eviction apply to in-memory handles only" }),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Classify a failed open: file descriptor exhaustion becomes
+/// `ResourceExhausted`, anything else is passed through.
+pub fn open_error(reason: String) -> BridgeError {
+    // EMFILE (24) is the per-process limit, ENFILE (23) the system-wide one.
+    let exhausted = ["Too many open files", "os error 24", "os error 23"]
+        .iter()
+        .any(|needle| reason.contains(needle));
+    if exhausted {
+        BridgeError::Kind(
+            "ResourceExhausted",
+            json!({ "resource": "file_descriptors", "reason": reason }),
+        )
+    } else {
+        reason.into()
+    }
+}
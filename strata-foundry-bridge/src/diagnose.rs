@@ -0,0 +1,58 @@
+//! Diagnostics for failed opens (`strata_last_open_error`).
+//!
+//! stratadb reports a failed open as a single message. When a file-backed
+//! open fails, the bridge also looks at the path itself and keeps the
+//! result, process-wide, until the next failed open replaces it.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use crate::handle::unix_millis;
+
+static LAST_OPEN_ERROR: Mutex<Option<Value>> = Mutex::new(None);
+
+/// Record why opening `path` failed with `error`.
+///
+/// `exists` and `kind` describe `path`; permissions are those of `path`,
+/// or of its nearest existing ancestor (`checked`) when it does not exist,
+/// since that is the directory stratadb would have to create it in.
+pub fn record_open_failure(path: &Path, error: &str) {
+    let metadata = std::fs::metadata(path).ok();
+    let kind = match &metadata {
+        Some(m) if m.is_dir() => "directory",
+        Some(_) => "file",
+        None => "missing",
+    };
+    let checked = path.ancestors().find(|p| p.exists()).unwrap_or(path);
+    let mode = std::fs::metadata(checked).ok().map(|m| m.permissions().mode() & 0o7777);
+    let diagnostic = json!({
+        "path": path.to_string_lossy(),
+        "exists": metadata.is_some(),
+        "kind": kind,
+        "checked": checked.to_string_lossy(),
+        "permissions": mode.map(|mode| format!("{mode:04o}")),
+        "readable": accessible(checked, libc::R_OK),
+        "writable": accessible(checked, libc::W_OK),
+        "error": error,
+        "at_ms": unix_millis(),
+    });
+    *LAST_OPEN_ERROR.lock().unwrap_or_else(|e| e.into_inner()) = Some(diagnostic);
+}
+
+/// The most recent open failure's diagnostic, if any open has failed.
+pub fn last_open_error() -> Option<Value> {
+    LAST_OPEN_ERROR.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Whether this process may access `path` in `mode`, as `access(2)` sees it.
+fn accessible(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(c_path.as_ptr(), mode) == 0 }
+}
@@ -0,0 +1,34 @@
+//! Free space on a database's filesystem (`strata_disk_free`).
+
+use std::ffi::CString;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Bytes available to this process on the filesystem holding `path`.
+pub fn free_bytes(path: &Path) -> io::Result<u64> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // `f_bavail` excludes blocks reserved for root.
+    Ok((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+/// The mount point of the filesystem holding `path`: its highest ancestor
+/// on the same device.
+pub fn mount_point(path: &Path) -> io::Result<PathBuf> {
+    let path = path.canonicalize()?;
+    let device = path.metadata()?.dev();
+    let mut mount = path.as_path();
+    while let Some(parent) = mount.parent() {
+        if parent.metadata()?.dev() != device {
+            break;
+        }
+        mount = parent;
+    }
+    Ok(mount.to_path_buf())
+}
@@ -0,0 +1,126 @@
+//! Bridge-level errors.
+//!
+//! stratadb errors are passed through as strings (see `HandleRegistry::execute`);
+//! this type adds the failures the bridge itself detects, each surfaced to Swift
+//! under its own variant name.
+
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+/// Only the variant and a short message cross the boundary.
+pub const VERBOSITY_TERSE: i32 = 0;
+/// The variant and all of its fields (the default).
+pub const VERBOSITY_NORMAL: i32 = 1;
+/// Additionally the originating command and the decoded stratadb cause.
+pub const VERBOSITY_DEBUG: i32 = 2;
+
+static VERBOSITY: AtomicI32 = AtomicI32::new(VERBOSITY_NORMAL);
+
+/// Set the detail level for errors returned to Swift, clamped to the known levels.
+pub fn set_verbosity(level: i32) {
+    VERBOSITY.store(level.clamp(VERBOSITY_TERSE, VERBOSITY_DEBUG), Ordering::Relaxed);
+}
+
+pub fn verbosity() -> i32 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Longest message kept at `VERBOSITY_TERSE`.
+const TERSE_MESSAGE_LEN: usize = 120;
+
+#[derive(Debug)]
+pub enum BridgeError {
+    /// Free-form failure, surfaced as `{"Internal":{"reason":...}}`.
+    Internal(String),
+    /// A typed failure, surfaced as `{"<kind>": <fields>}`.
+    Kind(&'static str, Value),
+}
+
+impl BridgeError {
+    /// The error object as surfaced to Swift, e.g. `{"Internal":{"reason":...}}`.
+    pub fn to_json(&self) -> Value {
+        match self {
+            BridgeError::Internal(msg) => json!({ "Internal": { "reason": msg } }),
+            BridgeError::Kind(kind, fields) => json!({ *kind: fields }),
+        }
+    }
+
+    /// The error object at the current verbosity; `command` is the
+    /// originating command JSON, if there was one.
+    pub fn render(&self, command: Option<&str>) -> Value {
+        self.render_at(verbosity(), command)
+    }
+
+    /// `render` at an explicit level rather than the global setting.
+    pub fn render_at(&self, level: i32, command: Option<&str>) -> Value {
+        let (kind, mut fields) = match self {
+            BridgeError::Internal(msg) => ("Internal", json!({ "reason": msg })),
+            BridgeError::Kind(kind, fields) => (*kind, fields.clone()),
+        };
+
+        if level <= VERBOSITY_TERSE {
+            let reason = fields["reason"].as_str().unwrap_or(kind);
+            let first_line = reason.lines().next().unwrap_or_default();
+            let short: String = first_line.chars().take(TERSE_MESSAGE_LEN).collect();
+            return json!({ kind: { "reason": short } });
+        }
+
+        if level >= VERBOSITY_DEBUG {
+            if let Some(map) = fields.as_object_mut() {
+                // stratadb failures arrive as their serialized error; decode it
+                // so the underlying variant and its fields are visible.
+                if let Some(cause) = map
+                    .get("reason")
+                    .and_then(Value::as_str)
+                    .and_then(|r| serde_json::from_str::<Value>(r).ok())
+                    .filter(Value::is_object)
+                {
+                    map.insert("cause".into(), cause);
+                }
+                if let Some(command) = command {
+                    let command = serde_json::from_str(command).unwrap_or_else(|_| json!(command));
+                    map.insert("command".into(), command);
+                }
+            }
+        }
+        json!({ kind: fields })
+    }
+}
+
+impl From<String> for BridgeError {
+    fn from(msg: String) -> Self {
+        BridgeError::Internal(msg)
+    }
+}
+
+impl From<&str> for BridgeError {
+    fn from(msg: &str) -> Self {
+        BridgeError::Internal(msg.to_string())
+    }
+}
+
+/// The message of the most recent panic caught at the boundary.
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Remember a caught panic for `strata_health`.
+pub fn record_panic(message: &str) {
+    *LAST_PANIC.lock().unwrap_or_else(|e| e.into_inner()) = Some(message.to_string());
+}
+
+/// The most recent caught panic's message, if any panic has been caught.
+pub fn last_panic() -> Option<String> {
+    LAST_PANIC.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Extract a readable message from a `catch_unwind` payload.
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "panic in Rust bridge".to_string()
+    }
+}
@@ -0,0 +1,104 @@
+//! Handle groups: several databases behind one ID (`strata_open_group`).
+//!
+//! A group maps shard names to ordinary handles. Commands sent to the group
+//! name their shard alongside the command tag,
+//! `{"shard": "user_a", "KvGet": {...}}`, and run on that shard's handle
+//! exactly as if sent to it directly. Group IDs come from the handle ID
+//! space, so `strata_execute` and `strata_close` take them in place of a
+//! handle.
+
+use std::collections::BTreeMap;
+
+use dashmap::DashMap;
+use serde_json::{json, Value};
+
+use crate::config::{self, OpenConfig};
+use crate::error::BridgeError;
+use crate::handle::HandleRegistry;
+
+/// Registry of open groups: group ID to shard name to handle ID.
+pub struct GroupRegistry {
+    groups: DashMap<u64, BTreeMap<String, u64>>,
+}
+
+impl GroupRegistry {
+    pub fn new() -> Self {
+        Self { groups: DashMap::new() }
+    }
+
+    /// Open every shard in `paths_json`, `{"<shard>": "<path>" | null}`,
+    /// where null opens an in-memory database. If any open fails, the
+    /// shards already opened are closed again. Returns the group ID.
+    pub fn open(
+        &self,
+        registry: &'static HandleRegistry,
+        paths_json: &str,
+    ) -> Result<u64, BridgeError> {
+        let invalid = |reason: &str| BridgeError::Kind("InvalidInput", json!({ "reason": reason }));
+        let paths: BTreeMap<String, Option<String>> = serde_json::from_str(paths_json)
+            .map_err(|_| invalid("paths_json must map shard names to paths or null"))?;
+        if paths.is_empty() {
+            return Err(invalid("a group needs at least one shard"));
+        }
+
+        let config = OpenConfig::parse(None)?;
+        let mut shards = BTreeMap::new();
+        for (shard, path) in paths {
+            let opened = match &path {
+                Some(path) => registry.open(path, config.clone()).map(|(id, _)| id),
+                None => registry.open_memory(config.clone(), 0),
+            };
+            match opened {
+                Ok(id) => {
+                    shards.insert(shard, id);
+                }
+                Err(e) => {
+                    for id in shards.into_values() {
+                        registry.close(id);
+                    }
+                    return Err(config::open_error(e));
+                }
+            }
+        }
+
+        let id = registry.reserve_id();
+        self.groups.insert(id, shards);
+        Ok(id)
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.groups.contains_key(&id)
+    }
+
+    /// Split a group command into its shard's handle and the command
+    /// without `"shard"`.
+    pub fn route(&self, id: u64, command_json: &str) -> Result<(u64, String), BridgeError> {
+        let shards = self.groups.get(&id).ok_or("invalid group")?;
+        let mut command: Value =
+            serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+        let shard = command.as_object_mut().and_then(|m| m.remove("shard"));
+        let Some(shard) = shard.as_ref().and_then(Value::as_str) else {
+            return Err(BridgeError::Kind(
+                "InvalidInput",
+                json!({ "reason": "commands sent to a group need a \"shard\"", "group": id }),
+            ));
+        };
+        let handle = shards.get(shard).copied().ok_or_else(|| {
+            BridgeError::Kind("NotFound", json!({ "shard": shard, "group": id }))
+        })?;
+        Ok((handle, command.to_string()))
+    }
+
+    /// The handles of a group's shards; empty for an unknown group.
+    pub fn handles(&self, id: u64) -> Vec<u64> {
+        self.groups.get(&id).map(|shards| shards.values().copied().collect()).unwrap_or_default()
+    }
+
+    /// Forget a group, returning its shards' handles for the caller to close.
+    pub fn close(&self, id: u64) -> Vec<u64> {
+        self.groups
+            .remove(&id)
+            .map(|(_, shards)| shards.into_values().collect())
+            .unwrap_or_default()
+    }
+}
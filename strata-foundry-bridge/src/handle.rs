@@ -3,15 +3,197 @@
 //! Handles are integer IDs stored in a global concurrent map.
 //! This avoids passing raw pointers across the FFI boundary.
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
+use serde_json::json;
 use stratadb::{Command, Output, Strata};
 
+use crate::audit::{self, AuditLog};
+use crate::backup;
+use crate::cache::MemoryCap;
+use crate::coalesce::Coalescer;
+use crate::commands;
+use crate::compact;
+use crate::compress;
+use crate::config::{OpenConfig, WalSync};
+use crate::disk;
+use crate::error::{panic_message, record_panic, BridgeError};
+use crate::latency::Latencies;
+use crate::diagnose;
+use crate::journal::Journal;
+use crate::limits::{Limits, ValueLimits};
+use crate::log;
+use crate::policy::{self, KeyPolicy};
+use crate::pressure;
+use crate::project;
+use crate::readahead;
+use crate::readcache::{self, ReadCache};
+use crate::slots::CommandSlots;
+use crate::threads;
+use crate::tolerant;
+
+/// Writes a `read_only` handle refuses besides the audited mutations.
+const ALSO_WRITES: &[&str] = &["BranchImport", "VectorReindex"];
+/// Commands whose output holds user data as plain JSON rather than tagged
+/// values, where `{"Int": 5}` may be a document and not an `Int`.
+const PLAIN_OUTPUTS: &[&str] = &["JsonGetInline"];
+
+/// Bridge-side metadata tracked alongside each open database.
+pub struct HandleMeta {
+    /// Filesystem path for file-backed handles; `None` for in-memory ones.
+    path: Option<PathBuf>,
+    /// When the handle was opened, for uptime.
+    opened_at: Instant,
+    /// Wall-clock open time in milliseconds since the Unix epoch.
+    opened_at_ms: u64,
+    /// Set when a command panicked mid-execution. A faulted handle rejects
+    /// further commands until the client closes and reopens it.
+    faulted: AtomicBool,
+    /// The panic message that faulted the handle.
+    fault_reason: Mutex<Option<String>>,
+    /// Directory created by `open_temp`, deleted when the handle closes.
+    temp_dir: Option<PathBuf>,
+    /// Key and value size limits checked before writes.
+    limits: ValueLimits,
+    /// Keys the handle may name, set by `strata_set_key_policy`.
+    key_policy: RwLock<Option<Arc<KeyPolicy>>>,
+    /// Audit trail of mutations; `None` unless enabled with `set_audit`.
+    audit: Mutex<Option<AuditLog>>,
+    /// Sort list outputs by key before returning them.
+    deterministic: AtomicBool,
+    /// Serialize `Int` values in outputs as JSON strings.
+    int_as_string: AtomicBool,
+    /// Accept `//` comments and trailing commas in command JSON.
+    tolerant_json: AtomicBool,
+    /// Options the handle was opened with.
+    config: OpenConfig,
+    /// KV size cap of an in-memory handle opened with `max_bytes`.
+    cap: Option<Arc<MemoryCap>>,
+    /// In-flight commands, bounded by `config.max_concurrent_commands`.
+    slots: CommandSlots,
+    /// The most recent failed command's error, for `strata_handle_last_error`.
+    last_error: Mutex<Option<serde_json::Value>>,
+    /// Latency histograms per command tag, for `strata_stats_latency`.
+    latencies: Latencies,
+    /// State cells whose sets are coalesced, by `strata_set_state_coalesce`.
+    coalesce: Arc<Coalescer>,
+    /// Where successful mutations are appended, with `journal_path`.
+    journal: Option<Journal>,
+    /// `strata_execute` timeout in milliseconds; zero is unbounded.
+    default_timeout_ms: AtomicU64,
+    /// Set by `strata_handle_pin`: `strata_close` leaves the handle open.
+    pinned: AtomicBool,
+    /// Cached read outputs, sized by `strata_set_read_cache`.
+    read_cache: Arc<ReadCache>,
+    /// Seeds each command's random choices, with the `seed` option.
+    rng: Option<Arc<Mutex<fastrand::Rng>>>,
+}
+
+impl HandleMeta {
+    fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            opened_at: Instant::now(),
+            opened_at_ms: unix_millis(),
+            faulted: AtomicBool::new(false),
+            fault_reason: Mutex::new(None),
+            temp_dir: None,
+            limits: ValueLimits::default(),
+            key_policy: RwLock::new(None),
+            audit: Mutex::new(None),
+            deterministic: AtomicBool::new(false),
+            int_as_string: AtomicBool::new(false),
+            tolerant_json: AtomicBool::new(false),
+            config: OpenConfig::default(),
+            cap: None,
+            slots: CommandSlots::default(),
+            last_error: Mutex::new(None),
+            latencies: Latencies::default(),
+            coalesce: Arc::default(),
+            journal: None,
+            default_timeout_ms: AtomicU64::new(0),
+            pinned: AtomicBool::new(false),
+            read_cache: Arc::default(),
+            rng: None,
+        }
+    }
+
+    /// Milliseconds since the handle was opened.
+    pub fn uptime_ms(&self) -> u64 {
+        self.opened_at.elapsed().as_millis() as u64
+    }
+
+    pub fn is_faulted(&self) -> bool {
+        self.faulted.load(Ordering::Acquire)
+    }
+
+    fn mark_faulted(&self, reason: String) {
+        *self.fault_reason.lock().unwrap_or_else(|e| e.into_inner()) = Some(reason);
+        self.faulted.store(true, Ordering::Release);
+    }
+
+    fn clear_fault(&self) {
+        self.faulted.store(false, Ordering::Release);
+        *self.fault_reason.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    fn fault_reason(&self) -> Option<String> {
+        self.fault_reason.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// An open database and its metadata.
+///
+/// Handles opened on the same path share one `Strata`; it is dropped when
+/// the last of them closes.
+struct HandleEntry {
+    strata: Arc<Strata>,
+    meta: HandleMeta,
+}
+
+impl HandleEntry {
+    fn new(strata: Arc<Strata>, path: Option<PathBuf>) -> Self {
+        Self {
+            strata,
+            meta: HandleMeta::new(path),
+        }
+    }
+
+    fn describe(&self, id: u64) -> serde_json::Value {
+        let kind = match (&self.meta.path, &self.meta.temp_dir) {
+            (_, Some(_)) => "temp",
+            (Some(_), None) => "file",
+            (None, None) => "memory",
+        };
+        json!({
+            "handle": id,
+            "kind": kind,
+            "path": self.meta.path.as_ref().map(|p| p.to_string_lossy()),
+            "opened_at_ms": self.meta.opened_at_ms,
+            "uptime_ms": self.meta.uptime_ms(),
+            "faulted": self.meta.is_faulted(),
+        })
+    }
+}
+
 /// Thread-safe registry of all open database handles.
 pub struct HandleRegistry {
     next_id: AtomicU64,
-    handles: DashMap<u64, Strata>,
+    handles: DashMap<u64, HandleEntry>,
+    /// Commands slower than this are logged as warnings. Zero disables.
+    slow_command_threshold_ms: AtomicU64,
+    /// Directory relative open paths resolve against; `None` means the process CWD.
+    base_dir: RwLock<Option<PathBuf>>,
+    /// Databases open on disk, keyed by canonical path, so a second open of
+    /// the same path shares the first `Strata` instead of opening it twice.
+    /// The lock also serializes file-backed opens.
+    open_paths: Mutex<HashMap<PathBuf, Weak<Strata>>>,
 }
 
 impl HandleRegistry {
@@ -19,43 +201,1149 @@ impl HandleRegistry {
         Self {
             next_id: AtomicU64::new(1),
             handles: DashMap::new(),
+            slow_command_threshold_ms: AtomicU64::new(0),
+            base_dir: RwLock::new(None),
+            open_paths: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Open a database at the given filesystem path.
-    pub fn open(&self, path: &str) -> Result<u64, String> {
-        let strata = Strata::open(path).map_err(|e| e.to_string())?;
-        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
-        self.handles.insert(id, strata);
-        Ok(id)
+    /// Set the slow-command log threshold in milliseconds. Zero disables it.
+    pub fn set_slow_command_threshold_ms(&self, ms: u64) {
+        self.slow_command_threshold_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Set the directory relative open paths resolve against, or clear it
+    /// with `None` to resolve them against the process working directory.
+    pub fn set_base_dir(&self, dir: Option<PathBuf>) {
+        *self.base_dir.write().unwrap_or_else(|e| e.into_inner()) = dir;
+    }
+
+    /// Resolve an open path: relative paths are joined onto the base dir, if
+    /// one is set; absolute paths are returned unchanged.
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        match &*self.base_dir.read().unwrap_or_else(|e| e.into_inner()) {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Open a database at the given filesystem path, resolved with `resolve_path`.
+    ///
+    /// If the path is already open, the new handle shares that database.
+    /// Otherwise, with `backup_on_open`, the directory is backed up first;
+    /// the backup's path is returned with the handle ID.
+    pub fn open(
+        &'static self,
+        path: &str,
+        config: OpenConfig,
+    ) -> Result<(u64, Option<PathBuf>), String> {
+        let path = self.resolve_path(path);
+        let journal = open_journal(&config)?;
+        let (strata, backup) = self
+            .open_shared(&path, config.backup_on_open)
+            .inspect_err(|e| diagnose::record_open_failure(&path, e))?;
+        if let Some(pattern) = config.access_pattern {
+            // Only a hint: the database is usable either way.
+            if let Err(e) = readahead::apply(&path, pattern) {
+                log::warn(&format!("readahead hint for {} failed: {e}", path.display()));
+            }
+        }
+        let mut entry = HandleEntry::new(strata, Some(path));
+        entry.meta.journal = journal;
+        entry.meta.config = config.clone();
+        let id = self.insert(entry);
+        self.start_autoflush(id, config);
+        Ok((id, backup))
+    }
+
+    /// The database open at `path`, opening it if no handle has it open yet.
+    ///
+    /// A fresh open with `backup_retain` set backs the directory up first,
+    /// keeping that many backups. Sharing an open database takes no backup.
+    fn open_shared(
+        &self,
+        path: &Path,
+        backup_retain: Option<u32>,
+    ) -> Result<(Arc<Strata>, Option<PathBuf>), String> {
+        let mut open_paths = self.open_paths.lock().unwrap_or_else(|e| e.into_inner());
+        open_paths.retain(|_, strata| strata.strong_count() > 0);
+
+        if let Some(strata) = open_paths.get(&path_key(path)).and_then(Weak::upgrade) {
+            return Ok((strata, None));
+        }
+        let backup = match backup_retain.filter(|&retain| retain > 0) {
+            Some(retain) => {
+                backup::backup(path, retain).map_err(|e| format!("backup on open failed: {e}"))?
+            }
+            None => None,
+        };
+        let strata = Arc::new(Strata::open(path).map_err(|e| e.to_string())?);
+        // Key by the path as it exists now, so opens through another spelling
+        // of it (a symlink, `..`) find this database.
+        open_paths.insert(path_key(path), Arc::downgrade(&strata));
+        Ok((strata, backup))
     }
 
     /// Open an in-memory (ephemeral) database.
-    pub fn open_memory(&self) -> Result<u64, String> {
+    ///
+    /// With `max_bytes` set, the handle's KV entries are capped (see `cache`).
+    /// `expected_entries` is a sizing hint for the bridge's own per-key
+    /// bookkeeping; stratadb takes none.
+    pub fn open_memory(&self, config: OpenConfig, expected_entries: usize) -> Result<u64, String> {
+        let journal = open_journal(&config)?;
         let strata = Strata::cache().map_err(|e| e.to_string())?;
+        let mut entry = HandleEntry::new(Arc::new(strata), None);
+        entry.meta.journal = journal;
+        let eviction = config.eviction.unwrap_or_default();
+        entry.meta.cap = config
+            .max_bytes
+            .map(|max| Arc::new(MemoryCap::new(max, eviction, expected_entries)));
+        entry.meta.config = config;
+        Ok(self.insert(entry))
+    }
+
+    /// Open a file-backed database in a fresh directory under the OS temp dir.
+    ///
+    /// The directory is deleted when the handle is closed.
+    /// Returns the handle ID and the chosen path.
+    pub fn open_temp(&'static self, config: OpenConfig) -> Result<(u64, PathBuf), String> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!(
+            "strata-foundry-{}-{}-{nanos}.strata",
+            std::process::id(),
+            self.next_id.load(Ordering::Relaxed),
+        ));
+
+        let journal = open_journal(&config)?;
+        let (strata, _) = self.open_shared(&dir, None).inspect_err(|_| {
+            let _ = std::fs::remove_dir_all(&dir);
+        })?;
+        let mut entry = HandleEntry::new(strata, Some(dir.clone()));
+        entry.meta.temp_dir = Some(dir.clone());
+        entry.meta.journal = journal;
+        entry.meta.config = config.clone();
+        let id = self.insert(entry);
+        self.start_autoflush(id, config);
+        Ok((id, dir))
+    }
+
+    /// For `interval` WAL sync, flush the handle's database on a background
+    /// thread every interval until the handle closes.
+    fn start_autoflush(&'static self, id: u64, config: OpenConfig) {
+        let Some(WalSync::Interval { ms }) = config.wal_sync else {
+            return;
+        };
+        let spawned = threads::spawn("autoflush", move || loop {
+            std::thread::sleep(Duration::from_millis(ms));
+            // Ends with the handle; IDs are never reused.
+            let Some(entry) = self.handles.get(&id) else {
+                return;
+            };
+            let result = guard(id, &entry, |strata| {
+                strata.flush().map_err(|e| BridgeError::from(e.to_string()))
+            });
+            drop(entry);
+            if let Err(e) = result {
+                log::warn(&format!("autoflush of handle {id} failed: {}", e.to_json()));
+            }
+        });
+        if let Err(e) = spawned {
+            log::warn(&format!("could not start autoflush for handle {id}: {e}"));
+        }
+    }
+
+    /// Take an ID from the handle ID space for something that is not a
+    /// handle, such as a snapshot, so the two never collide.
+    pub fn reserve_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn insert(&self, mut entry: HandleEntry) -> u64 {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
-        self.handles.insert(id, strata);
-        Ok(id)
+        let timeout_ms = entry.meta.config.default_timeout_ms.unwrap_or(0);
+        entry.meta.default_timeout_ms.store(timeout_ms, Ordering::Relaxed);
+        let rng = entry.meta.config.seed.map(fastrand::Rng::with_seed);
+        entry.meta.rng = rng.map(|rng| Arc::new(Mutex::new(rng)));
+        self.handles.insert(id, entry);
+        id
     }
 
-    /// Close a database handle.
+    /// Close a database handle, removing its directory if it was opened with `open_temp`.
+    ///
+    /// The database itself closes once no other handle shares it.
     pub fn close(&self, id: u64) {
-        self.handles.remove(&id);
+        if let Some((_, entry)) = self.handles.remove(&id) {
+            if let Err(e) = entry.meta.coalesce.flush_all(&entry.strata) {
+                log::warn(&format!("coalesced writes of handle {id} failed: {}", e.to_json()));
+            }
+            let temp_dir = entry.meta.temp_dir.clone();
+            // Drop the database before deleting the files underneath it.
+            drop(entry);
+            if let Some(dir) = temp_dir {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
     }
 
-    /// Execute a JSON command against a handle. Returns JSON output.
-    pub fn execute(&self, id: u64, command_json: &str) -> Result<String, String> {
-        let handle = self.handles.get(&id).ok_or("invalid handle")?;
+    /// Reopen a file-backed handle's database in place, keeping its ID.
+    ///
+    /// Recovers a faulted handle or one whose files were briefly unavailable.
+    /// The old database is closed before the new one opens, so file locks are
+    /// released; other handles sharing it move to the new one too. Every
+    /// sharer is faulted for the duration, so no command runs against the
+    /// closed database, and stays faulted with the error as its reason if
+    /// the open fails. The handles' settings are kept as they are.
+    pub fn reopen(&self, id: u64) -> Result<(), BridgeError> {
+        let (old, path) = {
+            let entry = self.handles.get(&id).ok_or("invalid handle")?;
+            let path = entry.meta.path.clone().ok_or_else(|| {
+                BridgeError::Kind(
+                    "InvalidInput",
+                    json!({ "reason": "in-memory handles cannot be reopened", "handle": id }),
+                )
+            })?;
+            (Arc::clone(&entry.strata), path)
+        };
+        let sharers: Vec<u64> = self
+            .handles
+            .iter()
+            .filter(|e| Arc::ptr_eq(&e.strata, &old))
+            .map(|e| *e.key())
+            .collect();
 
-        let cmd: Command =
-            serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+        // Fault each sharer and swap a throwaway in-memory database in under
+        // the entry's write lock: commands already running finish first, and
+        // later ones are refused rather than writing into the placeholder.
+        // The old database is then dropped, releasing its files.
+        let placeholder = Arc::new(Strata::cache().map_err(|e| e.to_string())?);
+        for sharer in &sharers {
+            if let Some(mut entry) = self.handles.get_mut(sharer) {
+                if let Err(e) = entry.meta.coalesce.flush_all(&entry.strata) {
+                    let e = e.to_json();
+                    log::warn(&format!("coalesced writes of handle {sharer} failed: {e}"));
+                }
+                entry.meta.mark_faulted("reopening".to_string());
+                entry.strata = Arc::clone(&placeholder);
+            }
+        }
+        drop(old);
+
+        let strata = match self.open_shared(&path, None) {
+            Ok((strata, _)) => strata,
+            Err(e) => {
+                let reason = format!("reopen failed: {e}");
+                for sharer in &sharers {
+                    if let Some(entry) = self.handles.get(sharer) {
+                        entry.meta.mark_faulted(reason.clone());
+                    }
+                }
+                return Err(faulted_error(id, Some(reason)));
+            }
+        };
+        for sharer in &sharers {
+            if let Some(mut entry) = self.handles.get_mut(sharer) {
+                entry.strata = Arc::clone(&strata);
+                // The reopened database may differ, so drop cached reads.
+                entry.meta.read_cache.clear();
+                entry.meta.clear_fault();
+            }
+        }
+        Ok(())
+    }
 
-        let output: Output = handle.executor().execute(cmd).map_err(|e| {
-            // Serialize the stratadb Error as JSON (it derives Serialize)
-            serde_json::to_string(&e)
-                .unwrap_or_else(|_| format!(r#"{{"Internal":{{"reason":"{e}"}}}}"#))
+    /// Copy the KV value at `key` on `src` to `dst_key` on `dst`, without the
+    /// value leaving Rust. Returns the version written on `dst`. The write
+    /// is held to `dst`'s value limits and memory cap and is journaled like a
+    /// `KvPut`.
+    pub fn copy_kv(&self, src: u64, dst: u64, key: &str, dst_key: &str) -> Result<u64, BridgeError> {
+        self.check_key_policy(src, &json!({ "KvGet": { "key": key } }))?;
+        self.check_key_policy(dst, &json!({ "KvPut": { "key": dst_key } }))?;
+        let value = self.run_guarded(src, |strata| {
+            let output = commands::call(&mut strata.executor(), json!({ "KvGet": { "key": key } }))?;
+            match commands::maybe_versioned(output) {
+                Some(mut record) => Ok(record["value"].take()),
+                None => Err(BridgeError::Kind("KeyNotFound", json!({ "key": key, "handle": src }))),
+            }
         })?;
+        let size = value.to_string().len() as u64;
+        let put = json!({ "KvPut": { "key": dst_key, "value": value } });
+        let version = self.run_put(dst, dst_key, size, |strata| {
+            let output = commands::call(&mut strata.executor(), put.clone())?;
+            Ok(commands::version(&output).unwrap_or_default())
+        })?;
+        self.journal(dst, &put);
+        Ok(version)
+    }
 
-        serde_json::to_string(&output).map_err(|e| format!("failed to serialize output: {e}"))
+    /// Run a put of `size` value bytes to `key` like `run_guarded`, within
+    /// the handle's value limits and memory cap.
+    pub fn run_put<T>(
+        &self,
+        id: u64,
+        key: &str,
+        size: u64,
+        put: impl FnOnce(&Strata) -> Result<T, BridgeError>,
+    ) -> Result<T, BridgeError> {
+        self.check_write(id, "KvPut")?;
+        self.limits(id).check(key, size)?;
+        let cap = self.memory_cap(id);
+        self.run_guarded(id, |strata| match &cap {
+            Some(cap) => cap.put(strata, key, size, put),
+            None => put(strata),
+        })
     }
+
+    /// The handle's memory cap, `None` if it has none.
+    pub fn memory_cap(&self, id: u64) -> Option<Arc<MemoryCap>> {
+        self.handles.get(&id).and_then(|entry| entry.meta.cap.clone())
+    }
+
+    /// Describe every open handle, ordered by ID.
+    pub fn list(&self) -> Vec<serde_json::Value> {
+        let mut ids: Vec<u64> = self.handles.iter().map(|e| *e.key()).collect();
+        ids.sort_unstable();
+        ids.into_iter()
+            .filter_map(|id| self.handles.get(&id).map(|entry| entry.describe(id)))
+            .collect()
+    }
+
+    /// IDs of the open handles on the database at `path`, resolved with
+    /// `resolve_path` and compared canonically, in order.
+    pub fn handles_for_path(&self, path: &str) -> Vec<u64> {
+        let key = path_key(&self.resolve_path(path));
+        let mut ids: Vec<u64> = self
+            .handles
+            .iter()
+            .filter(|e| e.meta.path.as_deref().is_some_and(|p| path_key(p) == key))
+            .map(|e| *e.key())
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The number of open handles and the faulted ones, as
+    /// `[{"handle", "reason"}]` sorted by handle ID. Only faulted handles
+    /// take a lock.
+    pub fn health(&self) -> (usize, Vec<serde_json::Value>) {
+        let mut faulted: Vec<(u64, Option<String>)> = self
+            .handles
+            .iter()
+            .filter(|entry| entry.meta.is_faulted())
+            .map(|entry| (*entry.key(), entry.meta.fault_reason()))
+            .collect();
+        faulted.sort_unstable_by_key(|(id, _)| *id);
+        let faulted = faulted
+            .into_iter()
+            .map(|(id, reason)| json!({ "handle": id, "reason": reason }))
+            .collect();
+        (self.handles.len(), faulted)
+    }
+
+    /// `{"free_bytes", "mount"}` for the filesystem a file-backed handle's
+    /// database is on, or `{"memory": true}` for an in-memory handle.
+    pub fn disk_free(&self, id: u64) -> Result<serde_json::Value, BridgeError> {
+        let path = self.handles.get(&id).ok_or("invalid handle")?.meta.path.clone();
+        let Some(path) = path else {
+            return Ok(json!({ "memory": true }));
+        };
+        let io_error = |e| format!("could not read free space of {}: {e}", path.display());
+        Ok(json!({
+            "free_bytes": disk::free_bytes(&path).map_err(io_error)?,
+            "mount": disk::mount_point(&path).map_err(io_error)?,
+        }))
+    }
+
+    /// Flush every file-backed handle; in-memory handles are skipped.
+    ///
+    /// A failure on one handle does not stop the sweep. Returns the number of
+    /// handles flushed and the errors of those that failed, keyed by handle ID.
+    pub fn flush_all(&self) -> (usize, Vec<(u64, BridgeError)>) {
+        let mut ids: Vec<u64> = self
+            .handles
+            .iter()
+            .filter(|e| e.meta.path.is_some())
+            .map(|e| *e.key())
+            .collect();
+        ids.sort_unstable();
+
+        let mut flushed = 0;
+        let mut errors = Vec::new();
+        for id in ids {
+            // Closed since the snapshot above — nothing left to flush.
+            let Some(entry) = self.handles.get(&id) else {
+                continue;
+            };
+            let result = guard(id, &entry, |strata| {
+                strata.flush().map_err(|e| BridgeError::from(e.to_string()))
+            });
+            match result {
+                Ok(()) => flushed += 1,
+                Err(e) => errors.push((id, e)),
+            }
+        }
+        (flushed, errors)
+    }
+
+    /// Write a handle's coalesced state sets, then flush its database.
+    pub fn flush(&self, id: u64) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        guard(id, &entry, |strata| {
+            entry.meta.coalesce.flush_all(strata)?;
+            strata.flush().map_err(|e| BridgeError::from(e.to_string()))
+        })
+    }
+
+    /// Refuse a command tagged `tag` on a handle opened `read_only` if it writes.
+    pub fn check_writable(&self, id: u64, tag: &str) -> Result<(), BridgeError> {
+        if audit::is_mutation(tag) || ALSO_WRITES.contains(&tag) {
+            self.check_write(id, tag)?;
+        }
+        Ok(())
+    }
+
+    /// Refuse `operation`, a write, on a handle opened `read_only`.
+    pub fn check_write(&self, id: u64, operation: &str) -> Result<(), BridgeError> {
+        if self.handles.get(&id).is_some_and(|e| e.meta.config.read_only) {
+            let details = json!({ "handle": id, "command": operation });
+            return Err(BridgeError::Kind("ReadOnly", details));
+        }
+        Ok(())
+    }
+
+    /// Write a handle's coalesced state sets, before reading its state
+    /// outside `execute`.
+    pub fn flush_coalesced(&self, id: u64) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        guard(id, &entry, |strata| entry.meta.coalesce.flush_all(strata))
+    }
+
+    /// Set a handle's key and value size limits in bytes. Zero means unlimited.
+    pub fn set_value_limits(
+        &self,
+        id: u64,
+        max_key_bytes: u64,
+        max_value_bytes: u64,
+    ) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.limits.set(max_key_bytes, max_value_bytes);
+        Ok(())
+    }
+
+    /// A handle's current size limits (unlimited for an unknown handle).
+    pub fn limits(&self, id: u64) -> Limits {
+        self.handles.get(&id).map(|e| e.meta.limits.snapshot()).unwrap_or_default()
+    }
+
+    /// Confine a handle's commands to the keys `policy` allows, or lift the
+    /// restriction with `None`.
+    pub fn set_key_policy(&self, id: u64, policy: Option<KeyPolicy>) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        *entry.meta.key_policy.write().unwrap_or_else(|e| e.into_inner()) = policy.map(Arc::new);
+        // Reads cached before the policy was set were never checked against it.
+        entry.meta.read_cache.clear();
+        Ok(())
+    }
+
+    /// Check the keys a parsed command names against the handle's key policy,
+    /// and for a summary scan (`KvAggregate`, `KvInferSchema`) the keys it
+    /// would read.
+    pub fn check_key_policy(
+        &self,
+        id: u64,
+        command: &serde_json::Value,
+    ) -> Result<(), BridgeError> {
+        let Some(policy) = self.key_policy(id) else {
+            return Ok(());
+        };
+        policy.check_command(command)?;
+        if policy::summary_body(command).is_some() {
+            self.run_guarded(id, |strata| policy.check_summary(strata, command))?;
+        }
+        Ok(())
+    }
+
+    /// Drop the keys the handle's key policy refuses from a listing output.
+    pub fn filter_keys(&self, id: u64, output: &mut serde_json::Value) {
+        if let Some(policy) = self.key_policy(id) {
+            policy.filter_output(output);
+        }
+    }
+
+    /// Refuse `operation`, which spans every key, on a handle with a key
+    /// policy.
+    pub fn check_unscoped(&self, id: u64, operation: &str) -> Result<(), BridgeError> {
+        match self.key_policy(id) {
+            Some(_) => Err(policy::unscoped(operation)),
+            None => Ok(()),
+        }
+    }
+
+    /// The handle's key policy, `None` when unrestricted.
+    pub fn key_policy(&self, id: u64) -> Option<Arc<KeyPolicy>> {
+        let entry = self.handles.get(&id)?;
+        let policy = entry.meta.key_policy.read().unwrap_or_else(|e| e.into_inner());
+        policy.clone()
+    }
+
+    /// Enable the audit trail with room for `capacity` entries, or disable
+    /// and discard it with zero. Re-enabling starts an empty log.
+    pub fn set_audit(&self, id: u64, capacity: usize) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        let mut audit = entry.meta.audit.lock().unwrap_or_else(|e| e.into_inner());
+        *audit = (capacity > 0).then(|| AuditLog::new(capacity));
+        Ok(())
+    }
+
+    /// The audit trail, oldest first; empty if auditing is off.
+    pub fn audit_entries(&self, id: u64) -> Result<Vec<serde_json::Value>, BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        let audit = entry.meta.audit.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(audit.as_ref().map(AuditLog::entries).unwrap_or_default())
+    }
+
+    /// Record a successful mutation in the handle's audit trail, if enabled.
+    pub fn audit(&self, id: u64, tag: &str, key: Option<&str>) {
+        if let Some(entry) = self.handles.get(&id) {
+            if let Some(log) = entry.meta.audit.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+                log.record(tag, key);
+            }
+        }
+    }
+
+    /// Append a successful mutation to the handle's journal, if it has one.
+    /// A failed append is logged; the command has already been applied.
+    pub fn journal(&self, id: u64, command: &serde_json::Value) {
+        let Some(entry) = self.handles.get(&id) else {
+            return;
+        };
+        if let Some(journal) = &entry.meta.journal {
+            if let Err(e) = journal.append(command) {
+                log::warn(&format!("journal append for handle {id} failed: {e}"));
+            }
+        }
+    }
+
+    pub fn is_journaled(&self, id: u64) -> bool {
+        self.handles.get(&id).is_some_and(|entry| entry.meta.journal.is_some())
+    }
+
+    fn is_audited(&self, id: u64) -> bool {
+        self.handles.get(&id).is_some_and(|entry| {
+            entry.meta.audit.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+        })
+    }
+
+    /// `{"uptime_ms", "cache", "coalesced", "read_cache"}` for `strata_stats`.
+    pub fn stats(&self, id: u64) -> Result<serde_json::Value, BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        Ok(json!({
+            "uptime_ms": entry.meta.uptime_ms(),
+            "cache": entry.meta.cap.as_ref().map(|cap| cap.stats()),
+            "coalesced": entry.meta.coalesce.stats(),
+            "read_cache": entry.meta.read_cache.stats(),
+        }))
+    }
+
+    /// Cache up to `max_entries` read outputs on the handle; zero disables
+    /// the cache.
+    pub fn set_read_cache(&self, id: u64, max_entries: usize) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.read_cache.resize(max_entries);
+        Ok(())
+    }
+
+    /// Free memory under pressure `level` on every handle: drop read
+    /// caches, and evict from in-memory LRU caches (see `pressure`).
+    /// Returns `{"level", "read_cache_entries", "evicted_keys", "evicted_bytes"}`.
+    pub fn relieve_memory_pressure(&self, level: i32) -> serde_json::Value {
+        let percent = pressure::eviction_percent(level);
+        let (mut read_cache_entries, mut evicted_keys, mut evicted_bytes) = (0, 0, 0);
+        let ids: Vec<u64> = self.handles.iter().map(|e| *e.key()).collect();
+        for id in ids {
+            let Some(entry) = self.handles.get(&id) else {
+                continue;
+            };
+            if let Some(cap) = entry.meta.cap.as_ref().filter(|_| percent > 0) {
+                match guard(id, &entry, |strata| cap.shed(strata, percent)) {
+                    Ok((keys, bytes)) => {
+                        evicted_keys += keys;
+                        evicted_bytes += bytes;
+                    }
+                    Err(e) => log::warn(&format!(
+                        "memory pressure eviction on handle {id} failed: {}",
+                        e.to_json()
+                    )),
+                }
+            }
+            read_cache_entries += entry.meta.read_cache.release();
+        }
+        json!({
+            "level": level,
+            "read_cache_entries": read_cache_entries,
+            "evicted_keys": evicted_keys,
+            "evicted_bytes": evicted_bytes,
+        })
+    }
+
+    /// Drop the cached reads a successful write tagged `tag` may have
+    /// changed, or all of them for `None`, on every handle sharing the
+    /// database.
+    pub fn invalidate_reads(&self, id: u64, tag: Option<&str>) {
+        let Some(strata) = self.handles.get(&id).map(|e| Arc::clone(&e.strata)) else {
+            return;
+        };
+        for entry in self.handles.iter().filter(|e| Arc::ptr_eq(&e.strata, &strata)) {
+            match tag {
+                Some(tag) => entry.meta.read_cache.invalidate(tag),
+                None => entry.meta.read_cache.clear(),
+            }
+        }
+    }
+
+    /// Coalesce `StateSet`s to `cell`, writing the latest once every
+    /// `interval_ms` from a background thread; zero stops coalescing it,
+    /// writing any value it holds.
+    pub fn set_state_coalesce(
+        &'static self,
+        id: u64,
+        cell: &str,
+        interval_ms: u64,
+    ) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        let coalesce = Arc::clone(&entry.meta.coalesce);
+        if interval_ms == 0 {
+            return guard(id, &entry, |strata| coalesce.disable(strata, cell));
+        }
+        drop(entry);
+
+        let generation = coalesce.enable(cell);
+        let cell = cell.to_string();
+        threads::spawn("coalesce", move || loop {
+            std::thread::sleep(Duration::from_millis(interval_ms));
+            // Ends with the handle; IDs are never reused.
+            let Some(entry) = self.handles.get(&id) else {
+                return;
+            };
+            let flushed =
+                guard(id, &entry, |strata| Ok(coalesce.flush_cell(strata, &cell, generation)));
+            drop(entry);
+            if !flushed.unwrap_or(false) {
+                return;
+            }
+        })
+        .map(drop)
+        .map_err(|e| BridgeError::from(format!("could not start coalescing thread: {e}")))
+    }
+
+    /// p50/p95/p99 latencies per command tag, for `strata_stats_latency`.
+    pub fn latency(&self, id: u64) -> Result<serde_json::Value, BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        Ok(entry.meta.latencies.summary())
+    }
+
+    /// Clear a handle's latency histograms.
+    pub fn reset_stats(&self, id: u64) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.latencies.reset();
+        Ok(())
+    }
+
+    /// The options a handle was opened with.
+    pub fn config(&self, id: u64) -> Result<OpenConfig, BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        let timeout_ms = entry.meta.default_timeout_ms.load(Ordering::Relaxed);
+        let default_timeout_ms = Some(timeout_ms).filter(|ms| *ms > 0);
+        Ok(OpenConfig { default_timeout_ms, ..entry.meta.config.clone() })
+    }
+
+    /// Bound every `strata_execute` call on the handle; zero is unbounded.
+    pub fn set_default_timeout_ms(&self, id: u64, ms: u64) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.default_timeout_ms.store(ms, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// `{"lazy", "warm"}`: whether the handle's loading was deferred, and
+    /// whether its indexes are loaded. stratadb loads them while opening and
+    /// has no deferred mode, so no handle is lazy and every open one is warm,
+    /// whatever its `lazy` option says.
+    pub fn warm_status(&self, id: u64) -> Result<serde_json::Value, BridgeError> {
+        self.handles.get(&id).ok_or("invalid handle")?;
+        Ok(json!({ "lazy": false, "warm": true }))
+    }
+
+    /// Pin or unpin the handle against `strata_close`.
+    pub fn set_pinned(&self, id: u64, pinned: bool) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.pinned.store(pinned, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn is_pinned(&self, id: u64) -> bool {
+        self.handles.get(&id).is_some_and(|e| e.meta.pinned.load(Ordering::Relaxed))
+    }
+
+    pub fn default_timeout_ms(&self, id: u64) -> u64 {
+        self.handles.get(&id).map_or(0, |e| e.meta.default_timeout_ms.load(Ordering::Relaxed))
+    }
+
+    /// Sort list outputs by key on this handle, for reproducible results.
+    pub fn set_deterministic_iteration(&self, id: u64, enabled: bool) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.deterministic.store(enabled, Ordering::Relaxed);
+        entry.meta.read_cache.clear();
+        Ok(())
+    }
+
+    fn syncs_every_write(&self, id: u64) -> bool {
+        self.handles.get(&id).is_some_and(|e| e.meta.config.wal_sync == Some(WalSync::Always))
+    }
+
+    fn is_deterministic(&self, id: u64) -> bool {
+        self.handles.get(&id).is_some_and(|e| e.meta.deterministic.load(Ordering::Relaxed))
+    }
+
+    /// Serialize `Int` values in this handle's outputs as strings, e.g.
+    /// `{"Int": "152847"}`, for hosts that would lose 64-bit precision.
+    pub fn set_int_as_string(&self, id: u64, enabled: bool) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.int_as_string.store(enabled, Ordering::Relaxed);
+        entry.meta.read_cache.clear();
+        Ok(())
+    }
+
+    fn is_int_as_string(&self, id: u64) -> bool {
+        self.handles.get(&id).is_some_and(|e| e.meta.int_as_string.load(Ordering::Relaxed))
+    }
+
+    /// Accept `//` comments and trailing commas in this handle's commands.
+    pub fn set_tolerant_json(&self, id: u64, enabled: bool) -> Result<(), BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        entry.meta.tolerant_json.store(enabled, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// `command_json` made strict, if the handle is tolerant; `None` means
+    /// use it as is.
+    pub fn relax_json(&self, id: u64, command_json: &str) -> Option<String> {
+        let tolerant =
+            self.handles.get(&id).is_some_and(|e| e.meta.tolerant_json.load(Ordering::Relaxed));
+        tolerant.then(|| tolerant::strip(command_json))
+    }
+
+    /// Remember a failed command's error as the handle's last error.
+    pub fn record_error(&self, id: u64, tag: Option<String>, error: serde_json::Value) {
+        if let Some(entry) = self.handles.get(&id) {
+            let record = json!({ "command": tag, "error": error, "at_ms": unix_millis() });
+            *entry.meta.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(record);
+        }
+    }
+
+    /// The handle's most recent command error, or `None` if none has failed.
+    pub fn last_error(&self, id: u64) -> Result<Option<serde_json::Value>, BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        let last_error = entry.meta.last_error.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(last_error.clone())
+    }
+
+    /// Milliseconds since the handle was opened, or `None` for an unknown handle.
+    pub fn uptime_ms(&self, id: u64) -> Option<u64> {
+        self.handles.get(&id).map(|entry| entry.meta.uptime_ms())
+    }
+
+    /// Execute a JSON command against a handle. Returns JSON output.
+    ///
+    /// Accepts the compact positional form (`["KvGet", "k"]`) for the
+    /// commands listed in `compact`, as well as the object envelope. Read
+    /// commands may carry a `fields` projection (see `project`). With a read
+    /// cache enabled, a cacheable read repeated verbatim returns the cached
+    /// output without running (see `readcache`).
+    pub fn execute(&self, id: u64, command_json: &str) -> Result<String, BridgeError> {
+        let cache = match self.handles.get(&id) {
+            Some(entry) if !entry.meta.is_faulted() => Arc::clone(&entry.meta.read_cache),
+            _ => return self.execute_uncached(id, command_json),
+        };
+        let Some(tag) = command_tag(command_json) else {
+            return self.execute_uncached(id, command_json);
+        };
+        if readcache::is_cacheable(&tag) {
+            if !cache.is_enabled() {
+                return self.execute_uncached(id, command_json);
+            }
+            let generation = match cache.get(command_json) {
+                Ok(output) => return Ok(output),
+                Err(generation) => generation,
+            };
+            let output = self.execute_uncached(id, command_json)?;
+            cache.insert(&tag, command_json, &output, generation);
+            return Ok(output);
+        }
+        let result = self.execute_uncached(id, command_json);
+        self.invalidate_reads(id, Some(&tag));
+        result
+    }
+
+    fn execute_uncached(&self, id: u64, command_json: &str) -> Result<String, BridgeError> {
+        let max_command_bytes = self.config(id)?.max_command_bytes;
+        if let Some(max) = max_command_bytes.filter(|&max| command_json.len() as u64 > max) {
+            return Err(compress::too_large(max));
+        }
+        let expanded;
+        let command_json = match compact::expand(command_json) {
+            Some(result) => {
+                expanded = result?;
+                expanded.as_str()
+            }
+            None => command_json,
+        };
+        let projected;
+        let (command_json, fields) = match project::split(command_json)? {
+            Some((command, fields)) => {
+                projected = command;
+                (projected.as_str(), Some(fields))
+            }
+            None => (command_json, None),
+        };
+        let policy = self.key_policy(id);
+        if policy.is_some() {
+            // Only pay for parsing out the keys when the handle is restricted.
+            let command: serde_json::Value = serde_json::from_str(command_json).unwrap_or_default();
+            self.check_key_policy(id, &command)?;
+        }
+        if let Some(tag) = command_tag(command_json) {
+            self.check_writable(id, &tag)?;
+        }
+        let limits = self.limits(id);
+        let (cap, coalesce, rng) = match self.handles.get(&id) {
+            Some(entry) => (
+                entry.meta.cap.clone(),
+                Some(Arc::clone(&entry.meta.coalesce)),
+                entry.meta.rng.clone(),
+            ),
+            None => (None, None, None),
+        };
+        let output = self.run_timed(id, command_json, |strata| {
+            let _seeded = rng.as_deref().map(SeededThread::enter);
+            if let Some(coalesce) = &coalesce {
+                if let Some(output) = coalesce.intercept(strata, command_json, limits)? {
+                    return Ok(output);
+                }
+            }
+            // Fast path: a stratadb command with a readable tag is parsed
+            // straight into `Command`, skipping the intermediate `Value`.
+            // Size limits need the parsed command, so they take the general path.
+            let run = |strata: &Strata| match commands::peek_tag(command_json) {
+                Some(tag)
+                    if limits.is_unlimited()
+                        && !commands::is_bridge_command(tag)
+                        && !commands::is_extended(tag) =>
+                {
+                    execute_direct(strata, command_json)
+                }
+                _ => execute_general(strata, command_json, limits),
+            };
+            match &cap {
+                Some(cap) => cap.run(strata, command_json, run),
+                None => run(strata),
+            }
+        })?;
+
+        let output = match &policy {
+            Some(policy) => policy.filter_output_json(output),
+            None => output,
+        };
+        let output = match &fields {
+            Some(fields) => project::apply(output, fields),
+            None => output,
+        };
+        let output = if self.is_deterministic(id) { sorted_output(output) } else { output };
+        let output = if self.is_int_as_string(id) { ints_as_strings(output) } else { output };
+
+        // `always` WAL sync: make each write durable before returning.
+        let is_write = || command_tag(command_json).is_some_and(|tag| audit::is_mutation(&tag));
+        if self.syncs_every_write(id) && is_write() {
+            self.run_guarded(id, |strata| {
+                strata.flush().map_err(|e| BridgeError::from(e.to_string()))
+            })?;
+        }
+
+        if self.is_journaled(id) && is_write() {
+            let command: serde_json::Value = serde_json::from_str(command_json).unwrap_or_default();
+            self.journal(id, &command);
+        }
+
+        if self.is_audited(id) {
+            // Only pay for parsing out the key when the handle is audited.
+            let command: serde_json::Value = serde_json::from_str(command_json).unwrap_or_default();
+            if let Some((tag, body)) = command.as_object().and_then(|m| m.iter().next()) {
+                if audit::is_mutation(tag) {
+                    self.audit(id, tag, audit::target_key(body));
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Run `f` like `run_guarded` in one of the handle's command slots,
+    /// logging a warning if it exceeds the slow-command threshold.
+    pub(crate) fn run_timed<T>(
+        &self,
+        id: u64,
+        command_json: &str,
+        f: impl FnOnce(&Strata) -> Result<T, BridgeError>,
+    ) -> Result<T, BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        let config = &entry.meta.config;
+        let _slot = entry.meta.slots.acquire(config.max_concurrent_commands, config.block)?;
+        let started = Instant::now();
+        let result = guard(id, &entry, f);
+        let elapsed = started.elapsed();
+        match commands::peek_tag(command_json) {
+            Some(tag) => entry.meta.latencies.record(tag, elapsed),
+            None => {
+                let tag = command_tag(command_json).unwrap_or_else(|| "<unknown>".to_string());
+                entry.meta.latencies.record(&tag, elapsed);
+            }
+        }
+        self.report_if_slow(id, command_json, elapsed);
+        result
+    }
+
+    fn report_if_slow(&self, id: u64, command_json: &str, elapsed: Duration) {
+        let threshold_ms = self.slow_command_threshold_ms.load(Ordering::Relaxed);
+        if threshold_ms == 0 || elapsed < Duration::from_millis(threshold_ms) {
+            return;
+        }
+        // Only pay for extracting the tag once we know the command was slow.
+        let tag = command_tag(command_json).unwrap_or_else(|| "<unknown>".to_string());
+        log::warn(&format!(
+            "slow command: {tag} on handle {id} took {}ms (threshold {threshold_ms}ms)",
+            elapsed.as_millis()
+        ));
+    }
+
+    /// Run `f` against a handle's database, faulting the handle if it panics.
+    ///
+    /// A panic mid-command may leave the `Strata` in a questionable state, so
+    /// rather than letting later commands silently hit it again, the handle is
+    /// marked faulted and every subsequent call returns `HandleFaulted`.
+    pub(crate) fn run_guarded<T>(
+        &self,
+        id: u64,
+        f: impl FnOnce(&Strata) -> Result<T, BridgeError>,
+    ) -> Result<T, BridgeError> {
+        let entry = self.handles.get(&id).ok_or("invalid handle")?;
+        guard(id, &entry, f)
+    }
+}
+
+/// The calling thread's `fastrand` generator seeded from a handle's, for
+/// one command; the thread's own generator is restored on drop.
+struct SeededThread {
+    saved: u64,
+}
+
+impl SeededThread {
+    fn enter(rng: &Mutex<fastrand::Rng>) -> Self {
+        let saved = fastrand::get_seed();
+        fastrand::seed(rng.lock().unwrap_or_else(|e| e.into_inner()).u64(..));
+        Self { saved }
+    }
+}
+
+impl Drop for SeededThread {
+    fn drop(&mut self) {
+        fastrand::seed(self.saved);
+    }
+}
+
+/// The body of `run_guarded` for an entry the caller has already looked up.
+fn guard<T>(
+    id: u64,
+    entry: &HandleEntry,
+    f: impl FnOnce(&Strata) -> Result<T, BridgeError>,
+) -> Result<T, BridgeError> {
+    if entry.meta.is_faulted() {
+        return Err(faulted_error(id, entry.meta.fault_reason()));
+    }
+
+    match catch_unwind(AssertUnwindSafe(|| f(&entry.strata))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let reason = panic_message(payload.as_ref());
+            record_panic(&reason);
+            entry.meta.mark_faulted(reason.clone());
+            Err(faulted_error(id, Some(reason)))
+        }
+    }
+}
+
+/// Parse `command_json` as a stratadb `Command` and run it.
+pub(crate) fn execute_direct(strata: &Strata, command_json: &str) -> Result<String, BridgeError> {
+    let cmd: Command =
+        serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+    run_command(strata, cmd)
+}
+
+/// Parse `command_json` as a `Value`, check it against `limits`, and run it
+/// as a bridge command if it is one, otherwise as a stratadb `Command`.
+/// Handles every command shape.
+pub(crate) fn execute_general(
+    strata: &Strata,
+    command_json: &str,
+    limits: Limits,
+) -> Result<String, BridgeError> {
+    let value: serde_json::Value =
+        serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+    limits.check_command(&value)?;
+    if let Some(result) = commands::dispatch(strata, &value) {
+        return Ok(result?.to_string());
+    }
+
+    let cmd: Command =
+        serde_json::from_value(value).map_err(|e| format!("invalid command JSON: {e}"))?;
+    run_command(strata, cmd)
+}
+
+fn run_command(strata: &Strata, cmd: Command) -> Result<String, BridgeError> {
+    let output: Output = strata.executor().execute(cmd).map_err(|e| {
+        // Serialize the stratadb Error as JSON (it derives Serialize)
+        serde_json::to_string(&e)
+            .unwrap_or_else(|_| format!(r#"{{"Internal":{{"reason":"{e}"}}}}"#))
+    })?;
+
+    serde_json::to_string(&output)
+        .map_err(|e| BridgeError::from(format!("failed to serialize output: {e}")))
+}
+
+/// Sort the rows of a list output by key. Anything else is returned as is.
+fn sorted_output(output: String) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&output) else {
+        return output;
+    };
+    let Some((tag, payload)) = value.as_object_mut().and_then(|m| m.iter_mut().next()) else {
+        return output;
+    };
+    let (rows, sort_key): (_, fn(&serde_json::Value) -> String) = match tag.as_str() {
+        "Keys" => (payload.as_array_mut(), |row| row.as_str().unwrap_or_default().to_string()),
+        "JsonListResult" => (payload["keys"].as_array_mut(), |row| {
+            row.as_str().unwrap_or_default().to_string()
+        }),
+        "VectorCollectionList" => (payload.as_array_mut(), |row| {
+            row["name"].as_str().unwrap_or_default().to_string()
+        }),
+        "BranchInfoList" => (payload.as_array_mut(), |row| {
+            row["info"]["id"].as_str().unwrap_or_default().to_string()
+        }),
+        _ => return output,
+    };
+    match rows {
+        Some(rows) => {
+            rows.sort_by_cached_key(sort_key);
+            value.to_string()
+        }
+        None => output,
+    }
+}
+
+/// Rewrite every tagged `{"Int": n}` in an output as `{"Int": "n"}`.
+///
+/// Any single-key `{"Int": n}` object is taken for a tagged `Int`, so the
+/// outputs of `PLAIN_OUTPUTS` commands are left alone: their plain JSON has
+/// no tagged values, and a document may itself be `{"Int": n}`.
+fn ints_as_strings(output: String) -> String {
+    fn rewrite(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                if map.len() == 1 {
+                    if let Some(int) = map.get_mut("Int").filter(|n| n.is_i64() || n.is_u64()) {
+                        *int = serde_json::Value::String(int.to_string());
+                        return;
+                    }
+                }
+                map.values_mut().for_each(rewrite);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(rewrite),
+            _ => {}
+        }
+    }
+
+    match serde_json::from_str::<serde_json::Value>(&output) {
+        Ok(value) if PLAIN_OUTPUTS.iter().any(|tag| value.get(tag).is_some()) => output,
+        Ok(mut value) => {
+            rewrite(&mut value);
+            value.to_string()
+        }
+        Err(_) => output,
+    }
+}
+
+/// Open the journal `config` names, if any.
+fn open_journal(config: &OpenConfig) -> Result<Option<Journal>, String> {
+    let Some(path) = &config.journal_path else {
+        return Ok(None);
+    };
+    Journal::open(Path::new(path), config.journal_fsync)
+        .map(Some)
+        .map_err(|e| format!("failed to open journal {path}: {e}"))
+}
+
+/// The key under which a database path is registered: canonical if the path
+/// exists, otherwise absolute.
+fn path_key(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Time returned by `unix_millis` while pinned by `set_fixed_clock`;
+/// negative when the real clock is in use.
+static FIXED_CLOCK_MS: AtomicI64 = AtomicI64::new(-1);
+
+/// Pin the time every bridge timestamp uses, or restore the real clock with `None`.
+pub fn set_fixed_clock(millis: Option<u64>) {
+    let millis = millis.map_or(-1, |ms| ms.min(i64::MAX as u64) as i64);
+    FIXED_CLOCK_MS.store(millis, Ordering::Relaxed);
+}
+
+/// Current wall-clock time in milliseconds since the Unix epoch, or the
+/// fixed time set with `set_fixed_clock`.
+pub fn unix_millis() -> u64 {
+    let fixed = FIXED_CLOCK_MS.load(Ordering::Relaxed);
+    if fixed >= 0 {
+        return fixed as u64;
+    }
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// The externally-tagged variant name of a command, e.g. `"KvGet"`.
+pub fn command_tag(command_json: &str) -> Option<String> {
+    if let Some(tag) = commands::peek_tag(command_json) {
+        return Some(tag.to_string());
+    }
+    match serde_json::from_str(command_json).ok()? {
+        serde_json::Value::Object(map) => map.keys().next().cloned(),
+        serde_json::Value::String(tag) => Some(tag),
+        // Compact form: `["KvGet", ...]`.
+        serde_json::Value::Array(items) => items.first()?.as_str().map(String::from),
+        _ => None,
+    }
+}
+
+fn faulted_error(id: u64, reason: Option<String>) -> BridgeError {
+    BridgeError::Kind(
+        "HandleFaulted",
+        json!({
+            "handle": id,
+            "reason": reason,
+            "hint": "close and reopen the handle",
+        }),
+    )
 }
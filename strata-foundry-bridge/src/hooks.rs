@@ -0,0 +1,55 @@
+//! Host command hooks — run before and after every `strata_execute`.
+//!
+//! Swift registers C function pointers with `strata_set_command_hook`; with
+//! none registered, commands run without the bookkeeping.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::RwLock;
+
+use serde_json::json;
+
+use crate::error::BridgeError;
+use crate::handle::command_tag;
+
+/// Called before a command with its tag and JSON; a nonzero return rejects it.
+/// Both strings are null-terminated and valid only for the duration of the call.
+pub type PreHook = extern "C" fn(tag: *const c_char, command_json: *const c_char) -> i32;
+
+/// Called after a command succeeds with its tag and output JSON, valid only
+/// for the duration of the call.
+pub type PostHook = extern "C" fn(tag: *const c_char, output_json: *const c_char);
+
+static HOOKS: RwLock<(Option<PreHook>, Option<PostHook>)> = RwLock::new((None, None));
+
+/// Register (or clear, with `None`) the hooks. Each is set independently.
+pub fn set(pre: Option<PreHook>, post: Option<PostHook>) {
+    *HOOKS.write().unwrap_or_else(|e| e.into_inner()) = (pre, post);
+}
+
+/// Run the pre hook, if any. A nonzero return becomes a `Rejected` error.
+pub fn before(command_json: &str) -> Result<(), BridgeError> {
+    let (pre, _) = *HOOKS.read().unwrap_or_else(|e| e.into_inner());
+    let Some(pre) = pre else {
+        return Ok(());
+    };
+    let tag = command_tag(command_json).unwrap_or_default();
+    let code = pre(c_string(&tag).as_ptr(), c_string(command_json).as_ptr());
+    if code != 0 {
+        return Err(BridgeError::Kind("Rejected", json!({ "command": tag, "code": code })));
+    }
+    Ok(())
+}
+
+/// Run the post hook, if any, with a successful command's output.
+pub fn after(command_json: &str, output_json: &str) {
+    let (_, post) = *HOOKS.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(post) = post {
+        let tag = command_tag(command_json).unwrap_or_default();
+        post(c_string(&tag).as_ptr(), c_string(output_json).as_ptr());
+    }
+}
+
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', " ")).unwrap_or_default()
+}
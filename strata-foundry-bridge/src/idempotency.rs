@@ -0,0 +1,110 @@
+//! Idempotency keys: remember a command's result so a retry under the same
+//! key returns it instead of applying the command again.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde_json::json;
+
+use crate::error::BridgeError;
+
+/// Results remembered per handle; the oldest are evicted beyond this.
+const CAPACITY: usize = 1024;
+/// How long a result is remembered.
+const TTL: Duration = Duration::from_secs(10 * 60);
+
+struct Entry {
+    /// The command in canonical JSON, to catch a key reused for another command.
+    command: String,
+    output: String,
+    stored_at: Instant,
+}
+
+/// One handle's remembered results, oldest first in `order`.
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<String, Entry>,
+    order: VecDeque<String>,
+}
+
+impl Cache {
+    /// Drop expired results, then the oldest while over capacity.
+    fn evict(&mut self, now: Instant) {
+        while let Some(key) = self.order.front() {
+            let expired = self
+                .entries
+                .get(key)
+                .is_none_or(|e| now.duration_since(e.stored_at) >= TTL);
+            if !expired && self.entries.len() < CAPACITY {
+                break;
+            }
+            if let Some(key) = self.order.pop_front() {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// Per-handle idempotency caches, keyed by handle ID.
+pub struct IdempotencyRegistry {
+    caches: DashMap<u64, Arc<Mutex<Cache>>>,
+}
+
+impl IdempotencyRegistry {
+    pub fn new() -> Self {
+        Self {
+            caches: DashMap::new(),
+        }
+    }
+
+    /// Run `execute` for `command_json` unless `key` already has a result on
+    /// `handle`, in which case that result is returned without running it.
+    ///
+    /// Only successful results are remembered, so a failed command can be
+    /// retried under the same key. Reusing a key for a different command is
+    /// an `IdempotencyKeyReused` error. Idempotent commands on one handle run
+    /// one at a time, so concurrent retries cannot both apply.
+    pub fn execute(
+        &self,
+        handle: u64,
+        key: &str,
+        command_json: &str,
+        execute: impl FnOnce() -> Result<String, BridgeError>,
+    ) -> Result<String, BridgeError> {
+        let command: serde_json::Value = serde_json::from_str(command_json)
+            .map_err(|e| format!("invalid command JSON: {e}"))?;
+        let command = command.to_string();
+
+        let cache = Arc::clone(&self.caches.entry(handle).or_default());
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        cache.evict(now);
+
+        if let Some(entry) = cache.entries.get(key) {
+            if entry.command != command {
+                return Err(BridgeError::Kind(
+                    "IdempotencyKeyReused",
+                    json!({ "key": key, "reason": "key was used for a different command" }),
+                ));
+            }
+            return Ok(entry.output.clone());
+        }
+
+        let output = execute()?;
+        cache.order.push_back(key.to_string());
+        let entry = Entry {
+            command,
+            output: output.clone(),
+            stored_at: now,
+        };
+        cache.entries.insert(key.to_string(), entry);
+        Ok(output)
+    }
+
+    /// Forget a handle's results, as it closes.
+    pub fn close_handle(&self, handle: u64) {
+        self.caches.remove(&handle);
+    }
+}
@@ -0,0 +1,41 @@
+//! Mutation journal (`journal_path` in `config_json`).
+//!
+//! Each successful mutating command is appended to the journal file as one
+//! JSON line, `{"at_ms": <unix millis>, "command": {...}}`, in the order the
+//! commands completed. Reads are not journaled. With `journal_fsync`, each
+//! line is synced to disk before the command returns.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use crate::handle::unix_millis;
+
+pub struct Journal {
+    file: Mutex<File>,
+    fsync: bool,
+}
+
+impl Journal {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: &Path, fsync: bool) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(file), fsync })
+    }
+
+    /// Append one command.
+    pub fn append(&self, command: &Value) -> io::Result<()> {
+        let mut line = json!({ "at_ms": unix_millis(), "command": command }).to_string();
+        line.push('\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        // One write per line, so concurrent appenders never interleave.
+        file.write_all(line.as_bytes())?;
+        if self.fsync {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Per-command latency histograms (`strata_stats_latency`).
+//!
+//! Each command tag gets a log-linear histogram: exact buckets below 16µs,
+//! then eight buckets per power of two, so any recorded latency is off by at
+//! most 12.5%. Recording is one bucket increment under the handle's lock.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+/// Sub-buckets per power of two.
+const SUB_BUCKETS: u64 = 8;
+/// Values below this get one bucket each.
+const LINEAR: u64 = 16;
+/// Enough buckets for any `u64` of microseconds.
+const BUCKETS: usize = (LINEAR + (64 - 4) * SUB_BUCKETS) as usize;
+
+struct Histogram {
+    counts: Box<[u64; BUCKETS]>,
+    total: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self { counts: Box::new([0; BUCKETS]), total: 0, max_us: 0 }
+    }
+
+    fn record(&mut self, us: u64) {
+        self.counts[bucket(us)] += 1;
+        self.total += 1;
+        self.max_us = self.max_us.max(us);
+    }
+
+    /// The upper bound of the bucket holding quantile `q`, capped at the
+    /// largest value recorded.
+    fn percentile(&self, q: f64) -> u64 {
+        let rank = ((q * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return upper_bound(index).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+
+    fn summary(&self) -> Value {
+        json!({
+            "count": self.total,
+            "p50_us": self.percentile(0.50),
+            "p95_us": self.percentile(0.95),
+            "p99_us": self.percentile(0.99),
+            "max_us": self.max_us,
+        })
+    }
+}
+
+fn bucket(us: u64) -> usize {
+    if us < LINEAR {
+        return us as usize;
+    }
+    let exponent = 63 - u64::from(us.leading_zeros());
+    let sub = (us >> (exponent - 3)) & (SUB_BUCKETS - 1);
+    (LINEAR + (exponent - 4) * SUB_BUCKETS + sub) as usize
+}
+
+fn upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR {
+        return index;
+    }
+    let exponent = (index - LINEAR) / SUB_BUCKETS + 4;
+    let sub = (index - LINEAR) % SUB_BUCKETS;
+    let width = 1u64 << (exponent - 3);
+    ((SUB_BUCKETS + sub) << (exponent - 3)).saturating_add(width - 1)
+}
+
+/// A handle's latency histograms, keyed by command tag.
+#[derive(Default)]
+pub struct Latencies {
+    by_tag: Mutex<HashMap<String, Histogram>>,
+}
+
+impl Latencies {
+    pub fn record(&self, tag: &str, elapsed: Duration) {
+        let us = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let mut by_tag = self.by_tag.lock().unwrap_or_else(|e| e.into_inner());
+        match by_tag.get_mut(tag) {
+            Some(histogram) => histogram.record(us),
+            None => {
+                let mut histogram = Histogram::new();
+                histogram.record(us);
+                by_tag.insert(tag.to_string(), histogram);
+            }
+        }
+    }
+
+    /// `{"<tag>": {"count", "p50_us", "p95_us", "p99_us", "max_us"}}`
+    pub fn summary(&self) -> Value {
+        let by_tag = self.by_tag.lock().unwrap_or_else(|e| e.into_inner());
+        let summary: Map<String, Value> =
+            by_tag.iter().map(|(tag, h)| (tag.clone(), h.summary())).collect();
+        Value::Object(summary)
+    }
+
+    pub fn reset(&self) {
+        self.by_tag.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
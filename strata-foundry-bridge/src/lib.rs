@@ -3,16 +3,84 @@
 //! All complex types cross the FFI boundary as JSON strings.
 //! Integer handle IDs are used instead of raw pointers.
 
+// Every `extern "C"` entry point takes C string pointers from Swift and checks
+// them for null via `cstr_to_str`; marking them all `unsafe` would not change
+// the C ABI or make the Swift side any safer.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+mod audit;
+mod backup;
+mod batch;
+mod cache;
+mod capabilities;
+mod coalesce;
+mod commands;
+mod compact;
+mod compress;
+mod config;
+mod diagnose;
+mod disk;
+mod error;
+mod group;
 mod handle;
+mod hooks;
+mod idempotency;
+mod journal;
+mod latency;
+mod limits;
+mod log;
+mod policy;
+mod pressure;
+mod project;
+mod readahead;
+mod readcache;
+mod recovery;
+mod safe_free;
+mod schema;
+mod slots;
+mod snapshot;
+mod stream;
+mod threads;
+mod tolerant;
+mod txn;
+mod watch;
 
 use std::ffi::{CStr, CString};
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_void};
 
+use config::OpenConfig;
+use error::BridgeError;
+use group::GroupRegistry;
 use handle::HandleRegistry;
+use idempotency::IdempotencyRegistry;
+use snapshot::SnapshotRegistry;
+use stream::StreamRegistry;
+use txn::TxnRegistry;
+use watch::WatchRegistry;
 
 /// Global handle registry — manages all open database handles and sessions.
 static REGISTRY: std::sync::LazyLock<HandleRegistry> = std::sync::LazyLock::new(HandleRegistry::new);
 
+/// Global stream registry — pull-based cursors opened with `strata_stream_open`.
+static STREAMS: std::sync::LazyLock<StreamRegistry> = std::sync::LazyLock::new(StreamRegistry::new);
+
+/// Global registry of open transactions.
+static TXNS: std::sync::LazyLock<TxnRegistry> = std::sync::LazyLock::new(TxnRegistry::new);
+
+/// Global registry of handle groups from `strata_open_group`.
+static GROUPS: std::sync::LazyLock<GroupRegistry> = std::sync::LazyLock::new(GroupRegistry::new);
+
+/// Global registry of read snapshots from `strata_snapshot_begin`.
+static SNAPSHOTS: std::sync::LazyLock<SnapshotRegistry> =
+    std::sync::LazyLock::new(SnapshotRegistry::new);
+
+/// Global per-handle caches for `strata_execute_idempotent`.
+static IDEMPOTENCY: std::sync::LazyLock<IdempotencyRegistry> =
+    std::sync::LazyLock::new(IdempotencyRegistry::new);
+
+/// Global registry of KV prefix subscriptions.
+static WATCHES: std::sync::LazyLock<WatchRegistry> = std::sync::LazyLock::new(WatchRegistry::new);
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
@@ -27,14 +95,31 @@ unsafe fn cstr_to_str<'a>(ptr: *const c_char) -> Option<&'a str> {
 
 /// Convert a Rust string to a C string the caller must free with `strata_free_string`.
 fn to_c_string(s: &str) -> *mut c_char {
-    CString::new(s).unwrap_or_default().into_raw()
+    let ptr = CString::new(s).unwrap_or_default().into_raw();
+    safe_free::register(ptr as *const u8);
+    ptr
+}
+
+/// Hand a byte buffer to the caller, who must free it with `strata_free_bytes`.
+/// Writes its length to `out_len` (0 for `None`, which returns null).
+fn to_raw_bytes(bytes: Option<Vec<u8>>, out_len: *mut usize) -> *mut u8 {
+    if !out_len.is_null() {
+        unsafe { *out_len = bytes.as_ref().map_or(0, Vec::len) };
+    }
+    match bytes {
+        Some(bytes) => Box::into_raw(bytes.into_boxed_slice()) as *mut u8,
+        None => std::ptr::null_mut(),
+    }
 }
 
 /// Wrap a closure in panic-catching. Returns JSON error on panic.
 fn catch_panic<F: FnOnce() -> String + std::panic::UnwindSafe>(f: F) -> *mut c_char {
     match std::panic::catch_unwind(f) {
         Ok(json) => to_c_string(&json),
-        Err(_) => to_c_string(r#"{"error":{"Internal":{"reason":"panic in Rust bridge"}}}"#),
+        Err(payload) => {
+            error::record_panic(&error::panic_message(payload.as_ref()));
+            to_c_string(r#"{"error":{"Internal":{"reason":"panic in Rust bridge"}}}"#)
+        }
     }
 }
 
@@ -45,7 +130,17 @@ fn ok_json(value: &str) -> String {
 
 /// Format an error result as JSON: `{"error": <error_json>}`
 fn error_json(msg: &str) -> String {
-    format!(r#"{{"error":{{"Internal":{{"reason":{}}}}}}}"#, serde_json::json!(msg))
+    bridge_error_json(&BridgeError::from(msg))
+}
+
+/// Format a typed error result as JSON: `{"error": {"<kind>": <fields>}}`
+fn error_kind_json(kind: &'static str, fields: serde_json::Value) -> String {
+    bridge_error_json(&BridgeError::Kind(kind, fields))
+}
+
+/// Format a `BridgeError` as JSON at the current verbosity: `{"error": {...}}`
+fn bridge_error_json(e: &BridgeError) -> String {
+    serde_json::json!({ "error": e.render(None) }).to_string()
 }
 
 // ---------------------------------------------------------------------------
@@ -54,14 +149,48 @@ fn error_json(msg: &str) -> String {
 
 /// Open a database at the given path.
 ///
+/// Opening a path that is already open (from any thread) returns a new
+/// handle ID sharing the same underlying database, rather than opening its
+/// files twice. The database closes when the last such handle is closed.
+///
 /// # Arguments
 /// - `path`: null-terminated UTF-8 path to a `.strata` directory
-/// - `config_json`: null-terminated JSON string for OpenOptions, or null for defaults
+/// - `config_json`: null-terminated JSON string for OpenOptions, or null for defaults.
+///   Recognized options: `max_open_files` and `cache_bytes` (recorded only:
+///   stratadb takes no tuning options, see `strata_get_config`), `wal_sync`
+///   (`"always"`, `"interval:<ms>"` or `"never"`), `access_pattern`
+///   (`"sequential"` prefetches the database's files into the page cache
+///   for scans; `"random"` prefetches nothing), `lazy` (unsupported and
+///   recorded only, see `strata_warm_status`), `read_only` (every write
+///   fails with `{"error": {"ReadOnly": {"handle", "command"}}}`),
+///   `require_format_version`,
+///   `max_concurrent_commands` with `block`: commands beyond the limit
+///   wait if `block` is true and otherwise fail with `{"error": {"Busy": {...}}}`,
+///   and `backup_on_open`: a retention count. The directory is then copied
+///   to `<path>.backup-<unix millis>` before it is opened, and older backups
+///   beyond the count are deleted. No backup is taken when the path is already
+///   open in this process or does not exist yet. `verify_on_open` checks
+///   that every primitive on every branch is readable right after opening;
+///   issues fail the open with `VerifyFailed` unless `allow_degraded` is set.
+///   `max_command_bytes` refuses longer commands with `CommandTooLarge`.
+///   `default_timeout_ms` bounds each `strata_execute` call (see
+///   `strata_set_default_timeout_ms`).
+///   `journal_path` appends each successful mutating command to that file as
+///   a JSON line `{"at_ms", "command"}`; with `journal_fsync` each line is
+///   synced before the command returns.
+///   `seed` makes the random choices of commands such as `KvSample`
+///   reproducible: handles opened with the same seed that run the same
+///   commands on the same data choose the same entries.
 ///
 /// # Returns
 /// JSON string (caller must free with `strata_free_string`):
-/// - Success: `{"ok": <handle_id>}`
-/// - Error: `{"error": {...}}`
+/// - Success: `{"ok": <handle_id>}`, or with `backup_on_open` or `verify_on_open`
+///   `{"ok": {"handle": <handle_id>, "backup": "<path>"|null, "verify": {"ok", "issues"}}}`
+///   (each key present only when its option is set)
+/// - Error: `{"error": {...}}`, or `{"error": {"ResourceExhausted": {...}}}` when
+///   the OS is out of file descriptors, or `{"error": {"FormatMismatch": {"found", "required"}}}`.
+///   stratadb does not report its format version yet, so `found` is null and
+///   any `require_format_version` is refused.
 #[no_mangle]
 pub extern "C" fn strata_open(path: *const c_char, config_json: *const c_char) -> *mut c_char {
     catch_panic(|| {
@@ -70,122 +199,5378 @@ pub extern "C" fn strata_open(path: *const c_char, config_json: *const c_char) -
             None => return error_json("path is null or invalid UTF-8"),
         };
 
-        let _config_str = unsafe { cstr_to_str(config_json) };
-        // TODO: parse OpenOptions from config_json
+        let config = match OpenConfig::parse(unsafe { cstr_to_str(config_json) }) {
+            Ok(config) => config,
+            Err(e) => return bridge_error_json(&e),
+        };
+        if let Err(e) = config.check_format().and_then(|()| config.check_file_backed()) {
+            return bridge_error_json(&e);
+        }
 
-        match REGISTRY.open(path_str) {
-            Ok(id) => ok_json(&id.to_string()),
-            Err(e) => error_json(&e),
+        let (id, backup) = match REGISTRY.open(path_str, config.clone()) {
+            Ok(opened) => opened,
+            Err(e) => return bridge_error_json(&config::open_error(e)),
+        };
+        let verify = match verify_opened(id, &config) {
+            Ok(verify) => verify,
+            Err(e) => return bridge_error_json(&e),
+        };
+        if config.backup_on_open.is_none() && !config.verify_on_open {
+            return ok_json(&id.to_string());
+        }
+
+        let mut result = serde_json::json!({ "handle": id });
+        if config.backup_on_open.is_some() {
+            result["backup"] = serde_json::json!(backup.map(|p| p.to_string_lossy().into_owned()));
+        }
+        if let Some(verify) = verify {
+            result["verify"] = verify;
+        }
+        ok_json(&result.to_string())
+    })
+}
+
+/// Run the `verify_on_open` checks on a handle that was just opened.
+///
+/// Returns the report, or `None` if verification was not requested. If it
+/// found issues and `allow_degraded` is not set, the handle is closed again
+/// and the open fails with `VerifyFailed`.
+fn verify_opened(
+    handle: u64,
+    config: &OpenConfig,
+) -> Result<Option<serde_json::Value>, BridgeError> {
+    if !config.verify_on_open {
+        return Ok(None);
+    }
+    let report = REGISTRY
+        .run_guarded(handle, |strata| Ok(commands::verify::verify(strata)))
+        .inspect_err(|_| strata_close(handle))?;
+    if report["ok"] == false && !config.allow_degraded {
+        strata_close(handle);
+        return Err(BridgeError::Kind("VerifyFailed", serde_json::json!({ "issues": report["issues"] })));
+    }
+    Ok(Some(report))
+}
+
+/// Open a database at the given path, reporting WAL recovery as it happens.
+///
+/// stratadb replays any pending WAL while opening. `progress` (nullable) is
+/// called with a JSON event when recovery starts and when it completes or
+/// fails. stratadb does not expose replay counts, so the summary covers the
+/// WAL found on disk and the time the open took.
+///
+/// # Returns
+/// JSON string (caller must free with `strata_free_string`):
+/// - Success: `{"ok": {"handle": <id>, "recovery": {"duration_ms", "wal_files", "wal_bytes"},
+///   "backup": "<path>"|null, "verify": {"ok", "issues"}|null}}`, where `backup` and `verify`
+///   are set by `backup_on_open` and `verify_on_open` (see `strata_open`)
+/// - Error: `{"error": {"RecoveryFailed": {"reason", "duration_ms", "wal_files", "wal_bytes"}}}`
+#[no_mangle]
+pub extern "C" fn strata_open_with_recovery(
+    path: *const c_char,
+    config_json: *const c_char,
+    progress: Option<recovery::RecoveryCallback>,
+) -> *mut c_char {
+    catch_panic(|| {
+        let path_str = match unsafe { cstr_to_str(path) } {
+            Some(s) => s,
+            None => return error_json("path is null or invalid UTF-8"),
+        };
+
+        let config = match OpenConfig::parse(unsafe { cstr_to_str(config_json) }) {
+            Ok(config) => config,
+            Err(e) => return bridge_error_json(&e),
+        };
+        if let Err(e) = config.check_format().and_then(|()| config.check_file_backed()) {
+            return bridge_error_json(&e);
+        }
+
+        let recovery = recovery::Recovery::start(&REGISTRY.resolve_path(path_str), progress);
+        match REGISTRY.open(path_str, config.clone()) {
+            Ok((id, backup)) => {
+                let recovery = recovery.complete();
+                match verify_opened(id, &config) {
+                    Ok(verify) => ok_json(
+                        &serde_json::json!({
+                            "handle": id,
+                            "recovery": recovery,
+                            "backup": backup.map(|p| p.to_string_lossy().into_owned()),
+                            "verify": verify,
+                        })
+                        .to_string(),
+                    ),
+                    Err(e) => bridge_error_json(&e),
+                }
+            }
+            Err(e) => error_kind_json("RecoveryFailed", recovery.fail(&e)),
         }
     })
 }
 
+/// Why the most recent file-backed open failed, beyond the error string.
+///
+/// Covers `strata_open`, `strata_open_with_recovery` and group shards. The
+/// diagnostic describes the path as found right after the failure, and is
+/// kept process-wide until another open fails.
+///
+/// # Returns
+/// JSON string: `{"ok": {"path", "exists", "kind": "directory"|"file"|"missing",
+/// "checked", "permissions": "0755"|null, "readable", "writable", "error", "at_ms"}}`,
+/// where `checked` is the path whose permissions are reported (the nearest
+/// existing ancestor of a missing path), or `{"ok": null}` if no open has failed.
+#[no_mangle]
+pub extern "C" fn strata_last_open_error() -> *mut c_char {
+    catch_panic(|| ok_json(&serde_json::json!(diagnose::last_open_error()).to_string()))
+}
+
+/// Set the directory that relative `strata_open` paths resolve against.
+///
+/// Absolute paths are never affected. Pass null to clear it, so relative
+/// paths resolve against the process working directory again. Applies to
+/// opens after the call; already-open handles keep their paths.
+#[no_mangle]
+pub extern "C" fn strata_set_base_dir(path: *const c_char) {
+    let dir = unsafe { cstr_to_str(path) }.map(std::path::PathBuf::from);
+    REGISTRY.set_base_dir(dir);
+}
+
 /// Open an in-memory (ephemeral) database.
 ///
 /// # Returns
 /// JSON string: `{"ok": <handle_id>}` or `{"error": {...}}`
 #[no_mangle]
 pub extern "C" fn strata_open_memory() -> *mut c_char {
-    catch_panic(|| match REGISTRY.open_memory() {
-        Ok(id) => ok_json(&id.to_string()),
-        Err(e) => error_json(&e),
+    catch_panic(|| {
+        let config = match OpenConfig::parse(None) {
+            Ok(config) => config,
+            Err(e) => return bridge_error_json(&e),
+        };
+        match REGISTRY.open_memory(config, 0) {
+            Ok(id) => ok_json(&id.to_string()),
+            Err(e) => error_json(&e),
+        }
     })
 }
 
-/// Close a database and free its handle.
+/// Open an in-memory database with options, e.g. as a bounded cache.
+///
+/// # Arguments
+/// - `config_json`: null-terminated JSON string for OpenOptions, or null for
+///   defaults. Besides the `strata_open` options, `max_bytes` caps the KV
+///   bytes the handle holds, and `eviction` picks what happens at the cap:
+///   `"lru"` evicts least recently used keys, `"reject"` (the default)
+///   refuses the write with `{"error": {"CacheFull": {...}}}`. Usage is
+///   reported by `strata_stats`.
+///
+/// # Returns
+/// JSON string: `{"ok": <handle_id>}` or `{"error": {...}}`
 #[no_mangle]
-pub extern "C" fn strata_close(handle: u64) {
-    REGISTRY.close(handle);
+pub extern "C" fn strata_open_memory_with_config(config_json: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let config = match OpenConfig::parse(unsafe { cstr_to_str(config_json) }) {
+            Ok(config) => config,
+            Err(e) => return bridge_error_json(&e),
+        };
+        match REGISTRY.open_memory(config, 0) {
+            Ok(id) => ok_json(&id.to_string()),
+            Err(e) => error_json(&e),
+        }
+    })
 }
 
-// ---------------------------------------------------------------------------
-// Command execution
-// ---------------------------------------------------------------------------
+/// `strata_open_memory_with_config` for a database expected to hold about
+/// `expected_entries` keys, e.g. before a bulk seed.
+///
+/// Best effort: stratadb's in-memory open takes no capacity hint, so only
+/// the bridge's own per-key bookkeeping (the `max_bytes` accounting) is
+/// sized up front. The database itself grows as it would anyway.
+///
+/// # Returns
+/// JSON string: `{"ok": <handle_id>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_open_memory_with_capacity(
+    expected_entries: usize,
+    config_json: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let config = match OpenConfig::parse(unsafe { cstr_to_str(config_json) }) {
+            Ok(config) => config,
+            Err(e) => return bridge_error_json(&e),
+        };
+        match REGISTRY.open_memory(config, expected_entries) {
+            Ok(id) => ok_json(&id.to_string()),
+            Err(e) => error_json(&e),
+        }
+    })
+}
 
-/// Execute a single command against a database.
+/// Open a file-backed database in a new directory under the OS temp dir.
+///
+/// The directory is deleted when the handle is closed, so callers get
+/// persistence semantics for the session without leaving files behind.
 ///
 /// # Arguments
-/// - `handle`: handle ID from `strata_open`
-/// - `command_json`: null-terminated JSON string (externally-tagged Command)
+/// - `config_json`: null-terminated JSON string for OpenOptions, or null for defaults
 ///
 /// # Returns
-/// JSON string (caller must free):
-/// - Success: the Output JSON (externally-tagged)
-/// - Error: `{"error": {...}}`
+/// JSON string: `{"ok": {"handle": <handle_id>, "path": "..."}}` or `{"error": {...}}`
 #[no_mangle]
-pub extern "C" fn strata_execute(handle: u64, command_json: *const c_char) -> *mut c_char {
+pub extern "C" fn strata_open_temp(config_json: *const c_char) -> *mut c_char {
     catch_panic(|| {
-        let json_str = match unsafe { cstr_to_str(command_json) } {
-            Some(s) => s,
-            None => return error_json("command_json is null or invalid UTF-8"),
+        let config = match OpenConfig::parse(unsafe { cstr_to_str(config_json) }) {
+            Ok(config) => config,
+            Err(e) => return bridge_error_json(&e),
         };
+        if let Err(e) = config.check_format().and_then(|()| config.check_file_backed()) {
+            return bridge_error_json(&e);
+        }
 
-        match REGISTRY.execute(handle, json_str) {
-            Ok(output) => output,
-            Err(e) => error_json(&e),
+        match REGISTRY.open_temp(config) {
+            Ok((id, path)) => ok_json(
+                &serde_json::json!({ "handle": id, "path": path.to_string_lossy() }).to_string(),
+            ),
+            Err(e) => bridge_error_json(&config::open_error(e)),
         }
     })
 }
 
-// ---------------------------------------------------------------------------
-// Memory management
-// ---------------------------------------------------------------------------
+/// Set process-wide default open options, or pass null to clear them.
+///
+/// Every later open merges its own `config_json` over these, field by field:
+/// a field the open sets, even to null, overrides the default. Handles
+/// already open keep their options.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {"InvalidInput": {...}}}`
+#[no_mangle]
+pub extern "C" fn strata_set_default_config(config_json: *const c_char) -> *mut c_char {
+    catch_panic(|| match config::set_defaults(unsafe { cstr_to_str(config_json) }) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
 
-/// Free a string returned by any `strata_*` function.
+/// The options a handle was opened with, as given in its `config_json`
+/// merged over any `strata_set_default_config` defaults.
 ///
-/// # Safety
-/// `ptr` must have been returned by a `strata_*` function and not yet freed.
+/// Unset options are null, meaning stratadb's default is in effect.
+/// Handles from `strata_open_memory` report the process-wide defaults.
+/// `unapplied` names the options that are set but only recorded, because
+/// stratadb cannot take them (`max_open_files`, `cache_bytes`, `lazy`).
+///
+/// # Returns
+/// JSON string: `{"ok": {"max_open_files": n|null, "cache_bytes": n|null, "wal_sync": "..."|null,
+/// "access_pattern": "sequential"|"random"|null, "lazy": bool, "read_only": bool,
+/// "require_format_version": n|null,
+/// "max_concurrent_commands": n|null, "block": bool, "journal_path": "..."|null,
+/// "journal_fsync": bool, "max_command_bytes": n|null, "default_timeout_ms": n|null,
+/// "seed": n|null, "max_bytes": n|null,
+/// "eviction": "lru"|"reject"|null, "unapplied": ["..."]}}`
+/// or `{"error": {...}}`
 #[no_mangle]
-pub unsafe extern "C" fn strata_free_string(ptr: *mut c_char) {
-    if !ptr.is_null() {
-        unsafe {
-            let _ = CString::from_raw(ptr);
+pub extern "C" fn strata_get_config(handle: u64) -> *mut c_char {
+    catch_panic(|| {
+        let config = REGISTRY.config(handle).and_then(|config| {
+            let mut json = serde_json::to_value(&config)
+                .map_err(|e| BridgeError::from(format!("failed to serialize config: {e}")))?;
+            json["unapplied"] = serde_json::json!(config.unapplied());
+            Ok(json.to_string())
+        });
+        match config {
+            Ok(json) => ok_json(&json),
+            Err(e) => bridge_error_json(&e),
         }
+    })
+}
+
+/// Close a database and free its handle.
+///
+/// Closing a group from `strata_open_group` closes each of its shards.
+/// A handle pinned with `strata_handle_pin` is left open: the refusal is
+/// logged and recorded as its `HandlePinned` last error.
+#[no_mangle]
+pub extern "C" fn strata_close(handle: u64) {
+    if REGISTRY.is_pinned(handle) {
+        let e = BridgeError::Kind("HandlePinned", serde_json::json!({ "handle": handle }));
+        log::warn(&format!("strata_close refused: handle {handle} is pinned"));
+        REGISTRY.record_error(handle, Some("strata_close".to_string()), e.render(None));
+        return;
     }
+    for shard in GROUPS.close(handle) {
+        strata_close(shard);
+    }
+    TXNS.close_handle(handle);
+    SNAPSHOTS.close_handle(handle);
+    WATCHES.close_handle(handle);
+    IDEMPOTENCY.close_handle(handle);
+    REGISTRY.close(handle);
 }
 
-// ---------------------------------------------------------------------------
-// Smoke test
-// ---------------------------------------------------------------------------
+/// Flush a database, then close it and free its handle, reporting whether
+/// the flush succeeded.
+///
+/// Pending coalesced state sets are written before the flush. If the flush
+/// fails, or the handle is pinned with `strata_handle_pin`, the handle is
+/// left open so the call can be retried; `strata_close` closes it
+/// regardless. A group from `strata_open_group` flushes every shard before
+/// closing any of them.
+///
+/// # Returns
+/// JSON string: `{"ok": {"flushed": true}}`,
+/// `{"error": {"HandlePinned": {"handle"}}}`, or another `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_close_checked(handle: u64) -> *mut c_char {
+    catch_panic(|| {
+        let handles =
+            if GROUPS.contains(handle) { GROUPS.handles(handle) } else { vec![handle] };
+        if let Some(pinned) = handles.iter().copied().find(|id| REGISTRY.is_pinned(*id)) {
+            let e = BridgeError::Kind("HandlePinned", serde_json::json!({ "handle": pinned }));
+            return bridge_error_json(&e);
+        }
+        for id in handles {
+            if let Err(e) = REGISTRY.flush(id) {
+                return bridge_error_json(&e);
+            }
+        }
+        strata_close(handle);
+        ok_json(r#"{"flushed":true}"#)
+    })
+}
 
-/// Returns `{"ok": "strata-foundry-bridge"}`. Use to verify the dylib loads.
+/// Open several databases behind one group ID, e.g. per-user shards.
+///
+/// # Arguments
+/// - `paths_json`: null-terminated JSON object mapping shard names to
+///   database paths, or to null for an in-memory database:
+///   `{"user_a": "/data/a.strata", "user_b": null}`
+///
+/// Pass the group ID to `strata_execute` with the shard named beside the
+/// command tag, `{"shard": "user_a", "KvGet": {"key": "k"}}`; the command runs
+/// on that shard as if sent to its own handle. A command without `"shard"`
+/// is `InvalidInput`, and an unknown shard is `NotFound`. `strata_close`
+/// on the group closes every shard. If any shard fails to open, none stay open.
+///
+/// # Returns
+/// JSON string: `{"ok": <group_id>}` or `{"error": {...}}`
 #[no_mangle]
-pub extern "C" fn strata_ping() -> *mut c_char {
-    to_c_string(r#"{"ok":"strata-foundry-bridge"}"#)
+pub extern "C" fn strata_open_group(paths_json: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let Some(paths_json) = (unsafe { cstr_to_str(paths_json) }) else {
+            return error_json("paths_json is null or invalid UTF-8");
+        };
+        match GROUPS.open(&REGISTRY, paths_json) {
+            Ok(id) => ok_json(&id.to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Whether a handle's indexes and segments are loaded, for cold-start UI.
+///
+/// Deferred loading is unsupported: stratadb loads everything while
+/// opening and has no deferred mode. The `lazy` open option is recorded only
+/// (`strata_get_config` lists it under `unapplied`), so `lazy` here is always
+/// false and `warm` is true as soon as the open returns.
+///
+/// # Returns
+/// JSON string: `{"ok": {"lazy": bool, "warm": bool}}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_warm_status(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.warm_status(handle) {
+        Ok(status) => ok_json(&status.to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
 
-    #[test]
-    fn test_open_memory_and_execute_ping() {
-        let result_ptr = strata_open_memory();
-        let result = unsafe { CStr::from_ptr(result_ptr) }.to_str().unwrap().to_string();
-        unsafe { strata_free_string(result_ptr) };
+/// Pin a handle so `strata_close` leaves it open, guarding a long-lived
+/// shared handle against a stray close. Lasts until `strata_handle_unpin`.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_handle_pin(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_pinned(handle, true) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
 
-        // Parse handle ID
-        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
-        let handle_id = v["ok"].as_u64().expect("expected ok with handle id");
+/// Undo `strata_handle_pin`, so `strata_close` closes the handle again.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_handle_unpin(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_pinned(handle, false) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
 
-        // Execute Ping command
-        let cmd = CString::new(r#"{"Ping":null}"#).unwrap();
-        let out_ptr = strata_execute(handle_id, cmd.as_ptr());
-        let out = unsafe { CStr::from_ptr(out_ptr) }.to_str().unwrap().to_string();
-        unsafe { strata_free_string(out_ptr) };
+/// Reopen a file-backed database under the same handle ID.
+///
+/// Use after a `HandleFaulted` error or a transient file failure to recover
+/// without re-registering the handle on the Swift side. In-memory handles
+/// cannot be reopened. Other handles on the same path move to the reopened
+/// database; if it fails to open, they are all left faulted.
+///
+/// # Returns
+/// JSON string: `{"ok": <handle_id>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_reopen(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.reopen(handle) {
+        Ok(()) => ok_json(&handle.to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
 
-        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
-        assert!(v["Pong"].is_object(), "Expected Pong, got: {}", out);
+/// List all open handles with their metadata.
+///
+/// # Returns
+/// JSON string: `{"ok": [{"handle", "kind", "path", "opened_at_ms", "uptime_ms", "faulted"}]}`
+/// where `kind` is `"file"`, `"temp"`, or `"memory"`.
+#[no_mangle]
+pub extern "C" fn strata_list_handles() -> *mut c_char {
+    catch_panic(|| ok_json(&serde_json::Value::from(REGISTRY.list()).to_string()))
+}
 
-        strata_close(handle_id);
-    }
+/// The IDs of open handles on the database at `path`, to spot a database
+/// opened more than once.
+///
+/// Relative paths are resolved against the base directory, and paths are
+/// compared after canonicalization, so `./db.strata` and its absolute form
+/// match.
+///
+/// # Returns
+/// JSON string: `{"ok": [<handle_id>, ...]}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_handles_for_path(path: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let Some(path) = (unsafe { cstr_to_str(path) }) else {
+            return error_json("path is null or invalid UTF-8");
+        };
+        ok_json(&serde_json::Value::from(REGISTRY.handles_for_path(path)).to_string())
+    })
+}
 
-    #[test]
-    fn test_strata_ping() {
-        let ptr = strata_ping();
-        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
-        unsafe { strata_free_string(ptr) };
-        assert_eq!(s, r#"{"ok":"strata-foundry-bridge"}"#);
+/// Flush every open file-backed database, e.g. before the app is backgrounded.
+///
+/// In-memory handles are skipped. A failing handle does not abort the sweep;
+/// its error is collected instead.
+///
+/// # Returns
+/// JSON string: `{"ok": {"flushed": N, "errors": [{"handle": id, "error": {...}}]}}`
+#[no_mangle]
+pub extern "C" fn strata_flush_all() -> *mut c_char {
+    catch_panic(|| {
+        let (flushed, errors) = REGISTRY.flush_all();
+        let errors: Vec<serde_json::Value> = errors
+            .iter()
+            .map(|(id, e)| serde_json::json!({ "handle": id, "error": e.to_json() }))
+            .collect();
+        ok_json(&serde_json::json!({ "flushed": flushed, "errors": errors }).to_string())
+    })
+}
+
+/// Limit the size of keys and values written through `handle`.
+///
+/// Writes with a key longer than `max_key_bytes` fail with `KeyTooLarge`,
+/// and writes whose value (as JSON) exceeds `max_value_bytes` fail with
+/// `{"ValueTooLarge": {"key", "bytes", "limit"}}`, before reaching storage.
+/// Zero means unlimited, the default.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_value_limits(
+    handle: u64,
+    max_key_bytes: u64,
+    max_value_bytes: u64,
+) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_value_limits(handle, max_key_bytes, max_value_bytes) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Confine `handle` to a key namespace, e.g. `allow_regex = "^tenantA:"`.
+///
+/// Every command naming a key or prefix (`key`, `prefix`, `cell`, `keys`,
+/// batch entries, a rename's `from`/`to`, a swap's `key_a`/`key_b`, a
+/// `MultiGet`'s `kv`/`state` and `VectorSearchById`'s `id`) must match
+/// `allow_regex` and must not match `deny_regex`, or it fails with
+/// `{"Forbidden": {"key"}}` before reaching storage. The policy covers
+/// snapshots and streams of the handle too. Patterns are unanchored. A null
+/// or empty pattern is not applied; both empty lifts the policy, the default.
+///
+/// Listings (`KvList`, `StateList`, `JsonList`, `JsonCatalog`, `KvSample`,
+/// streams) and search matches (`VectorSearch`, `VectorSearchById`) leave
+/// out the keys the policy refuses, so a page may come back short.
+/// `KvAggregate` and `KvInferSchema` fail with `{"Forbidden": {"key"}}` if
+/// their prefix holds such a key.
+///
+/// Under a policy, scans (`KvList`, `JsonList`, `StateList`, `JsonCatalog`,
+/// `KvAggregate`, ...) must name a non-empty prefix. Reads spanning every
+/// key (`KvNamespaces`, `Digest`, `ChangesSince`, `StateDiffBetween`,
+/// `Search`, `BranchDiff`, `BranchExport`), the `strata_export_*` functions
+/// and `strata_import_json` fail with `{"Forbidden": {"command"}}`.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {"InvalidInput": {...}}}` for
+/// a pattern that does not compile
+#[no_mangle]
+pub extern "C" fn strata_set_key_policy(
+    handle: u64,
+    allow_regex: *const c_char,
+    deny_regex: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let pattern = |ptr: *const c_char| match ptr.is_null() {
+            true => Some(""),
+            false => unsafe { cstr_to_str(ptr) },
+        };
+        let (Some(allow), Some(deny)) = (pattern(allow_regex), pattern(deny_regex)) else {
+            return error_json("pattern is invalid UTF-8");
+        };
+        let result = policy::KeyPolicy::new(allow, deny)
+            .and_then(|policy| REGISTRY.set_key_policy(handle, policy));
+        match result {
+            Ok(()) => ok_json("null"),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Enable an audit trail of mutating commands on `handle`, keeping at most
+/// `capacity` entries (oldest dropped first). Zero disables and clears it.
+///
+/// Commands run through `strata_execute` and atomic batches, plus
+/// `strata_kv_put_bytes` and the destination write of `strata_copy_kv`, are
+/// recorded once they succeed; a transaction's writes once it commits.
+/// Reads are not recorded.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_audit(handle: u64, capacity: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_audit(handle, capacity as usize) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Read the audit trail of `handle`, oldest first.
+///
+/// # Returns
+/// JSON string: `{"ok": [{"timestamp", "command_tag", "key"}]}` where
+/// `timestamp` is milliseconds since the Unix epoch and `key` is the key,
+/// cell, collection, or branch written (null for batches), or `{"error": {...}}`.
+#[no_mangle]
+pub extern "C" fn strata_get_audit(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.audit_entries(handle) {
+        Ok(entries) => ok_json(&serde_json::Value::from(entries).to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Coalesce rapid `StateSet`s to a state cell on the default branch and space.
+///
+/// Sets to `cell` are held in memory and only the latest is written, once
+/// every `interval_ms`, from a background thread; closing the handle writes
+/// any value still held. `StateGet` returns the held value, and other state
+/// commands write held values first. A held set returns the version of the
+/// cell's last write. `interval_ms` of zero stops coalescing the cell.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_state_coalesce(
+    handle: u64,
+    cell: *const c_char,
+    interval_ms: u64,
+) -> *mut c_char {
+    catch_panic(|| {
+        let Some(cell) = (unsafe { cstr_to_str(cell) }) else {
+            return error_json("cell is null or invalid UTF-8");
+        };
+        match REGISTRY.set_state_coalesce(handle, cell, interval_ms) {
+            Ok(()) => ok_json("null"),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Runtime statistics for `handle`.
+///
+/// `cache` is the `max_bytes` accounting of an in-memory handle opened with
+/// a cap, and null otherwise. `coalesced` counts the sets to each cell
+/// coalesced with `strata_set_state_coalesce` and the writes they became.
+///
+/// # Returns
+/// JSON string: `{"ok": {"uptime_ms", "cache": {"max_bytes", "used_bytes", "entries",
+/// "evictions", "eviction"}|null, "coalesced": {"<cell>": {"sets", "writes", "pending"}}}}`
+/// or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_stats(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.stats(handle) {
+        Ok(stats) => ok_json(&stats.to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Latency percentiles in microseconds for each command tag run on `handle`
+/// since it was opened or its stats were last reset.
+///
+/// Percentiles come from a log-linear histogram and are accurate to within
+/// 12.5%.
+///
+/// # Returns
+/// JSON string: `{"ok": {"<tag>": {"count", "p50_us", "p95_us", "p99_us", "max_us"}}}`
+/// or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_stats_latency(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.latency(handle) {
+        Ok(latency) => ok_json(&latency.to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Clear `handle`'s latency histograms.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_stats_reset(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.reset_stats(handle) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Space left on the filesystem holding `handle`'s database, to check
+/// before a large import.
+///
+/// # Returns
+/// JSON string: `{"ok": {"free_bytes": n, "mount": "/path"}}`, `{"ok": {"memory": true}}`
+/// for an in-memory handle, or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_disk_free(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.disk_free(handle) {
+        Ok(free) => ok_json(&free.to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Milliseconds since `handle` was opened, or -1 if the handle is unknown.
+#[no_mangle]
+pub extern "C" fn strata_handle_uptime_ms(handle: u64) -> i64 {
+    REGISTRY.uptime_ms(handle).map_or(-1, |ms| ms as i64)
+}
+
+// ---------------------------------------------------------------------------
+// Export
+// ---------------------------------------------------------------------------
+
+/// Write a human-readable JSON dump of the whole database to `out_path`.
+///
+/// The file holds `{"branches", "events", "json", "kv", "state"}` with keys in
+/// sorted order, so successive dumps can be diffed or committed to git.
+///
+/// # Returns
+/// JSON string: `{"ok": {"kv": N, "state": N, "events": N, "json": N, "branches": N}}`
+/// with the number of entries written per section, or `{"error": {...}}`.
+#[no_mangle]
+pub extern "C" fn strata_export_json(handle: u64, out_path: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let path = match unsafe { cstr_to_str(out_path) } {
+            Some(s) => s,
+            None => return error_json("out_path is null or invalid UTF-8"),
+        };
+
+        // Coalesced state sets are written first so the export includes them.
+        if let Err(e) = REGISTRY
+            .check_unscoped(handle, "strata_export_json")
+            .and_then(|()| REGISTRY.flush_coalesced(handle))
+        {
+            return bridge_error_json(&e);
+        }
+        let result = REGISTRY.run_guarded(handle, |strata| {
+            let dump = commands::dump::export(strata)?;
+            let text = serde_json::to_string_pretty(&dump)
+                .map_err(|e| format!("failed to serialize dump: {e}"))?;
+            std::fs::write(path, text + "\n")
+                .map_err(|e| format!("failed to write {path}: {e}"))?;
+            let count = |section: &str| match &dump[section] {
+                serde_json::Value::Array(items) => items.len(),
+                serde_json::Value::Object(map) => map.len(),
+                _ => 0,
+            };
+            Ok(serde_json::json!({
+                "kv": count("kv"),
+                "state": count("state"),
+                "events": count("events"),
+                "json": count("json"),
+                "branches": count("branches"),
+            }))
+        });
+        match result {
+            Ok(counts) => ok_json(&counts.to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Export the event log to `out_path` as CSV, for spreadsheets.
+///
+/// `columns_json` is a JSON array of column names, or null for
+/// `["sequence", "kind", "timestamp", "data"]`. `data` is the whole payload;
+/// other names pick a payload field, with `.` for nested fields
+/// (`"result.status"`). Nested values are JSON-encoded into their cell,
+/// missing fields are empty, and cells with commas, quotes or line breaks
+/// are quoted. The first row is the header.
+///
+/// # Returns
+/// JSON string: `{"ok": {"rows": N}}` (events written, header excluded) or
+/// `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_export_events_csv(
+    handle: u64,
+    out_path: *const c_char,
+    columns_json: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let path = match unsafe { cstr_to_str(out_path) } {
+            Some(s) => s,
+            None => return error_json("out_path is null or invalid UTF-8"),
+        };
+        let columns: Vec<String> = if columns_json.is_null() {
+            commands::csv::DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect()
+        } else {
+            let parsed = unsafe { cstr_to_str(columns_json) }
+                .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
+                .filter(|columns| !columns.is_empty());
+            match parsed {
+                Some(columns) => columns,
+                None => return error_json("columns_json must be a non-empty array of names"),
+            }
+        };
+
+        if let Err(e) = REGISTRY.check_unscoped(handle, "strata_export_events_csv") {
+            return bridge_error_json(&e);
+        }
+        let result = REGISTRY.run_guarded(handle, |strata| {
+            let file = std::fs::File::create(path)
+                .map_err(|e| format!("failed to create {path}: {e}"))?;
+            let mut out = std::io::BufWriter::new(file);
+            commands::csv::export_events(strata, &columns, &mut out)
+        });
+        match result {
+            Ok(rows) => ok_json(&serde_json::json!({ "rows": rows }).to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Write one primitive's section of a `strata_export_json` dump to `out_path`.
+///
+/// `primitive` is `"kv"`, `"state"`, `"events"`, `"json"`, `"vectors"` or
+/// `"branches"`. The file holds that section alone: an array for `events`
+/// and `branches`, an object keyed by key, cell or collection otherwise.
+/// `vectors`, which full dumps leave out, maps each collection to
+/// `{"dimension", "metric", "vectors": {"<key>": {"embedding", "metadata"}}}`.
+///
+/// # Returns
+/// JSON string: `{"ok": {"primitive": "...", "count": N}}` or `{"error": {...}}`;
+/// an unknown primitive is `{"error": {"InvalidInput": {...}}}`.
+#[no_mangle]
+pub extern "C" fn strata_export_primitive(
+    handle: u64,
+    primitive: *const c_char,
+    out_path: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let Some(primitive) = (unsafe { cstr_to_str(primitive) }) else {
+            return error_json("primitive is null or invalid UTF-8");
+        };
+        let path = match unsafe { cstr_to_str(out_path) } {
+            Some(s) => s,
+            None => return error_json("out_path is null or invalid UTF-8"),
+        };
+
+        // Coalesced state sets are written first so the export includes them.
+        if let Err(e) = REGISTRY
+            .check_unscoped(handle, "strata_export_primitive")
+            .and_then(|()| REGISTRY.flush_coalesced(handle))
+        {
+            return bridge_error_json(&e);
+        }
+        let result = REGISTRY.run_guarded(handle, |strata| {
+            let section = commands::dump::export_primitive(strata, primitive)?;
+            let text = serde_json::to_string_pretty(&section)
+                .map_err(|e| format!("failed to serialize {primitive}: {e}"))?;
+            std::fs::write(path, text + "\n")
+                .map_err(|e| format!("failed to write {path}: {e}"))?;
+            let count = match &section {
+                serde_json::Value::Array(items) => items.len(),
+                serde_json::Value::Object(map) => map.len(),
+                _ => 0,
+            };
+            Ok(serde_json::json!({ "primitive": primitive, "count": count }))
+        });
+        match result {
+            Ok(counts) => ok_json(&counts.to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Load a dump written by `strata_export_json` into an open database.
+///
+/// `mode` is `"merge"` (overlay the dump on existing data) or `"replace"`
+/// (delete existing KV, state and JSON data first). Events are always
+/// appended. Unknown top-level sections are skipped with a logged warning.
+///
+/// # Returns
+/// JSON string: `{"ok": {"kv": N, "state": N, "events": N, "json": N, "branches": N, "ignored": [...]}}`
+/// where `branches` counts branches created, or `{"error": {...}}`.
+#[no_mangle]
+pub extern "C" fn strata_import_json(
+    handle: u64,
+    in_path: *const c_char,
+    mode: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let path = match unsafe { cstr_to_str(in_path) } {
+            Some(s) => s,
+            None => return error_json("in_path is null or invalid UTF-8"),
+        };
+        let mode = match unsafe { cstr_to_str(mode) }.and_then(commands::dump::ImportMode::parse) {
+            Some(mode) => mode,
+            None => {
+                return error_kind_json(
+                    "InvalidInput",
+                    serde_json::json!({ "reason": "mode must be \"merge\" or \"replace\"" }),
+                )
+            }
+        };
+
+        let checked = REGISTRY
+            .check_write(handle, "strata_import_json")
+            .and_then(|()| REGISTRY.check_unscoped(handle, "strata_import_json"));
+        if let Err(e) = checked {
+            return bridge_error_json(&e);
+        }
+        let result = REGISTRY.run_guarded(handle, |strata| {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("failed to read {path}: {e}"))?;
+            let dump: serde_json::Value = serde_json::from_str(&text)
+                .map_err(|e| format!("invalid dump JSON in {path}: {e}"))?;
+            commands::dump::import(strata, &dump, mode)
+        });
+        REGISTRY.invalidate_reads(handle, None);
+        match result {
+            Ok(counts) => ok_json(&counts.to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Command execution
+// ---------------------------------------------------------------------------
+
+/// Execute a single command against a database.
+///
+/// # Arguments
+/// - `handle`: handle ID from `strata_open`
+/// - `command_json`: null-terminated JSON string (externally-tagged Command),
+///   or for simple KV, state, JSON and event calls the compact positional
+///   form `["KvGet", "user:alice"]` (see `compact.rs`). Read commands take an
+///   optional `"fields": ["name", "role"]` that strips object values in the
+///   output down to those top-level keys.
+///
+/// # Returns
+/// JSON string (caller must free):
+/// - Success: the Output JSON (externally-tagged)
+/// - Error: `{"error": {...}}`; `{"error": {"HandleFaulted": {...}}}` once a
+///   command has panicked on this handle — close and reopen it to recover.
+///
+/// `handle` may also be a snapshot ID from `strata_snapshot_begin`, which
+/// takes stratadb read commands only, or a group ID from `strata_open_group`.
+#[no_mangle]
+pub extern "C" fn strata_execute(handle: u64, command_json: *const c_char) -> *mut c_char {
+    catch_panic(|| execute_to_json(handle, command_json))
+}
+
+/// Execute a command and return its output compressed, for large reads.
+///
+/// The buffer's first byte is a format flag: `0` means the rest is the
+/// output JSON as-is (used when compression would not help, e.g. small
+/// outputs), `1` means the rest is a zstd frame of the output JSON. The
+/// output is the same JSON `strata_execute` returns, errors included.
+///
+/// # Returns
+/// A buffer of `*out_len` bytes the caller must free with `strata_free_bytes`,
+/// or null if `out_len` is null.
+#[no_mangle]
+pub extern "C" fn strata_execute_compressed(
+    handle: u64,
+    command_json: *const c_char,
+    out_len: *mut usize,
+) -> *mut u8 {
+    if out_len.is_null() {
+        return std::ptr::null_mut();
+    }
+    let output = std::panic::catch_unwind(|| execute_to_json(handle, command_json))
+        .unwrap_or_else(|_| r#"{"error":{"Internal":{"reason":"panic in Rust bridge"}}}"#.to_string());
+    to_raw_bytes(Some(compress::encode(output.as_bytes())), out_len)
+}
+
+/// Execute a gzip-compressed command, for large payloads such as bulk upserts.
+///
+/// `data` is `len` bytes of gzip holding the command JSON `strata_execute`
+/// takes, and runs as `strata_execute` would, so `handle` may also be a group
+/// or a snapshot. Decompression stops at the handle's `max_command_bytes`
+/// (256 MiB if unset, or for groups and snapshots), failing with
+/// `{"error": {"CommandTooLarge": {...}}}`; data that is not gzip or not UTF-8
+/// fails with `InvalidInput`.
+///
+/// # Returns
+/// JSON string, as from `strata_execute`
+#[no_mangle]
+pub extern "C" fn strata_execute_gzip(handle: u64, data: *const u8, len: usize) -> *mut c_char {
+    catch_panic(|| {
+        if data.is_null() {
+            return error_json("data is null");
+        }
+        let data = unsafe { std::slice::from_raw_parts(data, len) };
+        let max_bytes = if GROUPS.contains(handle) || SNAPSHOTS.contains(handle) {
+            compress::DEFAULT_MAX_GUNZIP_BYTES
+        } else {
+            match REGISTRY.config(handle) {
+                Ok(config) => {
+                    config.max_command_bytes.unwrap_or(compress::DEFAULT_MAX_GUNZIP_BYTES)
+                }
+                Err(e) => return bridge_error_json(&e),
+            }
+        };
+        match compress::gunzip(data, max_bytes) {
+            Ok(json_str) => execute_str_to_json(handle, &json_str),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// The body of `strata_execute`: output JSON or `{"error": ...}`.
+fn execute_to_json(handle: u64, command_json: *const c_char) -> String {
+    match unsafe { cstr_to_str(command_json) } {
+        Some(json_str) => execute_str_to_json(handle, json_str),
+        None => error_json("command_json is null or invalid UTF-8"),
+    }
+}
+
+/// `execute_to_json` on command JSON already read, routing group and
+/// snapshot IDs.
+fn execute_str_to_json(handle: u64, json_str: &str) -> String {
+    if GROUPS.contains(handle) {
+        let result = GROUPS
+            .route(handle, json_str)
+            .and_then(|(shard, command)| execute_bounded(shard, &command));
+        return match result {
+            Ok(output) => output,
+            Err(e) => serde_json::json!({ "error": e.render(Some(json_str)) }).to_string(),
+        };
+    }
+    if SNAPSHOTS.contains(handle) {
+        return match SNAPSHOTS.execute(&REGISTRY, handle, json_str) {
+            Ok(output) => output.to_string(),
+            Err(e) => serde_json::json!({ "error": e.render(Some(json_str)) }).to_string(),
+        };
+    }
+    match execute_bounded(handle, json_str) {
+        Ok(output) => output,
+        Err(e) => serde_json::json!({ "error": e.render(Some(json_str)) }).to_string(),
+    }
+}
+
+/// `execute_and_notify`, giving up with `Timeout` after the handle's
+/// default timeout. stratadb commands cannot be cancelled, so a timed-out
+/// command still runs to completion on its worker thread.
+fn execute_bounded(handle: u64, json_str: &str) -> Result<String, BridgeError> {
+    let timeout_ms = REGISTRY.default_timeout_ms(handle);
+    if timeout_ms == 0 {
+        return execute_and_notify(handle, json_str);
+    }
+    let (tx, rx) = std::sync::mpsc::channel();
+    let command = json_str.to_string();
+    threads::spawn("command", move || {
+        let _ = tx.send(execute_and_notify(handle, &command));
+    })
+    .map_err(|e| BridgeError::from(format!("failed to spawn command thread: {e}")))?;
+
+    match rx.recv_timeout(std::time::Duration::from_millis(timeout_ms)) {
+        Ok(result) => result,
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            let e = BridgeError::Kind("Timeout", serde_json::json!({ "timeout_ms": timeout_ms }));
+            REGISTRY.record_error(handle, handle::command_tag(json_str), e.render(Some(json_str)));
+            Err(e)
+        }
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            Err(BridgeError::from("command thread exited without a result".to_string()))
+        }
+    }
+}
+
+/// Execute a command between the host's command hooks, then report its KV
+/// changes to any subscriptions. A failure becomes the handle's last error.
+fn execute_and_notify(handle: u64, json_str: &str) -> Result<String, BridgeError> {
+    let relaxed = REGISTRY.relax_json(handle, json_str);
+    let json_str = relaxed.as_deref().unwrap_or(json_str);
+    let result = hooks::before(json_str).and_then(|()| REGISTRY.execute(handle, json_str));
+    let output = result.inspect_err(|e| {
+        REGISTRY.record_error(handle, handle::command_tag(json_str), e.render(Some(json_str)));
+    })?;
+    if WATCHES.is_watched(handle) {
+        let command: serde_json::Value = serde_json::from_str(json_str).unwrap_or_default();
+        WATCHES.notify_command(handle, &command);
+    }
+    hooks::after(json_str, &output);
+    Ok(output)
+}
+
+/// Execute several commands in one call.
+///
+/// `batch_json` is `{"commands": [...], "atomic": false, "branch": null}` or
+/// a bare array of commands. A best-effort batch runs every command, as
+/// `strata_execute` would, and reports each result. An atomic batch runs
+/// them in one transaction on `branch`: if any fails, none are applied.
+/// Atomic batches take stratadb commands only, and count against a capped
+/// in-memory handle's `max_bytes` as a whole.
+///
+/// # Returns
+/// JSON string: `{"ok": [<output or {"error": ...}>, ...]}` (one per command),
+/// `{"error": {"BatchFailed": {"index", "error"}}}` if an atomic batch rolled
+/// back, or another `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_execute_batch(handle: u64, batch_json: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let json_str = match unsafe { cstr_to_str(batch_json) } {
+            Some(s) => s,
+            None => return error_json("batch_json is null or invalid UTF-8"),
+        };
+        let batch = match batch::Batch::parse(json_str) {
+            Ok(batch) => batch,
+            Err(e) => return bridge_error_json(&e),
+        };
+
+        let (commands, atomic, branch) = match batch {
+            batch::Batch::Options { commands, atomic, branch } => (commands, atomic, branch),
+            batch::Batch::Commands(commands) => (commands, false, None),
+        };
+        let commands: Vec<String> = commands.iter().map(|c| c.to_string()).collect();
+
+        if !atomic {
+            let results: Vec<String> = commands
+                .iter()
+                .map(|command| match execute_and_notify(handle, command) {
+                    Ok(output) => output,
+                    Err(e) => serde_json::json!({ "error": e.render(Some(command)) }).to_string(),
+                })
+                .collect();
+            return ok_json(&format!("[{}]", results.join(",")));
+        }
+
+        match execute_atomic(handle, branch.as_deref(), &commands) {
+            Ok(outputs) => ok_json(&format!("[{}]", outputs.join(","))),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Run an atomic batch between the command hooks, then audit it and report
+/// its KV changes once it has committed.
+fn execute_atomic(
+    handle: u64,
+    branch: Option<&str>,
+    commands: &[String],
+) -> Result<Vec<String>, BridgeError> {
+    for command in commands {
+        hooks::before(command)?;
+    }
+    let parsed: Vec<serde_json::Value> =
+        commands.iter().map(|c| serde_json::from_str(c).unwrap_or_default()).collect();
+    for command in &parsed {
+        REGISTRY.check_key_policy(handle, command)?;
+        if let Some((tag, _)) = command.as_object().and_then(|m| m.iter().next()) {
+            REGISTRY.check_writable(handle, tag)?;
+        }
+    }
+    let limits = REGISTRY.limits(handle);
+    let outputs = REGISTRY.run_guarded(handle, |strata| {
+        let run = |strata: &stratadb::Strata| batch::run_atomic(strata, branch, &parsed, limits);
+        match REGISTRY.memory_cap(handle) {
+            Some(cap) => cap.run_commands(strata, branch, &parsed, run),
+            None => run(strata),
+        }
+    });
+    REGISTRY.invalidate_reads(handle, None);
+    let outputs = outputs?;
+
+    let mut results = Vec::with_capacity(outputs.len());
+    for ((command, json), mut output) in parsed.iter().zip(commands).zip(outputs) {
+        REGISTRY.filter_keys(handle, &mut output);
+        if let Some((tag, body)) = command.as_object().and_then(|m| m.iter().next()) {
+            if audit::is_mutation(tag) {
+                REGISTRY.audit(handle, tag, audit::target_key(body));
+                REGISTRY.journal(handle, command);
+            }
+        }
+        WATCHES.notify_command(handle, command);
+        let output = output.to_string();
+        hooks::after(json, &output);
+        results.push(output);
+    }
+    Ok(results)
+}
+
+/// Execute a command at most once per idempotency key.
+///
+/// The first successful result under `idempotency_key` is remembered on the
+/// handle (up to 1024 keys, for 10 minutes); repeating the call returns it
+/// without running the command again, which makes retries of writes such
+/// as `EventAppend` safe. Failed commands are not remembered. Using a key
+/// for a different command is an error.
+///
+/// # Returns
+/// JSON string: the command's output, as from `strata_execute`,
+/// `{"error": {"IdempotencyKeyReused": {"key", "reason"}}}`, or another `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_execute_idempotent(
+    handle: u64,
+    idempotency_key: *const c_char,
+    command_json: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let key = match unsafe { cstr_to_str(idempotency_key) } {
+            Some(s) => s,
+            None => return error_json("idempotency_key is null or invalid UTF-8"),
+        };
+        let json_str = match unsafe { cstr_to_str(command_json) } {
+            Some(s) => s,
+            None => return error_json("command_json is null or invalid UTF-8"),
+        };
+
+        match IDEMPOTENCY.execute(handle, key, json_str, || execute_and_notify(handle, json_str)) {
+            Ok(output) => output,
+            Err(e) => serde_json::json!({ "error": e.render(Some(json_str)) }).to_string(),
+        }
+    })
+}
+
+/// Copy a KV value from one open database to another.
+///
+/// The value moves inside Rust rather than round-tripping through Swift as
+/// JSON. `src_handle` and `dst_handle` may be the same database.
+///
+/// # Arguments
+/// - `key`: the key to read on `src_handle`
+/// - `dst_key`: the key to write on `dst_handle`, or null to reuse `key`
+///
+/// # Returns
+/// JSON string: `{"ok": <version>}` (the version written on `dst_handle`),
+/// `{"error": {"KeyNotFound": {"key", "handle"}}}` if `key` is absent on the
+/// source, or another `{"error": {...}}`.
+#[no_mangle]
+pub extern "C" fn strata_copy_kv(
+    src_handle: u64,
+    dst_handle: u64,
+    key: *const c_char,
+    dst_key: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let key = match unsafe { cstr_to_str(key) } {
+            Some(s) => s,
+            None => return error_json("key is null or invalid UTF-8"),
+        };
+        let dst_key = if dst_key.is_null() {
+            key
+        } else {
+            match unsafe { cstr_to_str(dst_key) } {
+                Some(s) => s,
+                None => return error_json("dst_key is invalid UTF-8"),
+            }
+        };
+
+        match REGISTRY.copy_kv(src_handle, dst_handle, key, dst_key) {
+            Ok(version) => {
+                REGISTRY.invalidate_reads(dst_handle, Some("KvPut"));
+                REGISTRY.audit(dst_handle, "KvPut", Some(dst_key));
+                WATCHES.notify(dst_handle, dst_key, "put");
+                ok_json(&version.to_string())
+            }
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Raw bytes
+// ---------------------------------------------------------------------------
+
+/// Store `len` bytes at `data` under `key` as a `Value::Bytes`, skipping JSON.
+///
+/// The bytes are copied; any content, including NULs, round-trips exactly.
+/// On a capped in-memory handle the key and bytes count against `max_bytes`,
+/// so a `"reject"` cap refuses the put with `CacheFull`.
+///
+/// # Returns
+/// JSON string: `{"ok": <version>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_kv_put_bytes(
+    handle: u64,
+    key: *const c_char,
+    data: *const u8,
+    len: usize,
+) -> *mut c_char {
+    catch_panic(|| {
+        let key = match unsafe { cstr_to_str(key) } {
+            Some(s) => s,
+            None => return error_json("key is null or invalid UTF-8"),
+        };
+        if data.is_null() && len > 0 {
+            return error_json("data is null");
+        }
+        let bytes = if len == 0 {
+            Vec::new()
+        } else {
+            unsafe { std::slice::from_raw_parts(data, len) }.to_vec()
+        };
+
+        let policy = REGISTRY.key_policy(handle);
+        if let Err(e) = policy.map_or(Ok(()), |policy| policy.check(key)) {
+            return bridge_error_json(&e);
+        }
+        // Only pay for encoding the bytes as JSON when the handle is journaled.
+        let journaled = REGISTRY.is_journaled(handle).then(|| {
+            let value = stratadb::Value::Bytes(bytes.clone());
+            serde_json::json!({ "KvPut": { "key": key, "value": value } })
+        });
+        let result = REGISTRY.run_put(handle, key, len as u64, |strata| {
+            strata
+                .kv_put(key, stratadb::Value::Bytes(bytes))
+                .map_err(|e| BridgeError::from(e.to_string()))
+        });
+        match result {
+            Ok(version) => {
+                REGISTRY.invalidate_reads(handle, Some("KvPut"));
+                if let Some(command) = &journaled {
+                    REGISTRY.journal(handle, command);
+                }
+                REGISTRY.audit(handle, "KvPut", Some(key));
+                WATCHES.notify(handle, key, "put");
+                ok_json(&version.to_string())
+            }
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Read a `Value::Bytes` stored under `key`.
+///
+/// On success returns a buffer of `*out_len` bytes that the caller must free
+/// with `strata_free_bytes`; an empty value gives a non-null pointer and a
+/// length of 0. Returns null (with `*out_len` set to 0) if the key is
+/// missing, holds a non-bytes value, or the read fails.
+#[no_mangle]
+pub extern "C" fn strata_kv_get_bytes(
+    handle: u64,
+    key: *const c_char,
+    out_len: *mut usize,
+) -> *mut u8 {
+    let result = std::panic::catch_unwind(|| {
+        let key = unsafe { cstr_to_str(key) }?;
+        REGISTRY.check_key_policy(handle, &serde_json::json!({ "KvGet": { "key": key } })).ok()?;
+        REGISTRY
+            .run_guarded(handle, |strata| {
+                let output = commands::call(
+                    &mut strata.executor(),
+                    serde_json::json!({ "KvGet": { "key": key } }),
+                )?;
+                let bytes = commands::maybe_versioned(output).and_then(|record| {
+                    record["value"]["Bytes"]
+                        .as_array()?
+                        .iter()
+                        .map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                        .collect::<Option<Vec<u8>>>()
+                });
+                Ok(bytes)
+            })
+            .ok()
+            .flatten()
+    });
+
+    to_raw_bytes(result.ok().flatten(), out_len)
+}
+
+// ---------------------------------------------------------------------------
+// Snapshots
+// ---------------------------------------------------------------------------
+
+/// Pin a consistent read view of a handle's database.
+///
+/// Pass the snapshot ID to `strata_execute` in place of the handle: reads
+/// see the database as it was when the snapshot began, whatever is written
+/// since. Writes through it fail with `{"error": {"SnapshotReadOnly": {...}}}`.
+/// Release it with `strata_snapshot_end`; closing the handle releases its
+/// snapshots.
+///
+/// # Returns
+/// JSON string: `{"ok": <snapshot_id>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_snapshot_begin(handle: u64) -> *mut c_char {
+    catch_panic(|| match SNAPSHOTS.begin(&REGISTRY, handle) {
+        Ok(id) => ok_json(&id.to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Release a snapshot from `strata_snapshot_begin`.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_snapshot_end(snapshot_id: u64) -> *mut c_char {
+    catch_panic(|| match SNAPSHOTS.end(snapshot_id) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Transactions
+// ---------------------------------------------------------------------------
+
+/// Begin a transaction on a handle.
+///
+/// Commands run with `strata_txn_execute` see the transaction's writes and
+/// are invisible to other callers until `strata_txn_commit`. Closing the
+/// handle rolls back its open transactions.
+///
+/// # Arguments
+/// - `branch`: null-terminated branch name, or null for the default branch
+///
+/// # Returns
+/// JSON string: `{"ok": <txn_id>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_txn_begin(handle: u64, branch: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let branch = unsafe { cstr_to_str(branch) };
+        match TXNS.begin(&REGISTRY, handle, branch) {
+            Ok(id) => ok_json(&id.to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Execute a stratadb command inside a transaction.
+///
+/// # Returns
+/// JSON string: the command's output, as from `strata_execute`, or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_txn_execute(txn_id: u64, command_json: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let json_str = match unsafe { cstr_to_str(command_json) } {
+            Some(s) => s,
+            None => return error_json("command_json is null or invalid UTF-8"),
+        };
+
+        match TXNS.execute(&REGISTRY, txn_id, json_str) {
+            Ok(output) => output.to_string(),
+            Err(e) => serde_json::json!({ "error": e.render(Some(json_str)) }).to_string(),
+        }
+    })
+}
+
+/// Mark a savepoint in a transaction to roll back to later.
+///
+/// # Returns
+/// JSON string: `{"ok": <savepoint_id>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_txn_savepoint(txn_id: u64) -> *mut c_char {
+    catch_panic(|| match TXNS.savepoint(txn_id) {
+        Ok(id) => ok_json(&id.to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Undo a transaction's writes back to a savepoint, keeping it open.
+///
+/// Writes made before the savepoint survive and are committed with the
+/// transaction. Savepoints created after it are released.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_txn_rollback_to(txn_id: u64, savepoint_id: u64) -> *mut c_char {
+    catch_panic(|| match TXNS.rollback_to(&REGISTRY, txn_id, savepoint_id) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Commit a transaction. The transaction ID is invalid afterwards.
+///
+/// On a capped in-memory handle the transaction's KV writes count against
+/// `max_bytes`; a `"reject"` cap they would overflow fails the commit with
+/// `CacheFull` and rolls the transaction back.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_txn_commit(txn_id: u64) -> *mut c_char {
+    catch_panic(|| match TXNS.commit(&REGISTRY, txn_id) {
+        Ok((handle, writes)) => {
+            REGISTRY.invalidate_reads(handle, None);
+            for write in &writes {
+                if let Some((tag, body)) = write.as_object().and_then(|m| m.iter().next()) {
+                    REGISTRY.audit(handle, tag, audit::target_key(body));
+                }
+                REGISTRY.journal(handle, write);
+                WATCHES.notify_command(handle, write);
+            }
+            ok_json("null")
+        }
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Roll back a transaction. The transaction ID is invalid afterwards.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_txn_rollback(txn_id: u64) -> *mut c_char {
+    catch_panic(|| match TXNS.rollback(txn_id) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Change subscriptions
+// ---------------------------------------------------------------------------
+
+/// Subscribe to changes of KV keys starting with `prefix` on a handle.
+///
+/// `callback` is called with `{"key": "...", "event": "put" | "delete"}` and
+/// `user_data` after each write to a matching key made through this handle,
+/// including `strata_kv_put_bytes`, `strata_copy_kv` and committed
+/// transactions. It runs on the writing thread, after the write succeeded.
+/// Subscriptions end with `strata_kv_unsubscribe` or when the handle closes.
+///
+/// # Returns
+/// JSON string: `{"ok": <subscription_id>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_kv_subscribe_prefix(
+    handle: u64,
+    prefix: *const c_char,
+    callback: Option<watch::WatchCallback>,
+    user_data: *mut c_void,
+) -> *mut c_char {
+    catch_panic(|| {
+        let prefix = match unsafe { cstr_to_str(prefix) } {
+            Some(s) => s,
+            None => return error_json("prefix is null or invalid UTF-8"),
+        };
+        let Some(callback) = callback else {
+            return error_json("callback is null");
+        };
+        if REGISTRY.uptime_ms(handle).is_none() {
+            return error_json("invalid handle");
+        }
+
+        let id = WATCHES.subscribe(handle, prefix, callback, user_data);
+        ok_json(&id.to_string())
+    })
+}
+
+/// End a subscription. Unknown IDs are ignored.
+#[no_mangle]
+pub extern "C" fn strata_kv_unsubscribe(subscription_id: u64) {
+    WATCHES.unsubscribe(subscription_id);
+}
+
+// ---------------------------------------------------------------------------
+// Streaming
+// ---------------------------------------------------------------------------
+
+/// Execute a list command such as `KvList` or `VectorSearch` and deliver
+/// each row to `callback` as a MessagePack buffer `(ptr, len)`, skipping JSON
+/// text for large result sets. Rows are the same values `strata_stream_next`
+/// returns, fetched page by page as the callback consumes them.
+///
+/// The callback runs on the calling thread before this returns. Each buffer
+/// is owned by the bridge and valid only during the callback: copy it to
+/// keep it. Returning nonzero from the callback stops the stream.
+///
+/// Like `strata_stream_open`, only read and list commands are accepted;
+/// anything else fails with `InvalidInput` before the callback is called.
+///
+/// # Returns
+/// JSON string: `{"ok": {"rows": n, "cancelled": bool}}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_execute_stream_msgpack(
+    handle: u64,
+    command_json: *const c_char,
+    callback: Option<stream::RowCallback>,
+    user_data: *mut c_void,
+) -> *mut c_char {
+    catch_panic(|| {
+        let json_str = match unsafe { cstr_to_str(command_json) } {
+            Some(s) => s,
+            None => return error_json("command_json is null or invalid UTF-8"),
+        };
+        let Some(callback) = callback else {
+            return error_json("callback is null");
+        };
+
+        match STREAMS.drain_msgpack(&REGISTRY, handle, json_str, callback, user_data) {
+            Ok((rows, cancelled)) => {
+                ok_json(&serde_json::json!({ "rows": rows, "cancelled": cancelled }).to_string())
+            }
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Open a pull-based stream over a list command such as `KvList`.
+///
+/// Rows are fetched lazily as `strata_stream_next` is called, so Swift
+/// consumes them at its own pace. Only read and list commands can be
+/// streamed; any other command fails with `InvalidInput`.
+///
+/// # Returns
+/// JSON string: `{"ok": <stream_id>}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_stream_open(handle: u64, command_json: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let json_str = match unsafe { cstr_to_str(command_json) } {
+            Some(s) => s,
+            None => return error_json("command_json is null or invalid UTF-8"),
+        };
+
+        match STREAMS.open(&REGISTRY, handle, json_str) {
+            Ok(id) => ok_json(&id.to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+/// Pull the next row from a stream.
+///
+/// # Returns
+/// JSON string: `{"ok": <row>}`, `{"done": true}` once the stream is drained,
+/// or `{"error": {...}}`.
+#[no_mangle]
+pub extern "C" fn strata_stream_next(stream_id: u64) -> *mut c_char {
+    catch_panic(|| match STREAMS.next(&REGISTRY, stream_id) {
+        Ok(Some(row)) => ok_json(&row.to_string()),
+        Ok(None) => r#"{"done":true}"#.to_string(),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Close a stream and discard any buffered rows.
+#[no_mangle]
+pub extern "C" fn strata_stream_close(stream_id: u64) {
+    STREAMS.close(stream_id);
+}
+
+/// Sort the output of list commands (`KvList`, `StateList`, `JsonList`,
+/// `VectorListCollections`, `BranchList`) by key on this handle.
+///
+/// Costs a sort per list call, so it is off by default; turn it on for
+/// snapshot tests that need reproducible ordering.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_deterministic_iteration(handle: u64, enabled: bool) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_deterministic_iteration(handle, enabled) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Serialize every `Int` value in this handle's outputs as a JSON string,
+/// e.g. `{"Int": "152847"}`, so hosts that parse numbers as doubles keep
+/// 64-bit precision. Applies to every command executed on the handle, except
+/// `JsonGetInline`, whose plain JSON has no tagged values.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_int_as_string(handle: u64, enabled: bool) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_int_as_string(handle, enabled) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Accept `//` line comments and trailing commas in command JSON on this
+/// handle, for commands written by hand in a debug console. They are
+/// stripped before the command is parsed; string contents are untouched.
+/// Off by default, so production commands are parsed strictly.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_tolerant_json(handle: u64, enabled: bool) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_tolerant_json(handle, enabled) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Bound every `strata_execute` call on this handle to `ms` milliseconds;
+/// zero removes the bound. A command still running at the deadline fails
+/// with `{"error": {"Timeout": {"timeout_ms"}}}` but is not cancelled: it
+/// completes in the background. Reported by `strata_get_config` as
+/// `default_timeout_ms`.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_default_timeout_ms(handle: u64, ms: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_default_timeout_ms(handle, ms) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Cache the outputs of up to `max_entries` reads on this handle, evicting
+/// the least recently used; zero disables the cache. A `KvGet`, `KvList`,
+/// `JsonGet`, `JsonList`, `StateGet` or `StateList` repeated with the same
+/// command JSON is answered from the cache until a write to its primitive,
+/// through any handle on the same database, drops it. Hits and misses are
+/// reported by `strata_stats` as `read_cache`.
+///
+/// # Returns
+/// JSON string: `{"ok": null}` or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_set_read_cache(handle: u64, max_entries: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.set_read_cache(handle, max_entries as usize) {
+        Ok(()) => ok_json("null"),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+/// Preview roughly what a command would do, without executing it.
+///
+/// Estimates come from stratadb metadata such as vector collection stats and
+/// the event log length. Commands without a meaningful plan return only
+/// their tag, with null estimates.
+///
+/// # Returns
+/// JSON string: `{"ok": {"command", "estimated_rows", "uses_index", "notes": [...]}}`
+/// or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_explain(handle: u64, command_json: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let json_str = match unsafe { cstr_to_str(command_json) } {
+            Some(s) => s,
+            None => return error_json("command_json is null or invalid UTF-8"),
+        };
+
+        let result = REGISTRY.run_guarded(handle, |strata| {
+            let command: serde_json::Value = serde_json::from_str(json_str)
+                .map_err(|e| format!("invalid command JSON: {e}"))?;
+            commands::explain::explain(strata, &command)
+        });
+        match result {
+            Ok(plan) => ok_json(&plan.to_string()),
+            Err(e) => bridge_error_json(&e),
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Diagnostics
+// ---------------------------------------------------------------------------
+
+/// Set how much detail errors carry across the boundary.
+///
+/// - `0`: the error variant and a short message only — for production builds
+/// - `1`: the variant and all of its fields (the default)
+/// - `2`: additionally the originating command and, for stratadb failures,
+///   the decoded underlying error under `cause`
+///
+/// Out-of-range levels are clamped.
+#[no_mangle]
+pub extern "C" fn strata_set_error_verbosity(level: i32) {
+    error::set_verbosity(level);
+}
+
+/// Register a callback that receives bridge log messages, or pass null to clear it.
+///
+/// The callback gets a level (0 = error, 1 = warn, 2 = info, 3 = debug) and a
+/// null-terminated message that is only valid for the duration of the call.
+/// It may be invoked from any thread.
+#[no_mangle]
+pub extern "C" fn strata_set_log_callback(callback: Option<log::LogCallback>) {
+    log::set_callback(callback);
+}
+
+/// Register a callback told what each `strata_notify_memory_pressure` freed,
+/// or pass null to clear it.
+///
+/// The callback gets the level, the same summary JSON the notification
+/// returns, valid only for the duration of the call, and `user_data`. It
+/// runs on the notifying thread.
+#[no_mangle]
+pub extern "C" fn strata_set_memory_pressure_callback(
+    callback: Option<pressure::MemoryPressureCallback>,
+    user_data: *mut c_void,
+) {
+    pressure::set_callback(callback, user_data);
+}
+
+/// Free memory in response to a platform memory warning.
+///
+/// `level` is 0 (normal), 1 (warning) or 2 (critical). Every level drops
+/// all handles' read caches (`strata_set_read_cache`). In-memory handles
+/// opened with `"eviction": "lru"` additionally evict their least recently
+/// used keys: a quarter of their bytes at level 1, half at level 2.
+///
+/// # Returns
+/// JSON string: `{"ok": {"level", "read_cache_entries", "evicted_keys", "evicted_bytes"}}`
+#[no_mangle]
+pub extern "C" fn strata_notify_memory_pressure(level: i32) -> *mut c_char {
+    catch_panic(|| {
+        let summary = REGISTRY.relieve_memory_pressure(level);
+        pressure::report(level, &summary);
+        ok_json(&summary.to_string())
+    })
+}
+
+/// Register hooks that run around every `strata_execute` (and
+/// `strata_execute_idempotent`) command, or pass null to clear either one.
+///
+/// `pre` gets the command's tag and JSON before it runs; a nonzero return
+/// rejects it with `{"error": {"Rejected": {"command", "code"}}}`. `post`
+/// gets the tag and output JSON after it succeeds. Strings are only valid
+/// for the duration of the call. Hooks may be invoked from any thread.
+#[no_mangle]
+pub extern "C" fn strata_set_command_hook(
+    pre: Option<hooks::PreHook>,
+    post: Option<hooks::PostHook>,
+) {
+    hooks::set(pre, post);
+}
+
+/// Set the prefix for names of threads the bridge spawns, e.g. `"strata"`
+/// gives `strata-worker-0`. Null or empty restores the default `"strata"`.
+///
+/// Applies to threads spawned after the call.
+#[no_mangle]
+pub extern "C" fn strata_set_thread_name_prefix(prefix: *const c_char) {
+    threads::set_prefix(unsafe { cstr_to_str(prefix) });
+}
+
+/// Log a warning (via the log callback) for any command slower than `ms` milliseconds.
+///
+/// The warning names the command tag, the handle, and the elapsed time.
+/// Zero disables the check (the default).
+#[no_mangle]
+pub extern "C" fn strata_set_slow_command_threshold_ms(ms: u64) {
+    REGISTRY.set_slow_command_threshold_ms(ms);
+}
+
+/// One cheap "is everything okay" check for status displays and monitoring.
+///
+/// `ok` is false while any handle is faulted. `worker_threads` counts the
+/// bridge's own running threads (e.g. autoflush), and `last_panic` is the
+/// message of the most recent panic caught at the boundary, if any. Takes no
+/// handle locks except to read fault reasons.
+///
+/// # Returns
+/// JSON string: `{"ok": bool, "handles": n, "faulted_handles": [{"handle", "reason"}],
+/// "worker_threads": n, "last_panic": "..."|null}` — not wrapped in `ok`,
+/// since this never fails.
+#[no_mangle]
+pub extern "C" fn strata_health() -> *mut c_char {
+    catch_panic(|| {
+        let (handles, faulted) = REGISTRY.health();
+        serde_json::json!({
+            "ok": faulted.is_empty(),
+            "handles": handles,
+            "faulted_handles": faulted,
+            "worker_threads": threads::running(),
+            "last_panic": error::last_panic(),
+        })
+        .to_string()
+    })
+}
+
+/// What the linked stratadb build supports, so the host can hide features
+/// it lacks. Probed once per process against a throwaway in-memory database.
+///
+/// # Returns
+/// JSON string: `{"vectors": bool, "branches": bool, "encryption": bool,
+/// "time_travel": bool}` — not wrapped in `ok`, since this never fails.
+#[no_mangle]
+pub extern "C" fn strata_capabilities() -> *mut c_char {
+    catch_panic(|| capabilities::capabilities().to_string())
+}
+
+/// Pin the clock the bridge stamps times with (audit entries, last errors,
+/// open times, backup names) to `millis` since the Unix epoch, so tests get
+/// deterministic timestamps. A negative value restores the real clock.
+///
+/// Times stratadb records itself, such as value timestamps, are unaffected.
+/// Only exported from debug builds.
+#[cfg(debug_assertions)]
+#[no_mangle]
+pub extern "C" fn strata_set_clock_fixed(millis: i64) {
+    handle::set_fixed_clock(u64::try_from(millis).ok());
+}
+
+/// The most recent error from a command executed on `handle`, for when the
+/// error JSON returned at the time was dropped.
+///
+/// Covers `strata_execute`, `strata_execute_idempotent`, and best-effort
+/// batches. It is kept per handle, so any thread can read it, and replaced by
+/// each later failure.
+///
+/// # Returns
+/// JSON string: `{"ok": {"command", "error", "at_ms"}}` where `command` is
+/// the failed command's tag and `at_ms` is milliseconds since the Unix epoch,
+/// `{"ok": null}` if no command has failed, or `{"error": {...}}`
+#[no_mangle]
+pub extern "C" fn strata_handle_last_error(handle: u64) -> *mut c_char {
+    catch_panic(|| match REGISTRY.last_error(handle) {
+        Ok(last_error) => ok_json(&serde_json::json!(last_error).to_string()),
+        Err(e) => bridge_error_json(&e),
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Introspection
+// ---------------------------------------------------------------------------
+
+/// Describe a command tag for inline help in the command console.
+///
+/// # Arguments
+/// - `tag`: null-terminated command tag, e.g. `"KvGet"`
+///
+/// # Returns
+/// JSON string (caller must free):
+/// - Success: `{"ok": {"tag": "...", "fields": [{"name","type","required"}], "summary": "..."}}`
+/// - Unknown tag: `{"error": {"NotFound": {"tag": "..."}}}`
+#[no_mangle]
+pub extern "C" fn strata_describe_command(tag: *const c_char) -> *mut c_char {
+    catch_panic(|| {
+        let tag_str = match unsafe { cstr_to_str(tag) } {
+            Some(s) => s,
+            None => return error_json("tag is null or invalid UTF-8"),
+        };
+
+        match schema::describe(tag_str) {
+            Some(desc) => ok_json(&desc.to_json().to_string()),
+            None => error_kind_json("NotFound", serde_json::json!({ "tag": tag_str })),
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Memory management
+// ---------------------------------------------------------------------------
+
+/// Free a string returned by any `strata_*` function.
+///
+/// # Safety
+/// `ptr` must have been returned by a `strata_*` function and not yet freed.
+/// In safe mode (`strata_set_safe_free`), other pointers are logged and
+/// ignored instead.
+#[no_mangle]
+pub unsafe extern "C" fn strata_free_string(ptr: *mut c_char) {
+    if !ptr.is_null() && safe_free::release(ptr as *const u8) {
+        unsafe {
+            let _ = CString::from_raw(ptr);
+        }
+    }
+}
+
+/// Turn safe mode for `strata_free_string` on or off.
+///
+/// In safe mode the bridge remembers every string it returns, and
+/// `strata_free_string` logs and ignores pointers it did not hand out (or
+/// already freed) instead of corrupting the heap. Meant for development; it
+/// adds bookkeeping to every call. Enable it before other calls, since
+/// strings returned while it was off cannot be freed while it is on.
+#[no_mangle]
+pub extern "C" fn strata_set_safe_free(enabled: bool) {
+    safe_free::set_enabled(enabled);
+}
+
+/// Free a buffer returned by `strata_kv_get_bytes` or `strata_execute_compressed`.
+///
+/// # Safety
+/// `ptr` and `len` must be exactly the pointer and length returned by the
+/// `strata_*` function, and the buffer must not have been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn strata_free_bytes(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        unsafe {
+            let _ = Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len));
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Smoke test
+// ---------------------------------------------------------------------------
+
+/// Returns `{"ok": "strata-foundry-bridge"}`. Use to verify the dylib loads.
+#[no_mangle]
+pub extern "C" fn strata_ping() -> *mut c_char {
+    to_c_string(r#"{"ok":"strata-foundry-bridge"}"#)
+}
+
+/// Value returned by `strata_ping_fast`.
+pub const PING_FAST_SENTINEL: i32 = 1;
+
+/// Returns `PING_FAST_SENTINEL` (1) without allocating or touching the registry.
+///
+/// For benchmarking raw FFI call latency separately from JSON marshaling;
+/// there is nothing to free.
+#[no_mangle]
+pub extern "C" fn strata_ping_fast() -> i32 {
+    PING_FAST_SENTINEL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Open an in-memory database and return its handle ID.
+    fn open_memory_handle() -> u64 {
+        let ptr = strata_open_memory();
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        v["ok"].as_u64().unwrap_or_else(|| panic!("expected ok with handle id, got: {result}"))
+    }
+
+    /// Convert plain JSON into stratadb's externally-tagged `Value` encoding.
+    fn tagged(v: serde_json::Value) -> serde_json::Value {
+        use serde_json::{json, Value};
+        match v {
+            Value::Null => json!("Null"),
+            Value::Bool(b) => json!({ "Bool": b }),
+            Value::Number(n) if n.is_i64() => json!({ "Int": n }),
+            Value::Number(n) => json!({ "Float": n }),
+            Value::String(s) => json!({ "String": s }),
+            Value::Array(items) => json!({ "Array": items.into_iter().map(tagged).collect::<Vec<_>>() }),
+            Value::Object(map) => json!({
+                "Object": map.into_iter().map(|(k, v)| (k, tagged(v))).collect::<serde_json::Map<_, _>>()
+            }),
+        }
+    }
+
+    /// Open an in-memory database seeded with a subset of the `create_sample_db` data.
+    fn open_sample_handle() -> u64 {
+        use serde_json::json;
+
+        let handle = open_memory_handle();
+        let run = |cmd: serde_json::Value| {
+            let v = execute_json(handle, &cmd.to_string());
+            assert!(v.get("error").is_none(), "seeding failed for {cmd}: {v}");
+        };
+
+        let kv = [
+            ("user:alice", json!({"name": "Alice Chen", "email": "alice@example.com", "age": 30, "role": "admin", "active": true})),
+            ("user:bob", json!({"name": "Bob Martinez", "email": "bob@example.com", "age": 25, "role": "developer", "active": true})),
+            ("user:carol", json!({"name": "Carol Kim", "email": "carol@example.com", "age": 35, "role": "designer", "active": false})),
+            ("config:app_version", json!("2.1.0")),
+            ("config:max_retries", json!(3)),
+            ("config:timeout_ms", json!(5000)),
+            ("config:debug_mode", json!(false)),
+            ("config:allowed_origins", json!(["https://app.strata.dev", "https://localhost:3000", "https://staging.strata.dev"])),
+            ("counter:page_views", json!(48291)),
+            ("counter:api_calls", json!(152847)),
+            ("counter:errors", json!(37)),
+            ("cache:trending_topics", json!(["rust", "ai-agents", "embedded-databases", "swiftui"])),
+            ("cache:exchange_rates", json!({"USD_EUR": 0.92, "USD_GBP": 0.79, "USD_JPY": 149.85, "updated_at": "2026-02-20T15:00:00Z"})),
+            ("session:abc123", json!({"user_id": "alice", "created_at": "2026-02-20T14:30:00Z", "expires_at": "2026-02-21T14:30:00Z", "ip": "192.168.1.42"})),
+        ];
+        for (key, value) in kv {
+            run(json!({"KvPut": {"key": key, "value": tagged(value)}}));
+        }
+
+        let state = [
+            ("agent:status", json!("idle")),
+            ("agent:step_count", json!(47)),
+            ("agent:last_action", json!("tool_call: search_docs")),
+            ("agent:memory_mb", json!(128.5)),
+            ("agent:errors_total", json!(2)),
+            ("pipeline:stage", json!("indexing")),
+            ("pipeline:progress", json!(0.73)),
+        ];
+        for (cell, value) in state {
+            run(json!({"StateSet": {"cell": cell, "value": tagged(value)}}));
+        }
+
+        let events = [
+            ("system", json!({"action": "startup", "version": "1.0.0"})),
+            ("auth", json!({"action": "login", "user": "alice", "method": "api_key"})),
+            ("tool_call", json!({"tool": "web_search", "query": "rust embedded database benchmarks", "duration_ms": 342, "results": 15})),
+            ("observation", json!({"content": "Found 15 results about embedded databases."})),
+            ("decision", json!({"reasoning": "Results look promising.", "confidence": 0.85})),
+            ("tool_call", json!({"tool": "read_document", "doc_id": "benchmark-report-2026", "duration_ms": 89})),
+            ("tool_call", json!({"tool": "read_document", "doc_id": "stratadb-architecture", "duration_ms": 124})),
+            ("observation", json!({"content": "Architecture doc describes 6 primitives."})),
+            ("tool_call", json!({"tool": "kv_put", "key": "cache:summary", "duration_ms": 3})),
+            ("error", json!({"message": "Rate limit exceeded for web_search", "code": 429, "retry_after_ms": 5000})),
+            ("system", json!({"action": "rate_limit_backoff", "wait_ms": 5000})),
+            ("tool_call", json!({"tool": "web_search", "query": "strata vs redb vs sqlite comparison", "duration_ms": 567, "results": 8})),
+            ("observation", json!({"content": "Comparison shows StrataDB excels at branching."})),
+            ("decision", json!({"reasoning": "Have enough data to write the summary.", "confidence": 0.92})),
+            ("tool_call", json!({"tool": "generate_text", "prompt_tokens": 2048, "completion_tokens": 512, "duration_ms": 1843})),
+            ("tool_call", json!({"tool": "json_set", "key": "doc:report", "duration_ms": 5})),
+            ("auth", json!({"action": "login", "user": "bob", "method": "oauth"})),
+            ("tool_call", json!({"tool": "kv_list", "prefix": "user:", "duration_ms": 2, "results": 3})),
+            ("system", json!({"action": "checkpoint", "step": 47})),
+            ("system", json!({"action": "task_complete", "total_steps": 47, "total_tool_calls": 8, "total_tokens": 4096})),
+        ];
+        for (kind, payload) in events {
+            run(json!({"EventAppend": {"event_type": kind, "payload": tagged(payload)}}));
+        }
+
+        let docs = [
+            ("doc:readme", json!({"title": "Getting Started with StrataDB", "author": "Alice Chen", "created": "2026-01-15T10:00:00Z", "content": "StrataDB is an embedded database built for AI agents.", "tags": ["docs", "getting-started", "tutorial"], "status": "published"})),
+            ("doc:changelog", json!({"version": "0.5.1", "date": "2026-02-18", "changes": [{"type": "feature", "description": "Added branch diff and merge support"}], "breaking_changes": false})),
+            ("doc:agent-config", json!({"name": "research-agent-v2", "model": "claude-sonnet-4-6", "max_steps": 100, "tools": ["web_search", "read_document"], "temperature": 0.7, "system_prompt": "You are a research assistant."})),
+            ("doc:report", json!({"title": "Embedded Database Comparison 2026", "author": "research-agent-v2", "generated_at": "2026-02-20T15:30:00Z", "summary": "Analysis of 5 embedded databases across 12 benchmarks.", "databases": ["StrataDB", "SQLite", "redb", "LMDB", "RocksDB"], "recommendation": "StrataDB recommended for AI agent workloads."})),
+        ];
+        for (key, doc) in docs {
+            run(json!({"JsonSet": {"key": key, "path": "$", "value": tagged(doc)}}));
+        }
+
+        run(json!({"BranchCreate": {"branch_id": "experiment"}}));
+        run(json!({"BranchCreate": {"branch_id": "staging"}}));
+        handle
+    }
+
+    /// Run a command through `strata_execute` and parse the JSON response.
+    fn execute_json(handle: u64, command: &str) -> serde_json::Value {
+        let cmd = CString::new(command).unwrap();
+        let ptr = strata_execute(handle, cmd.as_ptr());
+        let out = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        serde_json::from_str(&out).unwrap()
+    }
+
+    #[test]
+    fn test_open_memory_and_execute_ping() {
+        let result_ptr = strata_open_memory();
+        let result = unsafe { CStr::from_ptr(result_ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(result_ptr) };
+
+        // Parse handle ID
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let handle_id = v["ok"].as_u64().expect("expected ok with handle id");
+
+        // Execute Ping command
+        let cmd = CString::new(r#"{"Ping":null}"#).unwrap();
+        let out_ptr = strata_execute(handle_id, cmd.as_ptr());
+        let out = unsafe { CStr::from_ptr(out_ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(out_ptr) };
+
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert!(v["Pong"].is_object(), "Expected Pong, got: {}", out);
+
+        strata_close(handle_id);
+    }
+
+    #[test]
+    fn test_strata_ping() {
+        let ptr = strata_ping();
+        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(s, r#"{"ok":"strata-foundry-bridge"}"#);
+    }
+
+    #[test]
+    fn test_strata_ping_fast() {
+        assert_eq!(strata_ping_fast(), 1);
+        assert_eq!(strata_ping_fast(), PING_FAST_SENTINEL);
+    }
+
+    #[test]
+    fn test_describe_command() {
+        let tag = CString::new("KvGet").unwrap();
+        let ptr = strata_describe_command(tag.as_ptr());
+        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+
+        let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+        assert_eq!(v["ok"]["tag"], "KvGet");
+        let fields = v["ok"]["fields"].as_array().expect("expected fields array");
+        let key = fields.iter().find(|f| f["name"] == "key").expect("expected key field");
+        assert_eq!(key["required"], true);
+
+        let tag = CString::new("NoSuchCommand").unwrap();
+        let ptr = strata_describe_command(tag.as_ptr());
+        let s = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&s).unwrap();
+        assert!(v["error"]["NotFound"].is_object(), "Expected NotFound, got: {}", s);
+    }
+
+    #[test]
+    fn test_panicking_command_faults_handle() {
+        let result_ptr = strata_open_memory();
+        let result = unsafe { CStr::from_ptr(result_ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(result_ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let handle_id = v["ok"].as_u64().expect("expected ok with handle id");
+
+        let panicked: Result<(), BridgeError> =
+            REGISTRY.run_guarded(handle_id, |_| panic!("simulated command panic"));
+        assert!(matches!(panicked, Err(BridgeError::Kind("HandleFaulted", _))));
+
+        // Subsequent commands are rejected until the handle is reopened.
+        let cmd = CString::new(r#"{"Ping":null}"#).unwrap();
+        let out_ptr = strata_execute(handle_id, cmd.as_ptr());
+        let out = unsafe { CStr::from_ptr(out_ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(out_ptr) };
+
+        let v: serde_json::Value = serde_json::from_str(&out).unwrap();
+        let fault = &v["error"]["HandleFaulted"];
+        assert!(fault.is_object(), "Expected HandleFaulted, got: {}", out);
+        assert_eq!(fault["reason"], "simulated command panic");
+
+        strata_close(handle_id);
+    }
+
+    #[test]
+    fn test_open_temp_removed_on_close() {
+        let result_ptr = strata_open_temp(std::ptr::null());
+        let result = unsafe { CStr::from_ptr(result_ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(result_ptr) };
+
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let handle_id = v["ok"]["handle"].as_u64().expect("expected ok with handle id");
+        let path = std::path::PathBuf::from(v["ok"]["path"].as_str().expect("expected path"));
+        assert!(path.exists(), "temp database should exist while open");
+
+        strata_close(handle_id);
+        assert!(!path.exists(), "temp database should be removed on close");
+    }
+
+    #[test]
+    fn test_open_config_round_trips() {
+        let read = |ptr: *mut c_char| {
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+
+        let config = CString::new(r#"{"max_open_files":64,"cache_bytes":1048576}"#).unwrap();
+        let v = read(strata_open_temp(config.as_ptr()));
+        let handle = v["ok"]["handle"].as_u64().expect("expected ok with handle id");
+        let v = read(strata_get_config(handle));
+        assert_eq!(v["ok"]["max_open_files"], 64, "got: {v}");
+        assert_eq!(v["ok"]["cache_bytes"], 1_048_576);
+        // stratadb takes neither, so both are flagged as recorded only.
+        assert_eq!(v["ok"]["unapplied"], serde_json::json!(["max_open_files", "cache_bytes"]));
+        strata_close(handle);
+
+        // Defaults come back as nulls.
+        let v = read(strata_open_temp(std::ptr::null()));
+        let handle = v["ok"]["handle"].as_u64().unwrap();
+        let v = read(strata_get_config(handle));
+        assert!(v["ok"]["max_open_files"].is_null() && v["ok"]["cache_bytes"].is_null());
+        assert_eq!(v["ok"]["unapplied"], serde_json::json!([]), "got: {v}");
+        strata_close(handle);
+
+        let bad = CString::new(r#"{"max_open_files":"lots"}"#).unwrap();
+        let v = read(strata_open_temp(bad.as_ptr()));
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+
+        for policy in ["always", "interval:250", "never"] {
+            let config = CString::new(format!(r#"{{"wal_sync":"{policy}"}}"#)).unwrap();
+            let v = read(strata_open_temp(config.as_ptr()));
+            let handle = v["ok"]["handle"].as_u64().expect("expected ok with handle id");
+            let v = read(strata_get_config(handle));
+            assert_eq!(v["ok"]["wal_sync"], policy, "got: {v}");
+            strata_close(handle);
+        }
+        for bad in ["sometimes", "interval:", "interval:0", "interval:soon"] {
+            let config = CString::new(format!(r#"{{"wal_sync":"{bad}"}}"#)).unwrap();
+            let v = read(strata_open_temp(config.as_ptr()));
+            assert!(v["error"]["InvalidInput"].is_object(), "{bad}: {v}");
+        }
+
+        for (pattern, access) in [
+            ("sequential", config::AccessPattern::Sequential),
+            ("random", config::AccessPattern::Random),
+        ] {
+            let config = CString::new(format!(r#"{{"access_pattern":"{pattern}"}}"#)).unwrap();
+            let v = read(strata_open_temp(config.as_ptr()));
+            let handle = v["ok"]["handle"].as_u64().expect("expected ok with handle id");
+            let path = v["ok"]["path"].as_str().unwrap().to_string();
+            execute_json(handle, r#"{"KvPut":{"key":"k","value":{"Int":1}}}"#);
+            // Sequential prefetches the database's files; random leaves them be.
+            let prefetched = readahead::apply(std::path::Path::new(&path), access).unwrap();
+            assert_eq!(prefetched > 0, pattern == "sequential", "{pattern}: {prefetched}");
+            let v = read(strata_get_config(handle));
+            assert_eq!(v["ok"]["access_pattern"], pattern, "got: {v}");
+            assert_eq!(v["ok"]["unapplied"], serde_json::json!([]), "got: {v}");
+            strata_close(handle);
+        }
+        let config = CString::new(r#"{"access_pattern":"strided"}"#).unwrap();
+        let v = read(strata_open_temp(config.as_ptr()));
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+
+        let required = CString::new(r#"{"require_format_version":3}"#).unwrap();
+        let v = read(strata_open_temp(required.as_ptr()));
+        let mismatch = &v["error"]["FormatMismatch"];
+        assert_eq!(mismatch["required"], 3, "got: {v}");
+        assert!(mismatch["found"].is_null());
+
+        let e = config::open_error("Io: Too many open files (os error 24)".to_string());
+        assert_eq!(e.to_json()["ResourceExhausted"]["resource"], "file_descriptors");
+    }
+
+    static SLOW_LOGS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    extern "C" fn capture_slow_log(level: i32, message: *const c_char) {
+        let message = unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_string();
+        if level == log::LEVEL_WARN && message.starts_with("slow command") {
+            SLOW_LOGS.lock().unwrap().push(message);
+        }
+    }
+
+    #[test]
+    fn test_slow_command_threshold_logs() {
+        let result_ptr = strata_open_memory();
+        let result = unsafe { CStr::from_ptr(result_ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(result_ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let handle_id = v["ok"].as_u64().expect("expected ok with handle id");
+
+        strata_set_log_callback(Some(capture_slow_log));
+        strata_set_slow_command_threshold_ms(10);
+
+        // Ping under an artificial sleep stands in for a slow command.
+        REGISTRY
+            .run_timed(handle_id, r#"{"Ping":null}"#, |_| {
+                std::thread::sleep(std::time::Duration::from_millis(25));
+                Ok(())
+            })
+            .unwrap();
+
+        strata_set_slow_command_threshold_ms(0);
+        strata_set_log_callback(None);
+        strata_close(handle_id);
+
+        let logs = SLOW_LOGS.lock().unwrap();
+        let expected = format!("slow command: Ping on handle {handle_id}");
+        assert!(
+            logs.iter().any(|m| m.starts_with(&expected)),
+            "Expected a slow-command warning, got: {:?}",
+            logs
+        );
+    }
+
+    #[test]
+    fn test_kv_rename() {
+        let handle = open_memory_handle();
+        execute_json(handle, r#"{"KvPut":{"key":"session:abc123","value":{"String":"alice"}}}"#);
+        execute_json(handle, r#"{"KvPut":{"key":"session:taken","value":{"Int":1}}}"#);
+
+        // Absent source
+        let v = execute_json(handle, r#"{"KvRename":{"from":"session:missing","to":"session:new"}}"#);
+        assert_eq!(v["error"]["KeyNotFound"]["key"], "session:missing", "got: {v}");
+
+        // Existing target without overwrite leaves both keys untouched
+        let v = execute_json(handle, r#"{"KvRename":{"from":"session:abc123","to":"session:taken"}}"#);
+        assert_eq!(v["error"]["KeyExists"]["key"], "session:taken", "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"session:taken"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 1);
+
+        // Successful rename
+        let v = execute_json(handle, r#"{"KvRename":{"from":"session:abc123","to":"session:def456"}}"#);
+        assert_eq!(v["KvRename"]["overwritten"], false, "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"session:def456"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["String"], "alice");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"session:abc123"}}"#);
+        assert!(v["MaybeVersioned"].is_null(), "source should be gone, got: {v}");
+
+        strata_close(handle);
+    }
+
+    #[test]
+    #[cfg(feature = "json-schema")]
+    fn test_json_validate_against_schema() {
+        use serde_json::json;
+
+        let handle = open_memory_handle();
+        let set = |key: &str, value: serde_json::Value| {
+            let cmd = json!({"JsonSet": {"key": key, "path": "$", "value": tagged(value)}});
+            let v = execute_json(handle, &cmd.to_string());
+            assert!(v.get("error").is_none(), "{cmd} failed: {v}");
+        };
+        set(
+            "doc:schema",
+            json!({
+                "type": "object",
+                "required": ["title", "pages"],
+                "properties": {"title": {"type": "string"}, "pages": {"type": "integer"}},
+            }),
+        );
+        set("doc:report", json!({"title": "Q3", "pages": 12}));
+        set("doc:draft", json!({"title": 7}));
+
+        let targets = ["doc:report", "doc:draft"];
+        let cmd = json!({"JsonValidate": {"schema_key": "doc:schema", "targets": targets}});
+        let v = execute_json(handle, &cmd.to_string());
+        let results = v["JsonValidate"]["results"].as_array().unwrap_or_else(|| panic!("got: {v}"));
+        assert_eq!(results[0], json!({"key": "doc:report", "valid": true, "errors": []}));
+        assert_eq!(results[1]["valid"], false);
+        let errors = results[1]["errors"].as_array().unwrap();
+        let paths: Vec<&str> = errors.iter().map(|e| e["path"].as_str().unwrap()).collect();
+        assert!(paths.contains(&"/title"), "got: {v}");
+        assert!(paths.contains(&""), "missing `pages` is reported at the root, got: {v}");
+
+        let v = execute_json(handle, r#"{"JsonValidate":{"schema_key":"doc:none","targets":[]}}"#);
+        assert_eq!(v["error"]["KeyNotFound"]["key"], "doc:none", "got: {v}");
+        strata_close(handle);
+    }
+
+    static PRESSURE_LEVELS: std::sync::Mutex<Vec<i32>> = std::sync::Mutex::new(Vec::new());
+
+    /// Held by tests whose caches `strata_notify_memory_pressure` would disturb.
+    static CACHE_TESTS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    extern "C" fn capture_pressure(level: i32, _summary: *const c_char, _user_data: *mut c_void) {
+        PRESSURE_LEVELS.lock().unwrap().push(level);
+    }
+
+    #[test]
+    fn test_memory_pressure_clears_read_cache() {
+        let _serial = CACHE_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        let handle = open_capped_handle("lru");
+        for key in ["p:a", "p:b", "p:c"] {
+            let put = format!(r#"{{"KvPut":{{"key":"{key}","value":{{"Int":1}}}}}}"#);
+            execute_json(handle, &put);
+        }
+        unsafe { strata_free_string(strata_set_read_cache(handle, 8)) };
+        execute_json(handle, r#"{"KvGet":{"key":"p:a"}}"#);
+        strata_set_memory_pressure_callback(Some(capture_pressure), std::ptr::null_mut());
+
+        let ptr = strata_notify_memory_pressure(2);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        strata_set_memory_pressure_callback(None, std::ptr::null_mut());
+        let summary = &v["ok"];
+        assert!(summary["read_cache_entries"].as_u64() >= Some(1), "got: {v}");
+        assert!(summary["evicted_keys"].as_u64() >= Some(1), "got: {v}");
+        assert_eq!(PRESSURE_LEVELS.lock().unwrap().as_slice(), [2]);
+
+        let ptr = strata_stats(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(v["ok"]["read_cache"]["entries"], 0, "got: {v}");
+        assert_eq!(v["ok"]["read_cache"]["max_entries"], 8);
+        // Half the bytes of three equal entries: the two least recently used go.
+        assert_eq!(v["ok"]["cache"]["entries"], 1);
+        let v = execute_json(handle, r#"{"KvGet":{"key":"p:b"}}"#);
+        assert!(v["MaybeVersioned"].is_null(), "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"p:a"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 1, "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_read_cache_hits_and_invalidates() {
+        let _serial = CACHE_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        let handle = open_memory_handle();
+        let read_cache = || {
+            let ptr = strata_stats(handle);
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v["ok"]["read_cache"].clone()
+        };
+        let get = r#"{"KvGet":{"key":"view:title"}}"#;
+        execute_json(handle, r#"{"KvPut":{"key":"view:title","value":{"String":"Draft"}}}"#);
+        unsafe { strata_free_string(strata_set_read_cache(handle, 8)) };
+
+        execute_json(handle, get);
+        let v = execute_json(handle, get);
+        assert_eq!(v["MaybeVersioned"]["value"]["String"], "Draft", "got: {v}");
+        assert_eq!(read_cache()["hits"], 1);
+
+        // A write to another primitive leaves KV reads cached.
+        execute_json(handle, r#"{"StateSet":{"cell":"view:mode","value":{"Int":1}}}"#);
+        execute_json(handle, get);
+        assert_eq!(read_cache()["hits"], 2);
+
+        execute_json(handle, r#"{"KvPut":{"key":"view:title","value":{"String":"Final"}}}"#);
+        let v = execute_json(handle, get);
+        assert_eq!(v["MaybeVersioned"]["value"]["String"], "Final", "got: {v}");
+        let stats = read_cache();
+        assert_eq!(stats["hits"], 2, "got: {stats}");
+        assert_eq!(stats["misses"], 2);
+
+        unsafe { strata_free_string(strata_set_read_cache(handle, 0)) };
+        execute_json(handle, get);
+        assert_eq!(read_cache()["entries"], 0);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_changes_since_sequence() {
+        let handle = open_memory_handle();
+        let step = |cmd: &str| {
+            let v = execute_json(handle, cmd);
+            assert!(v.get("error").is_none(), "{cmd} failed: {v}");
+            // Keep each write's timestamp distinct from the checkpoint's.
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        };
+        step(r#"{"KvPut":{"key":"sync:kept","value":{"Int":1}}}"#);
+        step(r#"{"KvPut":{"key":"sync:edited","value":{"Int":1}}}"#);
+        step(r#"{"KvPut":{"key":"sync:dropped","value":{"Int":1}}}"#);
+        step(r#"{"StateSet":{"cell":"sync:cell","value":{"Int":1}}}"#);
+        step(r#"{"EventAppend":{"event_type":"checkpoint","payload":{"Int":0}}}"#);
+        step(r#"{"KvPut":{"key":"sync:edited","value":{"Int":2}}}"#);
+        step(r#"{"KvPut":{"key":"sync:added","value":{"Int":3}}}"#);
+        step(r#"{"KvDelete":{"key":"sync:dropped"}}"#);
+
+        let v = execute_json(handle, r#"{"ChangesSince":{"sequence":0}}"#);
+        let changes = &v["ChangesSince"];
+        assert_eq!(
+            changes["kv"]["changed"],
+            serde_json::json!([
+                {"key": "sync:added", "value": {"Int": 3}},
+                {"key": "sync:edited", "value": {"Int": 2}},
+            ]),
+            "got: {v}"
+        );
+        assert_eq!(changes["kv"]["removed"], serde_json::json!(["sync:dropped"]));
+        assert_eq!(changes["state"], serde_json::json!({"changed": [], "removed": []}));
+        assert_eq!(changes["json"], serde_json::json!({"changed": [], "removed": []}));
+
+        let v = execute_json(handle, r#"{"ChangesSince":{"sequence":0,"primitives":["state"]}}"#);
+        assert!(v["ChangesSince"].get("kv").is_none(), "got: {v}");
+        let v = execute_json(handle, r#"{"ChangesSince":{"sequence":1}}"#);
+        assert_eq!(v["error"]["InvalidInput"]["sequence"], 1, "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_last_open_error_diagnostic() {
+        // A database path under a regular file cannot be created.
+        let name = format!("strata-open-error-{}", std::process::id());
+        let file = std::env::temp_dir().join(name);
+        std::fs::write(&file, b"not a directory").unwrap();
+        let path = file.join("db.strata");
+        let path_c = CString::new(path.to_str().unwrap()).unwrap();
+
+        let ptr = strata_open(path_c.as_ptr(), std::ptr::null());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert!(v["error"].is_object(), "open should fail, got: {v}");
+
+        let ptr = strata_last_open_error();
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        // Other tests may fail opens concurrently; find ours by path.
+        if v["ok"]["path"] == path.to_str().unwrap() {
+            let diagnostic = &v["ok"];
+            assert_eq!(diagnostic["exists"], false, "got: {v}");
+            assert_eq!(diagnostic["kind"], "missing", "got: {v}");
+            assert_eq!(diagnostic["checked"], file.to_str().unwrap(), "got: {v}");
+            assert!(diagnostic["permissions"].is_string(), "got: {v}");
+            assert!(diagnostic["readable"].is_boolean() && diagnostic["writable"].is_boolean());
+            assert!(diagnostic["error"].as_str().is_some_and(|e| !e.is_empty()), "got: {v}");
+        }
+        for key in ["path", "exists", "kind", "permissions", "error", "at_ms"] {
+            assert!(v["ok"].get(key).is_some(), "{key} missing, got: {v}");
+        }
+        let _ = std::fs::remove_file(&file);
+    }
+
+    #[test]
+    fn test_kv_update_patches_atomically() {
+        let handle = open_memory_handle();
+        let settings = serde_json::json!({ "KvPut": { "key": "settings:app", "value": { "Object": {
+            "debug": { "Bool": false },
+            "retry": { "Object": { "max": { "Int": 3 } } },
+        }}}});
+        execute_json(handle, &settings.to_string());
+
+        let update = serde_json::json!({ "KvUpdate": { "key": "settings:app", "ops": [
+            { "op": "test", "path": "/debug", "value": { "Bool": false } },
+            { "op": "replace", "path": "/debug", "value": { "Bool": true } },
+            { "op": "increment", "path": "/retry/max", "value": { "Int": 1 } },
+        ]}});
+        let v = execute_json(handle, &update.to_string());
+        let value = &v["KvUpdate"]["value"]["Object"];
+        assert_eq!(value["debug"]["Bool"], true, "got: {v}");
+        assert_eq!(value["retry"]["Object"]["max"]["Int"], 4, "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"settings:app"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Object"]["retry"]["Object"]["max"]["Int"], 4);
+
+        // A failed op writes nothing; the earlier ops are discarded too.
+        let update = serde_json::json!({ "KvUpdate": { "key": "settings:app", "ops": [
+            { "op": "increment", "path": "/retry/max", "value": { "Int": 1 } },
+            { "op": "test", "path": "/debug", "value": { "Bool": false } },
+        ]}});
+        let v = execute_json(handle, &update.to_string());
+        assert_eq!(v["error"]["PatchFailed"]["index"], 1, "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"settings:app"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Object"]["retry"]["Object"]["max"]["Int"], 4);
+
+        // Absent keys need an upsert base.
+        let ops = serde_json::json!([{ "op": "increment", "path": "/n", "value": { "Int": 1 } }]);
+        let update = serde_json::json!({ "KvUpdate": { "key": "counter:new", "ops": ops } });
+        let v = execute_json(handle, &update.to_string());
+        assert_eq!(v["error"]["KeyNotFound"]["key"], "counter:new", "got: {v}");
+        let base = serde_json::json!({ "Object": { "n": { "Int": 0 } } });
+        let update =
+            serde_json::json!({ "KvUpdate": { "key": "counter:new", "ops": ops, "upsert": base } });
+        let v = execute_json(handle, &update.to_string());
+        assert_eq!(v["KvUpdate"]["value"]["Object"]["n"]["Int"], 1, "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_lazy_open_searches() {
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let name = format!("strata-lazy-{}.strata", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        let path_c = CString::new(dir.to_str().unwrap()).unwrap();
+
+        let handle = read(strata_open(path_c.as_ptr(), std::ptr::null()))["ok"].as_u64().unwrap();
+        for (key, vector) in [("north", [0.0, 1.0]), ("east", [1.0, 0.0])] {
+            let upsert = serde_json::json!({ "VectorUpsert": {
+                "collection": "compass", "key": key, "vector": vector, "metadata": null,
+            }});
+            assert!(execute_json(handle, &upsert.to_string()).get("error").is_none());
+        }
+        strata_close(handle);
+
+        let lazy = CString::new(r#"{"lazy":true}"#).unwrap();
+        let v = read(strata_open(path_c.as_ptr(), lazy.as_ptr()));
+        let handle = v["ok"].as_u64().expect("lazy open should succeed");
+        // Loading cannot be deferred, and the status says so.
+        let v = read(strata_warm_status(handle));
+        assert_eq!(v["ok"], serde_json::json!({ "lazy": false, "warm": true }), "got: {v}");
+        let v = read(strata_get_config(handle));
+        assert_eq!(v["ok"]["unapplied"], serde_json::json!(["lazy"]), "got: {v}");
+        let search = r#"{"VectorSearch":{"collection":"compass","query":[0.1,0.9],"k":1}}"#;
+        let v = execute_json(handle, search);
+        assert_eq!(v["VectorMatches"][0]["key"], "north", "got: {v}");
+        strata_close(handle);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_export_events_csv() {
+        let handle = open_sample_handle();
+        let name = format!("strata-events-{}.csv", std::process::id());
+        let out = std::env::temp_dir().join(name);
+        let out_c = CString::new(out.to_str().unwrap()).unwrap();
+        let columns = CString::new(r#"["sequence","kind","data","tool"]"#).unwrap();
+        let ptr = strata_export_events_csv(handle, out_c.as_ptr(), columns.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(v["ok"]["rows"], 20, "got: {v}");
+
+        let csv = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 21, "header plus one line per event");
+        assert_eq!(lines[0], "sequence,kind,data,tool");
+        assert!(lines[1].starts_with("0,"), "got: {}", lines[1]);
+        // Payloads are objects, so their cells are quoted JSON.
+        assert!(lines[1].contains(r#",""#) && lines[1].contains(r#""""#), "got: {}", lines[1]);
+        // A payload field column is filled where the field exists and empty elsewhere.
+        assert!(lines.iter().any(|line| line.ends_with(",read_document")), "got: {csv}");
+        assert!(lines.iter().any(|line| line.ends_with(',')), "got: {csv}");
+        strata_close(handle);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    /// Decodes each MessagePack row into the `Vec<serde_json::Value>` behind
+    /// `user_data`, stopping after `STOP_AFTER` rows.
+    extern "C" fn collect_msgpack_row(row: *const u8, len: usize, user_data: *mut c_void) -> i32 {
+        const STOP_AFTER: usize = 5;
+        let rows = unsafe { &mut *(user_data as *mut Vec<serde_json::Value>) };
+        let bytes = unsafe { std::slice::from_raw_parts(row, len) };
+        rows.push(rmp_serde::from_slice(bytes).unwrap());
+        (rows.len() == STOP_AFTER) as i32
+    }
+
+    #[test]
+    fn test_execute_stream_msgpack() {
+        let handle = open_sample_handle();
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let command = CString::new(r#"{"KvList":{"prefix":"user:"}}"#).unwrap();
+        let mut rows: Vec<serde_json::Value> = Vec::new();
+        let user_data = &mut rows as *mut Vec<serde_json::Value> as *mut c_void;
+        let v = read(strata_execute_stream_msgpack(
+            handle,
+            command.as_ptr(),
+            Some(collect_msgpack_row),
+            user_data,
+        ));
+        assert_eq!(v["ok"], serde_json::json!({ "rows": 3, "cancelled": false }), "got: {v}");
+        assert_eq!(rows, ["user:alice", "user:bob", "user:carol"]);
+
+        // The callback stops a longer stream after five rows.
+        rows.clear();
+        let command = CString::new(r#"{"KvList":{}}"#).unwrap();
+        let user_data = &mut rows as *mut Vec<serde_json::Value> as *mut c_void;
+        let v = read(strata_execute_stream_msgpack(
+            handle,
+            command.as_ptr(),
+            Some(collect_msgpack_row),
+            user_data,
+        ));
+        assert_eq!(v["ok"], serde_json::json!({ "rows": 5, "cancelled": true }), "got: {v}");
+        assert_eq!(rows.len(), 5);
+
+        // A write is refused rather than run outside the write path.
+        rows.clear();
+        let command = CString::new(r#"{"KvDelete":{"key":"user:alice"}}"#).unwrap();
+        let user_data = &mut rows as *mut Vec<serde_json::Value> as *mut c_void;
+        let v = read(strata_execute_stream_msgpack(
+            handle,
+            command.as_ptr(),
+            Some(collect_msgpack_row),
+            user_data,
+        ));
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+        assert!(rows.is_empty());
+        let v = execute_json(handle, r#"{"KvGet":{"key":"user:alice"}}"#);
+        assert!(v["MaybeVersioned"].is_object(), "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_kv_infer_schema() {
+        let handle = open_sample_handle();
+        let v = execute_json(handle, r#"{"KvInferSchema":{"prefix":"user:","sample":100}}"#);
+        let schema = &v["KvInferSchema"];
+        assert_eq!(schema["sampled"], 3, "got: {v}");
+        assert_eq!(schema["types"], serde_json::json!(["Object"]));
+        let fields = &schema["fields"];
+        let expected = [
+            ("name", "String"),
+            ("email", "String"),
+            ("age", "Int"),
+            ("role", "String"),
+            ("active", "Bool"),
+        ];
+        for (field, ty) in expected {
+            assert_eq!(fields[field]["types"], serde_json::json!([ty]), "{field}: {v}");
+            assert_eq!(fields[field]["optional"], false, "{field}: {v}");
+        }
+
+        // A field only some objects have is optional.
+        let dave = serde_json::json!({ "KvPut": { "key": "user:dave", "value": { "Object": {
+            "name": { "String": "Dave" },
+            "nickname": { "String": "D" },
+        }}}});
+        execute_json(handle, &dave.to_string());
+        let v = execute_json(handle, r#"{"KvInferSchema":{"prefix":"user:"}}"#);
+        let fields = &v["KvInferSchema"]["fields"];
+        assert_eq!(fields["name"]["optional"], false, "got: {v}");
+        assert_eq!(fields["age"]["optional"], true, "got: {v}");
+        assert_eq!(fields["nickname"]["optional"], true, "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_handle_pin() {
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let handle = open_memory_handle();
+        assert!(read(strata_handle_pin(handle))["ok"].is_null());
+
+        strata_close(handle);
+        let v = execute_json(handle, r#"{"KvGet":{"key":"anything"}}"#);
+        assert!(v.get("error").is_none(), "pinned handle should stay open, got: {v}");
+        let v = read(strata_handle_last_error(handle));
+        assert_eq!(v["ok"]["command"], "strata_close", "got: {v}");
+        assert_eq!(v["ok"]["error"]["HandlePinned"]["handle"], handle, "got: {v}");
+
+        assert!(read(strata_handle_unpin(handle))["ok"].is_null());
+        strata_close(handle);
+        let v = execute_json(handle, r#"{"KvGet":{"key":"anything"}}"#);
+        assert!(v["error"].is_object(), "unpinned handle should close, got: {v}");
+    }
+
+    #[test]
+    fn test_json_get_paths() {
+        let handle = open_sample_handle();
+        let paths = ["$.title", "$.summary", "$.recommendation", "$.missing"];
+        let cmd = serde_json::json!({ "JsonGet": { "key": "doc:report", "paths": paths } });
+        let v = execute_json(handle, &cmd.to_string());
+        let paths = &v["JsonGet"];
+        assert_eq!(paths["$.title"]["String"], "Embedded Database Comparison 2026", "got: {v}");
+        assert_eq!(
+            paths["$.summary"]["String"],
+            "Analysis of 5 embedded databases across 12 benchmarks."
+        );
+        assert!(paths["$.recommendation"]["String"].as_str().unwrap().starts_with("StrataDB"));
+        assert!(paths["$.missing"].is_null(), "got: {v}");
+
+        // Without `paths`, JsonGet is stratadb's own.
+        let v = execute_json(handle, r#"{"JsonGet":{"key":"doc:report","path":"$.title"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["String"], "Embedded Database Comparison 2026");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let ptr = strata_capabilities();
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        for key in ["vectors", "branches", "encryption", "time_travel"] {
+            assert!(v[key].is_boolean(), "{key} missing, got: {v}");
+        }
+        assert_eq!(v["vectors"], true, "got: {v}");
+        assert_eq!(v["branches"], true, "got: {v}");
+        assert_eq!(v["encryption"], false, "got: {v}");
+    }
+
+    #[test]
+    fn test_key_policy_confines_tenant() {
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let handle = open_memory_handle();
+        execute_json(handle, r#"{"KvPut":{"key":"tenantB:plan","value":{"Int":2}}}"#);
+        execute_json(handle, r#"{"KvPut":{"key":"tenantA:secret","value":{"Int":3}}}"#);
+        let tenant_b = CString::new("tenantB:raw").unwrap();
+        read(strata_kv_put_bytes(handle, tenant_b.as_ptr(), b"raw".as_ptr(), 3));
+        execute_json(handle, r#"{"VectorCreateCollection":{"collection":"docs","dimension":2}}"#);
+        for (key, vector) in [("tenantA:doc", [1.0, 0.0]), ("tenantB:doc", [0.9, 0.1])] {
+            let upsert = serde_json::json!({ "VectorUpsert": {
+                "collection": "docs", "key": key, "vector": vector,
+            }});
+            execute_json(handle, &upsert.to_string());
+        }
+        // A stream opened before the policy is checked as it fetches.
+        let list_all = CString::new(r#"{"KvList":{}}"#).unwrap();
+        let stream = read(strata_stream_open(handle, list_all.as_ptr()))["ok"].as_u64().unwrap();
+        let allow = CString::new("^tenantA:").unwrap();
+        let v = read(strata_set_key_policy(handle, allow.as_ptr(), std::ptr::null()));
+        assert!(v["ok"].is_null() && v.get("error").is_none(), "got: {v}");
+
+        let v = execute_json(handle, r#"{"KvPut":{"key":"tenantA:plan","value":{"Int":1}}}"#);
+        assert!(v["error"].is_null(), "got: {v}");
+        let v = execute_json(handle, r#"{"KvPut":{"key":"tenantB:plan","value":{"Int":2}}}"#);
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:plan", "got: {v}");
+        let v = execute_json(handle, r#"{"KvList":{"prefix":"tenantB:"}}"#);
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:", "got: {v}");
+        let v = execute_json(handle, r#"{"KvRename":{"from":"tenantA:plan","to":"tenantB:x"}}"#);
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:x", "got: {v}");
+        let swap = r#"{"KvSwap":{"key_a":"tenantA:plan","key_b":"tenantB:plan"}}"#;
+        let v = execute_json(handle, swap);
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:plan", "got: {v}");
+        for section in ["kv", "state"] {
+            let multi = format!(r#"{{"MultiGet":{{"{section}":["tenantB:plan"]}}}}"#);
+            let v = execute_json(handle, &multi);
+            assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:plan", "{multi} got: {v}");
+        }
+
+        // Scans need a prefix, and reads spanning every key are refused.
+        let unscoped = [
+            ("KvList", r#"{"KvList":{}}"#),
+            ("KvList", r#""KvList""#),
+            ("StateList", r#"{"StateList":{}}"#),
+            ("JsonList", r#"{"JsonList":{"limit":10}}"#),
+            ("JsonCatalog", r#"{"JsonCatalog":{}}"#),
+            ("KvAggregate", r#"{"KvAggregate":{"op":"count"}}"#),
+            ("KvNamespaces", r#"{"KvNamespaces":{}}"#),
+            ("Digest", r#"{"Digest":{}}"#),
+            ("ChangesSince", r#"{"ChangesSince":{"sequence":0}}"#),
+            ("StateDiffBetween", r#"{"StateDiffBetween":{"from":0,"to":0,"primitive":"kv"}}"#),
+        ];
+        for (tag, command) in unscoped {
+            let v = execute_json(handle, command);
+            assert_eq!(v["error"]["Forbidden"]["command"], tag, "{command} got: {v}");
+        }
+        let v = execute_json(handle, r#"{"KvList":{"prefix":"tenantA:"}}"#);
+        assert_eq!(v["Keys"], serde_json::json!(["tenantA:plan", "tenantA:secret"]), "got: {v}");
+
+        // Searches name no keys, so other tenants' matches are dropped.
+        let search = r#"{"VectorSearch":{"collection":"docs","query":[1.0,0.0],"k":10}}"#;
+        let v = execute_json(handle, search);
+        let matches = v["VectorMatches"].as_array().unwrap_or_else(|| panic!("got: {v}"));
+        assert_eq!(matches.len(), 1, "got: {v}");
+        assert_eq!(matches[0]["key"], "tenantA:doc", "got: {v}");
+        let by_id = |id: &str| {
+            let search = serde_json::json!({ "VectorSearchById": {
+                "collection": "docs", "id": id, "k": 10, "exclude_self": false,
+            }});
+            execute_json(handle, &search.to_string())
+        };
+        let v = by_id("tenantB:doc");
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:doc", "got: {v}");
+        let v = by_id("tenantA:doc");
+        assert_eq!(v["VectorSearchById"].as_array().map(Vec::len), Some(1), "got: {v}");
+
+        // Snapshots and streams of the handle are held to the policy.
+        let snapshot = read(strata_snapshot_begin(handle))["ok"].as_u64().unwrap();
+        let v = execute_json(snapshot, r#"{"KvGet":{"key":"tenantB:plan"}}"#);
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:plan", "got: {v}");
+        let v = execute_json(snapshot, r#"{"KvList":{}}"#);
+        assert_eq!(v["error"]["Forbidden"]["command"], "KvList", "got: {v}");
+        read(strata_snapshot_end(snapshot));
+        let v = read(strata_stream_next(stream));
+        assert_eq!(v["error"]["Forbidden"]["command"], "KvList", "got: {v}");
+        strata_stream_close(stream);
+        let v = read(strata_stream_open(handle, list_all.as_ptr()));
+        assert_eq!(v["error"]["Forbidden"]["command"], "KvList", "got: {v}");
+
+        // So are exports, imports and the raw key functions.
+        let out = std::env::temp_dir().join(format!("strata-policy-{}.json", std::process::id()));
+        let out = CString::new(out.to_str().unwrap()).unwrap();
+        let v = read(strata_export_json(handle, out.as_ptr()));
+        assert_eq!(v["error"]["Forbidden"]["command"], "strata_export_json", "got: {v}");
+        let kv = CString::new("kv").unwrap();
+        let v = read(strata_export_primitive(handle, kv.as_ptr(), out.as_ptr()));
+        assert_eq!(v["error"]["Forbidden"]["command"], "strata_export_primitive", "got: {v}");
+        let v = read(strata_export_events_csv(handle, out.as_ptr(), std::ptr::null()));
+        assert_eq!(v["error"]["Forbidden"]["command"], "strata_export_events_csv", "got: {v}");
+        let merge = CString::new("merge").unwrap();
+        let v = read(strata_import_json(handle, out.as_ptr(), merge.as_ptr()));
+        assert_eq!(v["error"]["Forbidden"]["command"], "strata_import_json", "got: {v}");
+        let tenant_a = CString::new("tenantA:raw").unwrap();
+        let v = read(strata_copy_kv(handle, handle, tenant_b.as_ptr(), tenant_a.as_ptr()));
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantB:raw", "got: {v}");
+        let mut len = 1;
+        assert!(strata_kv_get_bytes(handle, tenant_b.as_ptr(), &mut len).is_null());
+
+        // A deny pattern also holds for the keys under an allowed prefix.
+        let deny = CString::new(":secret$").unwrap();
+        read(strata_set_key_policy(handle, allow.as_ptr(), deny.as_ptr()));
+        let v = execute_json(handle, r#"{"KvGet":{"key":"tenantA:secret"}}"#);
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantA:secret", "got: {v}");
+        let v = execute_json(handle, r#"{"KvList":{"prefix":"tenantA:"}}"#);
+        assert_eq!(v["Keys"], serde_json::json!(["tenantA:plan"]), "got: {v}");
+        let v = execute_json(handle, r#"{"KvSample":{"n":10,"prefix":"tenantA:"}}"#);
+        assert_eq!(v["KvSample"].as_array().map(Vec::len), Some(1), "got: {v}");
+        let v = execute_json(handle, r#"{"KvAggregate":{"prefix":"tenantA:","op":"sum"}}"#);
+        assert_eq!(v["error"]["Forbidden"]["key"], "tenantA:secret", "got: {v}");
+        let list_a = CString::new(r#"{"KvList":{"prefix":"tenantA:"}}"#).unwrap();
+        let stream = read(strata_stream_open(handle, list_a.as_ptr()))["ok"].as_u64().unwrap();
+        assert_eq!(read(strata_stream_next(stream))["ok"], "tenantA:plan");
+        assert_eq!(read(strata_stream_next(stream))["done"], true);
+        strata_stream_close(stream);
+
+        let bad = CString::new("(").unwrap();
+        let v = read(strata_set_key_policy(handle, bad.as_ptr(), std::ptr::null()));
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+
+        // Empty patterns lift the policy.
+        let empty = CString::new("").unwrap();
+        read(strata_set_key_policy(handle, empty.as_ptr(), empty.as_ptr()));
+        let v = execute_json(handle, r#"{"KvPut":{"key":"tenantB:plan","value":{"Int":2}}}"#);
+        assert!(v["error"].is_null(), "got: {v}");
+    }
+
+    #[test]
+    fn test_json_canonicalize() {
+        use serde_json::json;
+
+        let handle = open_memory_handle();
+        let doc = json!({"title": "Q3", "meta": {"zeta": -0.0, "alpha": 2}, "id": 7});
+        let cmd = json!({"JsonSet": {"key": "doc:report", "path": "$", "value": tagged(doc)}});
+        execute_json(handle, &cmd.to_string());
+
+        let canonicalize = r#"{"JsonCanonicalize":{"key":"doc:report"}}"#;
+        let v = execute_json(handle, canonicalize);
+        assert_eq!(v["JsonCanonicalize"]["changed"], true, "got: {v}");
+        assert!(v["JsonCanonicalize"]["version"].is_u64(), "got: {v}");
+
+        let get = r#"{"JsonGet":{"key":"doc:report","path":"$"}}"#;
+        let raw = execute_json(handle, get).to_string();
+        let keys = ["\"alpha\"", "\"zeta\"", "\"id\"", "\"meta\"", "\"title\""];
+        let at: Vec<usize> = keys.iter().map(|k| raw.find(k).unwrap()).collect();
+        assert!(at[0] < at[1] && at[2] < at[3] && at[3] < at[4], "got: {raw}");
+        assert!(!raw.contains("-0.0"), "got: {raw}");
+
+        let v = execute_json(handle, canonicalize);
+        assert_eq!(v["JsonCanonicalize"]["changed"], false, "got: {v}");
+        assert!(v["JsonCanonicalize"]["version"].is_null(), "got: {v}");
+
+        let v = execute_json(handle, r#"{"JsonCanonicalize":{"key":"doc:missing"}}"#);
+        assert!(v["error"]["KeyNotFound"].is_object(), "got: {v}");
+    }
+
+    #[test]
+    fn test_close_checked() {
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let opened = read(strata_open_temp(std::ptr::null()));
+        let handle = opened["ok"]["handle"].as_u64().unwrap_or_else(|| panic!("got: {opened}"));
+        execute_json(handle, r#"{"KvPut":{"key":"save","value":{"Int":1}}}"#);
+
+        read(strata_handle_pin(handle));
+        let v = read(strata_close_checked(handle));
+        assert_eq!(v["error"]["HandlePinned"]["handle"], handle, "got: {v}");
+        read(strata_handle_unpin(handle));
+
+        let v = read(strata_close_checked(handle));
+        assert_eq!(v["ok"]["flushed"], true, "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"save"}}"#);
+        assert!(v["error"].is_object(), "handle should be gone, got: {v}");
+        let v = read(strata_close_checked(handle));
+        assert!(v["error"].is_object(), "got: {v}");
+    }
+
+    #[test]
+    fn test_kv_aggregate_counters() {
+        let handle = open_sample_handle();
+        let aggregate = |prefix: &str, op: &str| {
+            let cmd = serde_json::json!({ "KvAggregate": { "prefix": prefix, "op": op } });
+            execute_json(handle, &cmd.to_string())["KvAggregate"].clone()
+        };
+
+        let total = 48291 + 152847 + 37;
+        let sum = aggregate("counter:", "sum");
+        assert_eq!(sum, serde_json::json!({ "value": total, "count": 3, "skipped": 0 }));
+        assert_eq!(aggregate("counter:", "max")["value"], 152847);
+        assert_eq!(aggregate("counter:", "min")["value"], 37);
+        assert_eq!(aggregate("counter:", "avg")["value"], total as f64 / 3.0);
+
+        // Of the config values only max_retries and timeout_ms are numbers.
+        let count = aggregate("config:", "count");
+        assert_eq!(count, serde_json::json!({ "value": 2, "count": 2, "skipped": 3 }));
+        assert!(aggregate("nothing:", "avg")["value"].is_null());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_vector_search_by_id() {
+        let handle = open_memory_handle();
+        let create = r#"{"VectorCreateCollection":{"collection":"docs","dimension":2}}"#;
+        execute_json(handle, create);
+        let vectors = [("a", [1.0, 0.0]), ("b", [0.9, 0.1]), ("c", [0.0, 1.0]), ("d", [0.6, 0.4])];
+        for (key, vector) in vectors {
+            let upsert = serde_json::json!({ "VectorUpsert": {
+                "collection": "docs", "key": key, "vector": vector,
+            }});
+            execute_json(handle, &upsert.to_string());
+        }
+        let search = |id: &str, k: u64, exclude_self: bool| {
+            let search = serde_json::json!({ "VectorSearchById": {
+                "collection": "docs", "id": id, "k": k, "exclude_self": exclude_self,
+            }});
+            execute_json(handle, &search.to_string())
+        };
+
+        let v = search("a", 2, true);
+        let keys: Vec<&str> = v["VectorSearchById"]
+            .as_array()
+            .unwrap_or_else(|| panic!("got: {v}"))
+            .iter()
+            .map(|m| m["key"].as_str().unwrap())
+            .collect();
+        assert_eq!(keys, ["b", "d"]);
+
+        let v = search("a", 1, false);
+        assert_eq!(v["VectorSearchById"][0]["key"], "a", "got: {v}");
+
+        let v = search("zz", 2, true);
+        assert_eq!(v["error"]["NotFound"]["id"], "zz", "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_tolerant_json_strips_comments() {
+        let handle = open_memory_handle();
+        let cmd = r#"{
+            // Note for the console: "//" inside strings is kept.
+            "KvPut": {"key": "note://a", "value": {"String": "x, }"},},
+        }"#;
+        let v = execute_json(handle, cmd);
+        assert!(v["error"].is_object(), "strict parsing should reject it, got: {v}");
+
+        unsafe { strata_free_string(strata_set_tolerant_json(handle, true)) };
+        let v = execute_json(handle, cmd);
+        assert!(v.get("error").is_none(), "got: {v}");
+        let v = execute_json(handle, r#"["KvGet", "note://a",] // compact form too"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["String"], "x, }", "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_branch_tree() {
+        let handle = open_memory_handle();
+        for branch in ["staging", "experiment"] {
+            let create = format!(r#"{{"BranchCreate":{{"branch_id":"{branch}"}}}}"#);
+            let v = execute_json(handle, &create);
+            assert!(v.get("error").is_none(), "got: {v}");
+        }
+
+        let v = execute_json(handle, r#"{"BranchTree":{}}"#);
+        let root = &v["BranchTree"];
+        assert_eq!(root["id"], "default", "got: {v}");
+        let children = root["children"].as_array().unwrap();
+        let ids: Vec<&str> = children.iter().map(|c| c["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, ["experiment", "staging"]);
+        for child in children {
+            assert_eq!(child["children"], serde_json::json!([]));
+            assert!(child.get("orphaned").is_none(), "got: {v}");
+            assert!(child["created_at"].is_u64(), "got: {v}");
+        }
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_seeded_handles_sample_identically() {
+        let open_seeded = || {
+            let config = CString::new(r#"{"seed":42}"#).unwrap();
+            let ptr = strata_open_memory_with_config(config.as_ptr());
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            let handle = v["ok"].as_u64().expect("expected ok with handle id");
+            let entries: Vec<serde_json::Value> = (0..200)
+                .map(|i| serde_json::json!({ "key": format!("seed:{i}"), "value": { "Int": i } }))
+                .collect();
+            let batch = serde_json::json!({ "KvBatchPut": { "entries": entries } });
+            execute_json(handle, &batch.to_string());
+            handle
+        };
+        let samples = |handle: u64| -> Vec<serde_json::Value> {
+            (0..3).map(|_| execute_json(handle, r#"{"KvSample":{"n":5}}"#)).collect()
+        };
+        let (a, b) = (open_seeded(), open_seeded());
+        let sampled = samples(a);
+        assert_eq!(sampled, samples(b));
+        // Successive samples on a handle still differ.
+        assert_ne!(sampled[0], sampled[1], "got: {sampled:?}");
+        strata_close(a);
+        strata_close(b);
+    }
+
+    #[test]
+    fn test_kv_sample() {
+        let handle = open_sample_handle();
+        let v = execute_json(handle, r#"{"KvSample":{"n":2,"prefix":"user:"}}"#);
+        let sampled = v["KvSample"].as_array().expect("expected a list of entries");
+        assert_eq!(sampled.len(), 2, "got: {v}");
+        for entry in sampled {
+            assert!(entry["key"].as_str().unwrap().starts_with("user:"), "got: {v}");
+            assert!(entry["value"].is_object(), "got: {v}");
+        }
+
+        // Fewer matches than n returns them all.
+        let v = execute_json(handle, r#"{"KvSample":{"n":1000}}"#);
+        assert_eq!(v["KvSample"].as_array().unwrap().len(), 14, "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_default_timeout() {
+        let handle = open_memory_handle();
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        assert!(read(strata_set_default_timeout_ms(handle, 1))["ok"].is_null());
+        assert_eq!(read(strata_get_config(handle))["ok"]["default_timeout_ms"], 1);
+
+        // Far more than a millisecond of work, even in release builds.
+        let entries: Vec<serde_json::Value> = (0..100_000)
+            .map(|i| serde_json::json!({ "key": format!("bulk:{i}"), "value": { "Int": i } }))
+            .collect();
+        let batch = serde_json::json!({ "KvBatchPut": { "entries": entries } });
+        let v = execute_json(handle, &batch.to_string());
+        assert_eq!(v["error"]["Timeout"]["timeout_ms"], 1, "got: {v}");
+
+        assert!(read(strata_set_default_timeout_ms(handle, 0))["ok"].is_null());
+        assert!(read(strata_get_config(handle))["ok"]["default_timeout_ms"].is_null());
+        let v = execute_json(handle, r#"{"KvGet":{"key":"bulk:0"}}"#);
+        assert!(v.get("error").is_none(), "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_kv_get_or_put() {
+        let handle = open_memory_handle();
+        let cmd = r#"{"KvGetOrPut":{"key":"counter:visits","default":{"Int":0}}}"#;
+        let v = execute_json(handle, cmd);
+        assert_eq!(v["KvGetOrPut"]["created"], true, "got: {v}");
+        assert_eq!(v["KvGetOrPut"]["value"]["Int"], 0, "got: {v}");
+
+        execute_json(handle, r#"{"KvPut":{"key":"counter:visits","value":{"Int":5}}}"#);
+        let v = execute_json(handle, cmd);
+        assert_eq!(v["KvGetOrPut"]["created"], false, "got: {v}");
+        assert_eq!(v["KvGetOrPut"]["value"]["Int"], 5, "got: {v}");
+
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_journal_appends_mutations() {
+        let name = format!("strata-journal-{}.jsonl", std::process::id());
+        let journal = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_file(&journal);
+        let config = serde_json::json!({ "journal_path": journal, "journal_fsync": true });
+        let config = CString::new(config.to_string()).unwrap();
+        let ptr = strata_open_temp(config.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let handle = v["ok"]["handle"].as_u64().expect("expected ok with handle id");
+
+        execute_json(handle, r#"{"KvPut":{"key":"order:1","value":{"Int":1}}}"#);
+        execute_json(handle, r#"{"StateSet":{"cell":"phase","value":{"String":"run"}}}"#);
+        execute_json(handle, r#"{"KvGet":{"key":"order:1"}}"#);
+        execute_json(handle, r#"{"KvDelete":{"key":"order:1"}}"#);
+        strata_close(handle);
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&journal)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let tags: Vec<&str> = lines
+            .iter()
+            .map(|line| line["command"].as_object().unwrap().keys().next().unwrap().as_str())
+            .collect();
+        assert_eq!(tags, ["KvPut", "StateSet", "KvDelete"]);
+        assert_eq!(lines[0]["command"]["KvPut"]["key"], "order:1");
+        assert!(lines.iter().all(|line| line["at_ms"].as_u64().is_some()));
+        let _ = std::fs::remove_file(&journal);
+    }
+
+    #[test]
+    fn test_group_routes_by_shard() {
+        let paths = CString::new(r#"{"user_a":null,"user_b":null}"#).unwrap();
+        let ptr = strata_open_group(paths.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let group = v["ok"].as_u64().expect("expected ok with group id");
+
+        for (shard, name) in [("user_a", "Alice"), ("user_b", "Bob")] {
+            let put = serde_json::json!({
+                "shard": shard,
+                "KvPut": { "key": "profile:name", "value": { "String": name } },
+            });
+            assert!(execute_json(group, &put.to_string()).get("error").is_none());
+        }
+        for (shard, name) in [("user_a", "Alice"), ("user_b", "Bob")] {
+            let get = serde_json::json!({ "shard": shard, "KvGet": { "key": "profile:name" } });
+            let v = execute_json(group, &get.to_string());
+            assert_eq!(v["MaybeVersioned"]["value"]["String"], name, "{shard}: {v}");
+        }
+
+        let v = execute_json(group, r#"{"KvGet":{"key":"profile:name"}}"#);
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+        let v = execute_json(group, r#"{"shard":"user_c","KvGet":{"key":"profile:name"}}"#);
+        assert_eq!(v["error"]["NotFound"]["shard"], "user_c", "got: {v}");
+
+        strata_close(group);
+        let v = execute_json(group, r#"{"shard":"user_a","KvGet":{"key":"profile:name"}}"#);
+        assert!(v["error"].is_object(), "closed group should be gone, got: {v}");
+    }
+
+    #[test]
+    fn test_snapshot_reads_pinned_view() {
+        let handle = open_memory_handle();
+        execute_json(handle, r#"{"KvPut":{"key":"report:total","value":{"Int":10}}}"#);
+
+        let ptr = strata_snapshot_begin(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let snapshot = v["ok"].as_u64().expect("expected ok with snapshot id");
+
+        execute_json(handle, r#"{"KvPut":{"key":"report:total","value":{"Int":11}}}"#);
+        let get = r#"{"KvGet":{"key":"report:total"}}"#;
+        assert_eq!(execute_json(handle, get)["MaybeVersioned"]["value"]["Int"], 11);
+        assert_eq!(execute_json(snapshot, get)["MaybeVersioned"]["value"]["Int"], 10);
+
+        let put = r#"{"KvPut":{"key":"report:total","value":{"Int":12}}}"#;
+        let v = execute_json(snapshot, put);
+        assert_eq!(v["error"]["SnapshotReadOnly"]["command"], "KvPut", "got: {v}");
+
+        let ptr = strata_snapshot_end(snapshot);
+        unsafe { strata_free_string(ptr) };
+        let v = execute_json(snapshot, get);
+        assert!(v["error"].is_object(), "ended snapshot should be gone, got: {v}");
+        assert_eq!(execute_json(handle, get)["MaybeVersioned"]["value"]["Int"], 11);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_kv_swap() {
+        let handle = open_memory_handle();
+        execute_json(handle, r#"{"KvPut":{"key":"config:active","value":{"String":"blue"}}}"#);
+        execute_json(handle, r#"{"KvPut":{"key":"config:staging","value":{"String":"green"}}}"#);
+        let get = |key: &str| {
+            let cmd = format!(r#"{{"KvGet":{{"key":"{key}"}}}}"#);
+            execute_json(handle, &cmd)["MaybeVersioned"].clone()
+        };
+
+        let cmd = r#"{"KvSwap":{"key_a":"config:active","key_b":"config:staging"}}"#;
+        let v = execute_json(handle, cmd);
+        assert!(v["KvSwap"]["version"].is_u64(), "got: {v}");
+        assert_eq!(get("config:active")["value"]["String"], "green");
+        assert_eq!(get("config:staging")["value"]["String"], "blue");
+
+        // An absent key fails the whole swap
+        let cmd = r#"{"KvSwap":{"key_a":"config:active","key_b":"config:next"}}"#;
+        let v = execute_json(handle, cmd);
+        assert_eq!(v["error"]["KeyNotFound"]["key"], "config:next", "got: {v}");
+        assert_eq!(get("config:active")["value"]["String"], "green");
+
+        // ...unless allow_missing treats it as null
+        let v = execute_json(
+            handle,
+            r#"{"KvSwap":{"key_a":"config:active","key_b":"config:next","allow_missing":true}}"#,
+        );
+        assert!(v.get("error").is_none(), "got: {v}");
+        assert!(get("config:active").is_null());
+        assert_eq!(get("config:next")["value"]["String"], "green");
+
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_json_rename() {
+        let handle = open_sample_handle();
+        let get = |key: &str| {
+            let cmd = format!(r#"{{"JsonGet":{{"key":"{key}","path":"$"}}}}"#);
+            execute_json(handle, &cmd)["MaybeVersioned"].clone()
+        };
+
+        // Absent source
+        let v = execute_json(handle, r#"{"JsonRename":{"from":"doc:missing","to":"doc:new"}}"#);
+        assert_eq!(v["error"]["KeyNotFound"]["key"], "doc:missing", "got: {v}");
+
+        // Existing target without overwrite leaves both documents untouched
+        let v = execute_json(handle, r#"{"JsonRename":{"from":"doc:readme","to":"doc:report"}}"#);
+        assert_eq!(v["error"]["KeyExists"]["key"], "doc:report", "got: {v}");
+        assert!(get("doc:readme").is_object());
+        assert_eq!(get("doc:report")["value"]["Object"]["author"]["String"], "research-agent-v2");
+
+        // Successful rename
+        let v = execute_json(handle, r#"{"JsonRename":{"from":"doc:readme","to":"doc:getting-started"}}"#);
+        assert_eq!(v["JsonRename"]["overwritten"], false, "got: {v}");
+        let moved = get("doc:getting-started");
+        assert_eq!(moved["value"]["Object"]["title"]["String"], "Getting Started with StrataDB");
+        assert!(get("doc:readme").is_null(), "source should be gone");
+
+        // Overwrite replaces the whole target document
+        let v = execute_json(
+            handle,
+            r#"{"JsonRename":{"from":"doc:getting-started","to":"doc:report","overwrite":true}}"#,
+        );
+        assert_eq!(v["JsonRename"]["overwritten"], true, "got: {v}");
+        let report = get("doc:report");
+        assert_eq!(report["value"]["Object"]["author"]["String"], "Alice Chen");
+        assert!(report["value"]["Object"].get("recommendation").is_none());
+
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_json_merge_deep_and_shallow() {
+        use serde_json::json;
+
+        let handle = open_sample_handle();
+        let get_config = || {
+            execute_json(handle, r#"{"JsonGet":{"key":"doc:agent-config","path":"$"}}"#)
+                ["MaybeVersioned"]["value"]["Object"]
+                .clone()
+        };
+
+        // Seed a nested object to merge into.
+        let patch = json!({"limits": {"max_tokens": 4096, "timeout_ms": 30000}});
+        let cmd = json!({"JsonMerge": {"key": "doc:agent-config", "value": tagged(patch), "deep": true}});
+        let v = execute_json(handle, &cmd.to_string());
+        assert!(v["JsonMerge"]["version"].is_u64(), "got: {v}");
+
+        // Deep: nested fields merge, untouched siblings survive, arrays replace.
+        let patch = json!({"temperature": 0.2, "limits": {"max_tokens": 8192}, "tools": ["web_search"]});
+        let cmd = json!({"JsonMerge": {"key": "doc:agent-config", "value": tagged(patch), "deep": true}});
+        execute_json(handle, &cmd.to_string());
+        let config = get_config();
+        assert_eq!(config["temperature"]["Float"], 0.2);
+        assert_eq!(config["name"]["String"], "research-agent-v2");
+        assert_eq!(config["limits"]["Object"]["max_tokens"]["Int"], 8192);
+        assert_eq!(config["limits"]["Object"]["timeout_ms"]["Int"], 30000);
+        assert_eq!(config["tools"]["Array"].as_array().unwrap().len(), 1);
+
+        // Shallow: a nested object replaces the existing one wholesale.
+        let patch = json!({"limits": {"max_tokens": 1024}});
+        let cmd = json!({"JsonMerge": {"key": "doc:agent-config", "value": tagged(patch), "deep": false}});
+        execute_json(handle, &cmd.to_string());
+        let config = get_config();
+        assert_eq!(config["limits"]["Object"]["max_tokens"]["Int"], 1024);
+        assert!(config["limits"]["Object"].get("timeout_ms").is_none());
+        assert_eq!(config["model"]["String"], "claude-sonnet-4-6");
+
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_handle_uptime_increases() {
+        let handle = open_memory_handle();
+        let first = strata_handle_uptime_ms(handle);
+        assert!(first >= 0);
+        std::thread::sleep(std::time::Duration::from_millis(15));
+        let second = strata_handle_uptime_ms(handle);
+        assert!(second > first, "uptime should increase: {first} -> {second}");
+
+        let ptr = strata_list_handles();
+        let list = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&list).unwrap();
+        let entry = v["ok"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|h| h["handle"] == handle)
+            .expect("handle should be listed");
+        assert_eq!(entry["kind"], "memory");
+        assert!(entry["opened_at_ms"].as_u64().unwrap() > 0);
+
+        strata_close(handle);
+        assert_eq!(strata_handle_uptime_ms(handle), -1);
+    }
+
+    #[test]
+    fn test_stream_kv_list_to_completion() {
+        let handle = open_sample_handle();
+        let cmd = CString::new(r#"{"KvList":{}}"#).unwrap();
+        let ptr = strata_stream_open(handle, cmd.as_ptr());
+        let opened = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let stream_id = serde_json::from_str::<serde_json::Value>(&opened).unwrap()["ok"]
+            .as_u64()
+            .unwrap_or_else(|| panic!("expected stream id, got: {opened}"));
+
+        let mut keys = Vec::new();
+        loop {
+            let ptr = strata_stream_next(stream_id);
+            let row = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            let v: serde_json::Value = serde_json::from_str(&row).unwrap();
+            if v["done"] == true {
+                break;
+            }
+            keys.push(v["ok"].as_str().unwrap_or_else(|| panic!("unexpected row: {row}")).to_string());
+        }
+        assert_eq!(keys.len(), 14);
+        assert!(keys.windows(2).all(|w| w[0] < w[1]), "keys should arrive in order");
+
+        // A drained stream keeps reporting done until closed.
+        let ptr = strata_stream_next(stream_id);
+        assert_eq!(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap(), r#"{"done":true}"#);
+        unsafe { strata_free_string(ptr) };
+
+        strata_stream_close(stream_id);
+        let ptr = strata_stream_next(stream_id);
+        assert!(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().contains("invalid stream"));
+        unsafe { strata_free_string(ptr) };
+
+        // Writes cannot be smuggled through a stream.
+        let cmd = CString::new(r#"{"KvPut":{"key":"streamed","value":{"Int":1}}}"#).unwrap();
+        let ptr = strata_stream_open(handle, cmd.as_ptr());
+        let opened = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert!(opened.contains("InvalidInput"), "got: {opened}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"streamed"}}"#);
+        assert!(v["MaybeVersioned"].is_null(), "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_flush_all_flushes_file_handles() {
+        let mut handles = Vec::new();
+        for _ in 0..2 {
+            let ptr = strata_open_temp(std::ptr::null());
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let handle = v["ok"]["handle"].as_u64().unwrap();
+            execute_json(handle, r#"{"KvPut":{"key":"k","value":{"Int":1}}}"#);
+            handles.push(handle);
+        }
+        let memory = open_memory_handle();
+
+        let ptr = strata_flush_all();
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+
+        // Other tests may have file handles open concurrently, so only check ours.
+        assert!(v["ok"]["flushed"].as_u64().unwrap() >= 2, "{result}");
+        let errors = v["ok"]["errors"].as_array().unwrap();
+        for handle in handles.iter().chain([&memory]) {
+            assert!(!errors.iter().any(|e| e["handle"] == *handle), "{result}");
+        }
+
+        for handle in handles {
+            strata_close(handle);
+        }
+        strata_close(memory);
+    }
+
+    #[test]
+    fn test_digest_tracks_content() {
+        let handle = open_sample_handle();
+        let digest = |handle| {
+            execute_json(handle, r#"{"Digest":{"primitives":["kv","json"]}}"#)["Digest"]["digest"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        let original = digest(handle);
+        let key = r#""key":"config:app_version""#;
+        let before = execute_json(handle, &format!(r#"{{"KvGet":{{{key}}}}}"#))["MaybeVersioned"]["value"].clone();
+
+        execute_json(handle, &format!(r#"{{"KvPut":{{{key},"value":{{"String":"changed"}}}}}}"#));
+        assert_ne!(digest(handle), original);
+
+        execute_json(handle, &format!(r#"{{"KvPut":{{{key},"value":{before}}}}}"#));
+        assert_eq!(digest(handle), original, "reverting should restore the digest");
+
+        // Logical content, not history: a second DB with the same data matches.
+        let twin = open_sample_handle();
+        assert_eq!(digest(twin), original);
+
+        strata_close(handle);
+        strata_close(twin);
+    }
+
+    #[test]
+    fn test_export_json_sample() {
+        let handle = open_sample_handle();
+        let out = std::env::temp_dir().join(format!("strata-export-{}.json", std::process::id()));
+        let out_c = CString::new(out.to_str().unwrap()).unwrap();
+
+        let ptr = strata_export_json(handle, out_c.as_ptr());
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(v["ok"]["kv"], 14, "{result}");
+
+        let text = std::fs::read_to_string(&out).unwrap();
+        let dump: serde_json::Value = serde_json::from_str(&text).unwrap();
+        let sections: Vec<&str> = dump.as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(sections, ["branches", "events", "json", "kv", "state"]);
+        assert_eq!(dump["kv"].as_object().unwrap().len(), 14);
+        assert_eq!(dump["state"].as_object().unwrap().len(), 7);
+        assert_eq!(dump["events"].as_array().unwrap().len(), 20);
+        assert_eq!(dump["json"].as_object().unwrap().len(), 4);
+        assert!(dump["branches"].as_array().unwrap().contains(&"experiment".into()));
+
+        // Exporting unchanged data again produces an identical file.
+        let ptr = strata_export_json(handle, out_c.as_ptr());
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), text);
+
+        let _ = std::fs::remove_file(out);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_export_primitive_events() {
+        let handle = open_sample_handle();
+        let out = std::env::temp_dir().join(format!("strata-events-{}.json", std::process::id()));
+        let out_c = CString::new(out.to_str().unwrap()).unwrap();
+        let export = |primitive: &str| {
+            let primitive = CString::new(primitive).unwrap();
+            let ptr = strata_export_primitive(handle, primitive.as_ptr(), out_c.as_ptr());
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+
+        let v = export("events");
+        assert_eq!(v["ok"]["count"], 20, "got: {v}");
+        let events: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        let events = events.as_array().expect("events export should be an array");
+        assert_eq!(events.len(), 20);
+        assert!(events[0]["event_type"].is_string());
+
+        let create = r#"{"VectorCreateCollection":{"collection":"docs","dimension":2}}"#;
+        execute_json(handle, create);
+        for (key, vector) in [("a", [1.0, 0.0]), ("b", [0.0, 1.0])] {
+            let upsert = serde_json::json!({ "VectorUpsert": {
+                "collection": "docs", "key": key, "vector": vector,
+            }});
+            execute_json(handle, &upsert.to_string());
+        }
+        let v = export("vectors");
+        assert_eq!(v["ok"]["count"], 1, "got: {v}");
+        let vectors: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(vectors["docs"]["dimension"], 2, "got: {vectors}");
+        assert_eq!(vectors["docs"]["vectors"]["b"]["embedding"], serde_json::json!([0.0, 1.0]));
+
+        let v = export("blobs");
+        assert_eq!(v["error"]["InvalidInput"]["primitive"], "blobs", "got: {v}");
+
+        let _ = std::fs::remove_file(out);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_import_json_round_trip() {
+        let sample = open_sample_handle();
+        let out = std::env::temp_dir().join(format!("strata-import-{}.json", std::process::id()));
+        let out_c = CString::new(out.to_str().unwrap()).unwrap();
+        let ptr = strata_export_json(sample, out_c.as_ptr());
+        unsafe { strata_free_string(ptr) };
+
+        // Unknown sections are skipped rather than failing the import.
+        let mut dump: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        dump["vectors"] = serde_json::json!({});
+        std::fs::write(&out, dump.to_string()).unwrap();
+
+        let fresh = open_memory_handle();
+        execute_json(fresh, r#"{"KvPut":{"key":"stale","value":{"Int":1}}}"#);
+        let mode = CString::new("replace").unwrap();
+        let ptr = strata_import_json(fresh, out_c.as_ptr(), mode.as_ptr());
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(v["ok"]["kv"], 14, "{result}");
+        assert_eq!(v["ok"]["ignored"], serde_json::json!(["vectors"]));
+
+        let digest = |handle| execute_json(handle, r#"{"Digest":{}}"#)["Digest"]["digest"].clone();
+        assert_eq!(digest(fresh), digest(sample), "import should reproduce the sample data");
+        assert!(execute_json(fresh, r#"{"BranchExists":{"branch":"staging"}}"#)["Bool"] == true);
+
+        let _ = std::fs::remove_file(out);
+        strata_close(sample);
+        strata_close(fresh);
+    }
+
+    #[test]
+    fn test_error_verbosity_levels() {
+        let handle = open_memory_handle();
+        let cmd = r#"{"KvRename":{"from":"missing","to":"other"}}"#;
+        let err = REGISTRY.execute(handle, cmd).unwrap_err();
+
+        let field_count = |level| {
+            let rendered = err.render_at(level, Some(cmd));
+            rendered["KeyNotFound"].as_object().unwrap().len()
+        };
+        assert!(field_count(error::VERBOSITY_TERSE) < field_count(error::VERBOSITY_DEBUG));
+
+        let debug = err.render_at(error::VERBOSITY_DEBUG, Some(cmd));
+        assert_eq!(debug["KeyNotFound"]["key"], "missing");
+        assert_eq!(debug["KeyNotFound"]["command"]["KvRename"]["from"], "missing");
+        strata_close(handle);
+    }
+
+    static RECOVERY_EVENTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    extern "C" fn capture_recovery_event(event: *const c_char) {
+        let event = unsafe { CStr::from_ptr(event) }.to_string_lossy().into_owned();
+        RECOVERY_EVENTS.lock().unwrap().push(event);
+    }
+
+    #[test]
+    fn test_open_with_recovery_reports_summary() {
+        let dir = std::env::temp_dir().join(format!("strata-recovery-{}.strata", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path_c = CString::new(dir.to_str().unwrap()).unwrap();
+
+        let ptr = strata_open(path_c.as_ptr(), std::ptr::null());
+        let opened = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let handle = serde_json::from_str::<serde_json::Value>(&opened).unwrap()["ok"]
+            .as_u64()
+            .unwrap();
+        execute_json(handle, r#"{"KvPut":{"key":"k","value":{"Int":1}}}"#);
+        strata_close(handle);
+
+        let ptr = strata_open_with_recovery(path_c.as_ptr(), std::ptr::null(), Some(capture_recovery_event));
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let recovery = &v["ok"]["recovery"];
+        assert!(recovery["duration_ms"].is_u64(), "{result}");
+        assert!(recovery["wal_bytes"].is_u64(), "{result}");
+
+        let events = RECOVERY_EVENTS.lock().unwrap().clone();
+        assert!(events.iter().any(|e| e.contains(r#""phase":"recovering""#)));
+        assert!(events.iter().any(|e| e.contains(r#""phase":"complete""#)));
+
+        let handle = v["ok"]["handle"].as_u64().unwrap();
+        let got = execute_json(handle, r#"{"KvGet":{"key":"k"}}"#);
+        assert_eq!(got["MaybeVersioned"]["value"]["Int"], 1);
+
+        strata_close(handle);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_kv_bytes_round_trip_with_nul() {
+        let handle = open_memory_handle();
+        let key = CString::new("blob").unwrap();
+        let data: &[u8] = b"\x89PNG\0\r\n\0\xff";
+
+        let ptr = strata_kv_put_bytes(handle, key.as_ptr(), data.as_ptr(), data.len());
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert!(result.starts_with(r#"{"ok":"#), "{result}");
+
+        let mut len = usize::MAX;
+        let buf = strata_kv_get_bytes(handle, key.as_ptr(), &mut len);
+        assert!(!buf.is_null());
+        assert_eq!(unsafe { std::slice::from_raw_parts(buf, len) }, data);
+        unsafe { strata_free_bytes(buf, len) };
+
+        let missing = CString::new("missing").unwrap();
+        let buf = strata_kv_get_bytes(handle, missing.as_ptr(), &mut len);
+        assert!(buf.is_null());
+        assert_eq!(len, 0);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_peek_tag() {
+        use commands::peek_tag;
+        assert_eq!(peek_tag(r#"{"KvGet":{"key":"a"}}"#), Some("KvGet"));
+        assert_eq!(peek_tag(r#" { "Ping" : null }"#), Some("Ping"));
+        assert_eq!(peek_tag(r#""Ping""#), Some("Ping"));
+        assert_eq!(peek_tag(r#"{"Kv\u0047et":{}}"#), None);
+        assert_eq!(peek_tag(r#""Ping" trailing"#), None);
+        assert_eq!(peek_tag("[1,2]"), None);
+    }
+
+    #[test]
+    fn test_fast_and_general_paths_agree() {
+        let handle = open_sample_handle();
+        let commands = [
+            r#"{"KvGet":{"key":"user:alice"}}"#,
+            r#"{"KvList":{"prefix":"config:"}}"#,
+            r#"{"StateList":{}}"#,
+            r#"{"EventLen":{}}"#,
+            r#"{"EventGet":{"sequence":3}}"#,
+            r#"{"JsonGet":{"key":"missing","path":"$"}}"#,
+            r#"{"BranchExists":{"branch":"staging"}}"#,
+            r#"{"KvGet":{"key":"user:alice","bogus":1}}"#,
+            r#"{"NoSuchCommand":{}}"#,
+        ];
+        for cmd in commands {
+            let run = |path: fn(&stratadb::Strata, &str) -> Result<String, BridgeError>| {
+                REGISTRY
+                    .run_guarded(handle, |strata| path(strata, cmd))
+                    .map_err(|e| e.to_json())
+            };
+            let fast = run(handle::execute_direct);
+            let general = run(|strata, cmd| handle::execute_general(strata, cmd, Default::default()));
+            match (&fast, &general) {
+                (Ok(a), Ok(b)) => assert_eq!(a, b, "{cmd}"),
+                // serde reports positions differently for str and Value input,
+                // so for failures only the error kind must match.
+                (Err(a), Err(b)) => assert_eq!(
+                    a.as_object().unwrap().keys().collect::<Vec<_>>(),
+                    b.as_object().unwrap().keys().collect::<Vec<_>>(),
+                    "{cmd}"
+                ),
+                _ => panic!("paths disagree for {cmd}: {fast:?} vs {general:?}"),
+            }
+        }
+        strata_close(handle);
+    }
+
+    /// Compare throughput of the direct and general parse paths.
+    /// Run with:
+    ///   cargo test bench_execute_paths -- --nocapture --ignored
+    #[test]
+    #[ignore]
+    fn bench_execute_paths() {
+        let handle = open_sample_handle();
+        let cmd = r#"{"KvGet":{"key":"user:alice"}}"#;
+        let iterations = 50_000;
+        for (name, path) in [
+            ("direct", handle::execute_direct as fn(&stratadb::Strata, &str) -> _),
+            ("general", |strata, cmd| handle::execute_general(strata, cmd, Default::default())),
+        ] {
+            let started = std::time::Instant::now();
+            for _ in 0..iterations {
+                REGISTRY.run_guarded(handle, |strata| path(strata, cmd)).unwrap();
+            }
+            let elapsed = started.elapsed();
+            println!(
+                "{name}: {iterations} KvGet in {elapsed:?} ({:.0} ops/s)",
+                iterations as f64 / elapsed.as_secs_f64()
+            );
+        }
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_kv_namespaces() {
+        let handle = open_sample_handle();
+        let out = execute_json(handle, r#"{"KvNamespaces":{}}"#);
+        assert_eq!(
+            out["KvNamespaces"],
+            serde_json::json!([
+                {"prefix": "cache", "count": 2},
+                {"prefix": "config", "count": 5},
+                {"prefix": "counter", "count": 3},
+                {"prefix": "session", "count": 1},
+                {"prefix": "user", "count": 3},
+            ])
+        );
+
+        execute_json(handle, r#"{"KvPut":{"key":"plain","value":"Null"}}"#);
+        let out = execute_json(handle, r#"{"KvNamespaces":{"separator":":"}}"#);
+        assert_eq!(out["KvNamespaces"][0], serde_json::json!({"prefix": "", "count": 1}));
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_execute_compressed_round_trip() {
+        let handle = open_memory_handle();
+        for i in 0..500 {
+            execute_json(
+                handle,
+                &format!(r#"{{"KvPut":{{"key":"item:{i:04}","value":{{"String":"payload {i}"}}}}}}"#),
+            );
+        }
+
+        let compressed = |cmd: &str| {
+            let cmd = CString::new(cmd).unwrap();
+            let mut len = 0;
+            let ptr = strata_execute_compressed(handle, cmd.as_ptr(), &mut len);
+            let buf = unsafe { std::slice::from_raw_parts(ptr, len) }.to_vec();
+            unsafe { strata_free_bytes(ptr, len) };
+            buf
+        };
+
+        let cmd = r#"{"KvList":{}}"#;
+        let buf = compressed(cmd);
+        assert_eq!(buf[0], compress::FORMAT_ZSTD);
+        let json = zstd::decode_all(&buf[1..]).unwrap();
+        let plain = execute_json(handle, cmd);
+        assert!(buf.len() < json.len(), "compression should shrink a large output");
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&json).unwrap(), plain);
+        assert_eq!(plain["Keys"].as_array().unwrap().len(), 500);
+
+        // Small outputs are passed through with the raw flag.
+        let buf = compressed(r#"{"Ping":null}"#);
+        assert_eq!(buf[0], compress::FORMAT_RAW);
+        assert!(std::str::from_utf8(&buf[1..]).unwrap().contains("Pong"));
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_reopen_keeps_id_and_data() {
+        let ptr = strata_open_temp(std::ptr::null());
+        let opened = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let handle = serde_json::from_str::<serde_json::Value>(&opened).unwrap()["ok"]["handle"]
+            .as_u64()
+            .unwrap();
+        execute_json(handle, r#"{"KvPut":{"key":"k","value":{"Int":7}}}"#);
+
+        // Fault the handle, then recover it.
+        let _ = REGISTRY.run_guarded(handle, |_| -> Result<(), BridgeError> { panic!("simulated") });
+        assert!(execute_json(handle, r#"{"Ping":null}"#)["error"]["HandleFaulted"].is_object());
+
+        let ptr = strata_reopen(handle);
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(result, format!(r#"{{"ok":{handle}}}"#));
+        let got = execute_json(handle, r#"{"KvGet":{"key":"k"}}"#);
+        assert_eq!(got["MaybeVersioned"]["value"]["Int"], 7);
+
+        // A failed reopen faults every handle sharing the path; settings
+        // survive a successful one.
+        let path = serde_json::from_str::<serde_json::Value>(&opened).unwrap()["ok"]["path"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let path_c = CString::new(path.clone()).unwrap();
+        let ptr = strata_open(path_c.as_ptr(), std::ptr::null());
+        let sharer = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let sharer = serde_json::from_str::<serde_json::Value>(&sharer).unwrap()["ok"]
+            .as_u64()
+            .unwrap();
+        unsafe { strata_free_string(strata_set_value_limits(handle, 8, 0)) };
+        let aside = format!("{path}.aside");
+        std::fs::rename(&path, &aside).unwrap();
+        std::fs::write(&path, b"not a directory").unwrap();
+        let ptr = strata_reopen(handle);
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert!(result.contains("HandleFaulted"), "{result}");
+        for id in [handle, sharer] {
+            let v = execute_json(id, r#"{"KvPut":{"key":"lost","value":{"Int":1}}}"#);
+            assert!(v["error"]["HandleFaulted"].is_object(), "got: {v}");
+        }
+        std::fs::remove_file(&path).unwrap();
+        std::fs::rename(&aside, &path).unwrap();
+        unsafe { strata_free_string(strata_reopen(handle)) };
+        let got = execute_json(sharer, r#"{"KvGet":{"key":"k"}}"#);
+        assert_eq!(got["MaybeVersioned"]["value"]["Int"], 7, "got: {got}");
+        let v = execute_json(handle, r#"{"KvPut":{"key":"too-long-key","value":{"Int":1}}}"#);
+        assert!(v["error"]["KeyTooLarge"].is_object(), "got: {v}");
+        strata_close(sharer);
+        strata_close(handle);
+
+        let memory = open_memory_handle();
+        let ptr = strata_reopen(memory);
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert!(result.contains("InvalidInput"), "{result}");
+        strata_close(memory);
+    }
+
+    #[test]
+    fn test_copy_kv_between_handles() {
+        let src = open_memory_handle();
+        let dst = open_memory_handle();
+        execute_json(src, r#"{"KvPut":{"key":"warm","value":{"String":"cached"}}}"#);
+
+        let key = CString::new("warm").unwrap();
+        let ptr = strata_copy_kv(src, dst, key.as_ptr(), std::ptr::null());
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert!(result.starts_with(r#"{"ok":"#), "{result}");
+        let got = execute_json(dst, r#"{"KvGet":{"key":"warm"}}"#);
+        assert_eq!(got["MaybeVersioned"]["value"]["String"], "cached");
+
+        let renamed = CString::new("warm:copy").unwrap();
+        let ptr = strata_copy_kv(src, dst, key.as_ptr(), renamed.as_ptr());
+        unsafe { strata_free_string(ptr) };
+        let got = execute_json(dst, r#"{"KvGet":{"key":"warm:copy"}}"#);
+        assert_eq!(got["MaybeVersioned"]["value"]["String"], "cached");
+
+        let missing = CString::new("cold").unwrap();
+        let ptr = strata_copy_kv(src, dst, missing.as_ptr(), std::ptr::null());
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(v["error"]["KeyNotFound"]["key"], "cold");
+
+        strata_close(src);
+        strata_close(dst);
+    }
+
+    #[test]
+    fn test_spawned_threads_are_named() {
+        let name_of = |role| {
+            threads::spawn(role, || std::thread::current().name().map(String::from))
+                .unwrap()
+                .join()
+                .unwrap()
+                .unwrap()
+        };
+        assert!(name_of("worker").starts_with("strata-worker-"));
+
+        let prefix = CString::new("foundry").unwrap();
+        strata_set_thread_name_prefix(prefix.as_ptr());
+        assert!(name_of("timer").starts_with("foundry-timer-"));
+
+        strata_set_thread_name_prefix(std::ptr::null());
+        assert!(name_of("worker").starts_with("strata-worker-"));
+    }
+
+    #[test]
+    fn test_json_get_inline_returns_plain_object() {
+        let handle = open_memory_handle();
+        let doc = tagged(serde_json::json!({"name": "Ada", "tags": ["x", "y"], "meta": {"n": 1}}));
+        execute_json(
+            handle,
+            &serde_json::json!({"JsonSet": {"key": "doc", "path": "$", "value": doc}}).to_string(),
+        );
+
+        let out = execute_json(handle, r#"{"JsonGetInline":{"key":"doc"}}"#);
+        let value = &out["JsonGetInline"]["value"];
+        assert!(value.is_object(), "expected a JSON object, got {value}");
+        assert_eq!(*value, serde_json::json!({"name": "Ada", "tags": ["x", "y"], "meta": {"n": 1}}));
+
+        let missing = execute_json(handle, r#"{"JsonGetInline":{"key":"nope"}}"#);
+        assert!(missing["JsonGetInline"].is_null());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_value_limits() {
+        let handle = open_memory_handle();
+        let ptr = strata_set_value_limits(handle, 16, 64);
+        unsafe { strata_free_string(ptr) };
+
+        let ok = execute_json(handle, r#"{"KvPut":{"key":"small","value":{"String":"fits"}}}"#);
+        assert!(ok["Version"].is_u64(), "{ok}");
+
+        let big = "x".repeat(100);
+        let rejected = execute_json(
+            handle,
+            &format!(r#"{{"KvPut":{{"key":"big","value":{{"String":"{big}"}}}}}}"#),
+        );
+        assert_eq!(rejected["error"]["ValueTooLarge"]["key"], "big", "{rejected}");
+        assert!(rejected["error"]["ValueTooLarge"]["bytes"].as_u64().unwrap() > 64);
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"big"}}"#)["MaybeVersioned"].is_null());
+
+        let long_key = "k".repeat(17);
+        let rejected = execute_json(
+            handle,
+            &format!(r#"{{"StateSet":{{"cell":"{long_key}","value":"Null"}}}}"#),
+        );
+        assert!(rejected["error"]["KeyTooLarge"].is_object(), "{rejected}");
+
+        // A patch is measured by the values its ops write.
+        execute_json(handle, r#"{"KvPut":{"key":"doc","value":{"Object":{}}}}"#);
+        let update = serde_json::json!({ "KvUpdate": { "key": "doc", "ops": [
+            { "op": "add", "path": "/blob", "value": { "String": big } },
+        ]}});
+        let rejected = execute_json(handle, &update.to_string());
+        assert_eq!(rejected["error"]["ValueTooLarge"]["key"], "doc", "{rejected}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"doc"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"], serde_json::json!({ "Object": {} }), "{v}");
+
+        // A copy from an unlimited handle is held to the destination's limits.
+        let source = open_memory_handle();
+        let put = serde_json::json!({ "KvPut": { "key": "blob", "value": { "String": big } } });
+        execute_json(source, &put.to_string());
+        let (blob, long_key) = (CString::new("blob").unwrap(), CString::new(long_key).unwrap());
+        let ptr = strata_copy_kv(source, handle, blob.as_ptr(), std::ptr::null());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(v["error"]["ValueTooLarge"]["key"], "blob", "{v}");
+        execute_json(source, r#"{"KvPut":{"key":"small","value":{"Int":1}}}"#);
+        let small = CString::new("small").unwrap();
+        let ptr = strata_copy_kv(source, handle, small.as_ptr(), long_key.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert!(v["error"]["KeyTooLarge"].is_object(), "{v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"blob"}}"#)["MaybeVersioned"].is_null());
+        strata_close(source);
+
+        // Zero lifts the limits.
+        let ptr = strata_set_value_limits(handle, 0, 0);
+        unsafe { strata_free_string(ptr) };
+        let ok = execute_json(
+            handle,
+            &format!(r#"{{"KvPut":{{"key":"big","value":{{"String":"{big}"}}}}}}"#),
+        );
+        assert!(ok["Version"].is_u64(), "{ok}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_audit_records_mutations_in_order() {
+        let handle = open_memory_handle();
+        let ptr = strata_set_audit(handle, 3);
+        unsafe { strata_free_string(ptr) };
+
+        execute_json(handle, r#"{"KvPut":{"key":"a","value":{"Int":1}}}"#);
+        execute_json(handle, r#"{"KvGet":{"key":"a"}}"#);
+        execute_json(handle, r#"{"StateSet":{"cell":"s","value":{"Int":2}}}"#);
+        execute_json(handle, r#"{"KvDelete":{"key":"a"}}"#);
+        execute_json(handle, r#"{"JsonSet":{"key":"d","path":"$","value":{"Object":{}}}}"#);
+
+        let ptr = strata_get_audit(handle);
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let entries = v["ok"].as_array().unwrap();
+
+        // Capacity 3: the first KvPut was dropped, and the read never recorded.
+        let recorded: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|e| (e["command_tag"].as_str().unwrap(), e["key"].as_str().unwrap()))
+            .collect();
+        assert_eq!(recorded, [("StateSet", "s"), ("KvDelete", "a"), ("JsonSet", "d")]);
+        assert!(entries[0]["timestamp"].as_u64().unwrap() <= entries[2]["timestamp"].as_u64().unwrap());
+
+        // A transaction's writes are recorded once it commits.
+        let ptr = strata_txn_begin(handle, std::ptr::null());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let txn = v["ok"].as_u64().unwrap();
+        for cmd in [r#"{"KvPut":{"key":"t","value":{"Int":3}}}"#, r#"{"KvGet":{"key":"t"}}"#] {
+            let cmd = CString::new(cmd).unwrap();
+            unsafe { strata_free_string(strata_txn_execute(txn, cmd.as_ptr())) };
+        }
+        let audit = || {
+            let ptr = strata_get_audit(handle);
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v["ok"].as_array().unwrap().last().cloned().unwrap()
+        };
+        assert_eq!(audit()["key"], "d", "nothing is recorded before the commit");
+        unsafe { strata_free_string(strata_txn_commit(txn)) };
+        let last = audit();
+        assert_eq!(last["command_tag"], "KvPut", "got: {last}");
+        assert_eq!(last["key"], "t", "got: {last}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_relative_open_resolves_under_base_dir() {
+        let base = std::env::temp_dir().join(format!("strata-base-{}", std::process::id()));
+        std::fs::create_dir_all(&base).unwrap();
+        let base_c = CString::new(base.to_str().unwrap()).unwrap();
+        strata_set_base_dir(base_c.as_ptr());
+
+        let rel = CString::new("nested/app.strata").unwrap();
+        let ptr = strata_open(rel.as_ptr(), std::ptr::null());
+        let opened = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        strata_set_base_dir(std::ptr::null());
+        let handle = serde_json::from_str::<serde_json::Value>(&opened).unwrap()["ok"]
+            .as_u64()
+            .unwrap_or_else(|| panic!("open failed: {opened}"));
+
+        let listed = REGISTRY.list().into_iter().find(|h| h["handle"] == handle).unwrap();
+        assert_eq!(listed["path"], base.join("nested/app.strata").to_str().unwrap());
+
+        // Absolute paths bypass resolution, and clearing restores the CWD.
+        assert_eq!(REGISTRY.resolve_path("/abs/db.strata"), std::path::Path::new("/abs/db.strata"));
+        assert_eq!(REGISTRY.resolve_path("rel.strata"), std::path::Path::new("rel.strata"));
+
+        strata_close(handle);
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn test_concurrent_opens_share_database() {
+        let dir = std::env::temp_dir().join(format!("strata-shared-{}.strata", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.to_str().unwrap().to_string();
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let path_c = CString::new(path).unwrap();
+                    let ptr = strata_open(path_c.as_ptr(), std::ptr::null());
+                    let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+                    unsafe { strata_free_string(ptr) };
+                    serde_json::from_str::<serde_json::Value>(&result).unwrap()["ok"]
+                        .as_u64()
+                        .unwrap_or_else(|| panic!("open failed: {result}"))
+                })
+            })
+            .collect();
+        let mut handles: Vec<u64> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        handles.sort_unstable();
+        handles.dedup();
+        assert_eq!(handles.len(), 8, "each open gets its own handle id");
+
+        // Writes through one handle are visible through every other.
+        execute_json(handles[0], r#"{"KvPut":{"key":"shared","value":{"Int":42}}}"#);
+        for &handle in &handles {
+            let got = execute_json(handle, r#"{"KvGet":{"key":"shared"}}"#);
+            assert_eq!(got["MaybeVersioned"]["value"]["Int"], 42, "handle {handle}");
+        }
+
+        // Closing all but one keeps the database open for the last.
+        for &handle in &handles[1..] {
+            strata_close(handle);
+        }
+        assert!(execute_json(handles[0], r#"{"Ping":null}"#)["Pong"].is_object());
+        strata_close(handles[0]);
+
+        // After the last close the path opens afresh with its data on disk.
+        let path_c = CString::new(path).unwrap();
+        let ptr = strata_open(path_c.as_ptr(), std::ptr::null());
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let handle = serde_json::from_str::<serde_json::Value>(&result).unwrap()["ok"].as_u64().unwrap();
+        let got = execute_json(handle, r#"{"KvGet":{"key":"shared"}}"#);
+        assert_eq!(got["MaybeVersioned"]["value"]["Int"], 42);
+        strata_close(handle);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_execute_idempotent_runs_once() {
+        let handle = open_memory_handle();
+        let execute = |key: &str, cmd: &str| {
+            let key = CString::new(key).unwrap();
+            let cmd = CString::new(cmd).unwrap();
+            let ptr = strata_execute_idempotent(handle, key.as_ptr(), cmd.as_ptr());
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+
+        let append = r#"{"EventAppend":{"event_type":"retry","payload":{"Int":1}}}"#;
+        let first = execute("req-1", append);
+        let second = execute("req-1", append);
+        assert_eq!(first, second);
+        let v = execute_json(handle, r#"{"EventLen":{}}"#);
+        assert_eq!(v["Uint"], 1, "retry must not append twice");
+
+        // A new key runs the command again.
+        execute("req-2", append);
+        assert_eq!(execute_json(handle, r#"{"EventLen":{}}"#)["Uint"], 2);
+
+        let v = execute("req-1", r#"{"EventAppend":{"event_type":"other","payload":{"Int":2}}}"#);
+        assert_eq!(v["error"]["IdempotencyKeyReused"]["key"], "req-1", "got: {v}");
+        strata_close(handle);
+    }
+
+    static KV_CHANGES: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    extern "C" fn capture_kv_change(event_json: *const c_char, user_data: *mut c_void) {
+        let event = unsafe { CStr::from_ptr(event_json) }.to_str().unwrap().to_string();
+        assert_eq!(user_data as usize, 0xBEEF);
+        KV_CHANGES.lock().unwrap().push(event);
+    }
+
+    #[test]
+    fn test_safe_free_ignores_foreign_pointer() {
+        strata_set_safe_free(true);
+        let foreign = CString::new("not from the bridge").unwrap().into_raw();
+        unsafe { strata_free_string(foreign) };
+        // Still intact: safe mode refused to free it.
+        let back = unsafe { CString::from_raw(foreign) };
+        assert_eq!(back.to_str().unwrap(), "not from the bridge");
+
+        // Bridge strings are still freed, once.
+        let ptr = strata_ping();
+        unsafe { strata_free_string(ptr) };
+        unsafe { strata_free_string(ptr) };
+        strata_set_safe_free(false);
+    }
+
+    #[test]
+    fn test_kv_subscribe_prefix() {
+        let handle = open_sample_handle();
+        let prefix = CString::new("user:").unwrap();
+        let ptr = strata_kv_subscribe_prefix(
+            handle,
+            prefix.as_ptr(),
+            Some(capture_kv_change),
+            0xBEEF as *mut c_void,
+        );
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+        let subscription = v["ok"].as_u64().expect("expected ok with subscription id");
+
+        execute_json(handle, r#"{"KvPut":{"key":"user:dave","value":{"String":"dave"}}}"#);
+        execute_json(handle, r#"{"KvPut":{"key":"config:theme","value":{"String":"dark"}}}"#);
+        execute_json(handle, r#"{"KvDelete":{"key":"user:dave"}}"#);
+
+        let events: Vec<serde_json::Value> = KV_CHANGES
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|e| serde_json::from_str(&e).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                serde_json::json!({"key": "user:dave", "event": "put"}),
+                serde_json::json!({"key": "user:dave", "event": "delete"}),
+            ]
+        );
+
+        strata_kv_unsubscribe(subscription);
+        execute_json(handle, r#"{"KvPut":{"key":"user:erin","value":{"String":"erin"}}}"#);
+        assert!(KV_CHANGES.lock().unwrap().is_empty());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_txn_rollback_to_savepoint() {
+        let handle = open_memory_handle();
+        let read = |ptr: *mut c_char| {
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+        let txn_execute = |txn: u64, cmd: &str| {
+            let cmd = CString::new(cmd).unwrap();
+            read(strata_txn_execute(txn, cmd.as_ptr()))
+        };
+
+        let txn = read(strata_txn_begin(handle, std::ptr::null()))["ok"].as_u64().unwrap();
+        txn_execute(txn, r#"{"KvPut":{"key":"step:1","value":{"Int":1}}}"#);
+        let savepoint = read(strata_txn_savepoint(txn))["ok"].as_u64().unwrap();
+        txn_execute(txn, r#"{"KvPut":{"key":"step:2","value":{"Int":2}}}"#);
+        txn_execute(txn, r#"{"KvPut":{"key":"step:1","value":{"Int":10}}}"#);
+
+        // Uncommitted writes are invisible outside the transaction.
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"step:1"}}"#)["MaybeVersioned"].is_null());
+
+        let v = read(strata_txn_rollback_to(txn, savepoint));
+        assert!(v["ok"].is_null() && v.get("error").is_none(), "got: {v}");
+        let v = txn_execute(txn, r#"{"KvGet":{"key":"step:2"}}"#);
+        assert!(v["MaybeVersioned"].is_null(), "got: {v}");
+        txn_execute(txn, r#"{"KvPut":{"key":"step:3","value":{"Int":3}}}"#);
+
+        let v = read(strata_txn_commit(txn));
+        assert!(v.get("error").is_none(), "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"step:1"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 1);
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"step:2"}}"#)["MaybeVersioned"].is_null());
+        let v = execute_json(handle, r#"{"KvGet":{"key":"step:3"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 3);
+
+        // The transaction is gone after commit.
+        assert!(read(strata_txn_savepoint(txn))["error"].is_object());
+        strata_close(handle);
+    }
+
+    static HOOKED_OUTPUTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    /// Rejects `KvPut` to `hook:` keys; other tests' commands pass through.
+    extern "C" fn reject_hooked_puts(tag: *const c_char, command_json: *const c_char) -> i32 {
+        let tag = unsafe { CStr::from_ptr(tag) }.to_str().unwrap();
+        let command = unsafe { CStr::from_ptr(command_json) }.to_str().unwrap();
+        (tag == "KvPut" && command.contains("hook:")) as i32
+    }
+
+    extern "C" fn record_hooked_output(tag: *const c_char, output_json: *const c_char) {
+        let tag = unsafe { CStr::from_ptr(tag) }.to_str().unwrap();
+        let output = unsafe { CStr::from_ptr(output_json) }.to_str().unwrap();
+        if tag == "KvGet" && output.contains("hooked-value") {
+            HOOKED_OUTPUTS.lock().unwrap().push(output.to_string());
+        }
+    }
+
+    #[test]
+    fn test_command_hook_rejects_and_observes() {
+        let handle = open_memory_handle();
+        execute_json(handle, r#"{"KvPut":{"key":"seed","value":{"String":"hooked-value"}}}"#);
+        strata_set_command_hook(Some(reject_hooked_puts), Some(record_hooked_output));
+
+        let v = execute_json(handle, r#"{"KvPut":{"key":"hook:blocked","value":{"Int":1}}}"#);
+        assert_eq!(v["error"]["Rejected"]["command"], "KvPut", "got: {v}");
+        assert_eq!(v["error"]["Rejected"]["code"], 1);
+
+        let v = execute_json(handle, r#"{"KvGet":{"key":"seed"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["String"], "hooked-value");
+        assert_eq!(HOOKED_OUTPUTS.lock().unwrap().len(), 1);
+
+        strata_set_command_hook(None, None);
+        let v = execute_json(handle, r#"{"KvGet":{"key":"hook:blocked"}}"#);
+        assert!(v["MaybeVersioned"].is_null(), "rejected put must not be applied: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_kv_get_meta_versions() {
+        let handle = open_memory_handle();
+        let meta = || execute_json(handle, r#"{"KvGetMeta":{"key":"sync:doc"}}"#)["KvGetMeta"].clone();
+        assert!(meta().is_null());
+
+        execute_json(handle, r#"{"KvPut":{"key":"sync:doc","value":{"Int":1}}}"#);
+        let first = meta();
+        execute_json(handle, r#"{"KvPut":{"key":"sync:doc","value":{"Int":2}}}"#);
+        let second = meta();
+
+        assert_eq!(second["value"]["Int"], 2);
+        assert!(second["version"].as_u64() > first["version"].as_u64(), "{first} then {second}");
+        let updated_at = second["updated_at"].as_str().unwrap();
+        assert!(updated_at.ends_with('Z') && updated_at.len() == 27, "{updated_at}");
+        // RFC 3339 strings of equal length sort chronologically.
+        assert!(second["updated_at"].as_str() >= first["updated_at"].as_str());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_json_catalog() {
+        let handle = open_sample_handle();
+        let v = execute_json(handle, r#"{"JsonCatalog":{"limit":100}}"#);
+        let entries = v["JsonCatalog"].as_array().expect("expected a catalog list");
+        assert_eq!(entries.len(), 4, "got: {v}");
+        for entry in entries {
+            assert!(entry["bytes"].as_u64().unwrap() > 0, "{entry}");
+            assert!(entry.get("content").is_none());
+        }
+
+        let readme = entries.iter().find(|e| e["key"] == "doc:readme").unwrap();
+        let mut fields: Vec<&str> =
+            readme["fields"].as_array().unwrap().iter().map(|f| f.as_str().unwrap()).collect();
+        fields.sort_unstable();
+        assert_eq!(fields, ["author", "content", "created", "status", "tags", "title"]);
+
+        let v = execute_json(handle, r#"{"JsonCatalog":{"limit":2}}"#);
+        assert_eq!(v["JsonCatalog"].as_array().unwrap().len(), 2);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_int_as_string() {
+        let handle = open_memory_handle();
+        execute_json(handle, r#"{"KvPut":{"key":"counter:big","value":{"Int":9007199254740993}}}"#);
+        let get = r#"{"KvGet":{"key":"counter:big"}}"#;
+
+        let v = execute_json(handle, get);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 9_007_199_254_740_993_i64);
+
+        let ptr = strata_set_int_as_string(handle, true);
+        unsafe { strata_free_string(ptr) };
+        let v = execute_json(handle, get);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], "9007199254740993", "got: {v}");
+        // Only values are rewritten, not versions.
+        assert!(v["MaybeVersioned"]["version"].is_u64());
+
+        // A document that looks like a tagged Int is user data: tagged, it is
+        // rewritten only where the Int really is, and plain it is left alone.
+        let doc = tagged(serde_json::json!({ "Int": 5 }));
+        let set = serde_json::json!({ "JsonSet": { "key": "doc", "path": "$", "value": doc } });
+        execute_json(handle, &set.to_string());
+        let v = execute_json(handle, r#"{"JsonGet":{"key":"doc","path":"$"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Object"]["Int"]["Int"], "5", "got: {v}");
+        let v = execute_json(handle, r#"{"JsonGetInline":{"key":"doc"}}"#);
+        assert_eq!(v["JsonGetInline"]["value"], serde_json::json!({ "Int": 5 }), "got: {v}");
+
+        let ptr = strata_set_int_as_string(handle, false);
+        unsafe { strata_free_string(ptr) };
+        assert!(execute_json(handle, get)["MaybeVersioned"]["value"]["Int"].is_i64());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_state_diff_between_events() {
+        let handle = open_memory_handle();
+        let step = |cmd: &str| {
+            execute_json(handle, cmd);
+            // Keep each write's timestamp distinct from the next event's.
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        };
+        step(r#"{"KvPut":{"key":"plan:step","value":{"Int":1}}}"#);
+        step(r#"{"KvPut":{"key":"plan:scratch","value":{"Int":0}}}"#);
+        step(r#"{"EventAppend":{"event_type":"step","payload":{"Int":1}}}"#);
+        step(r#"{"KvPut":{"key":"plan:step","value":{"Int":2}}}"#);
+        step(r#"{"KvPut":{"key":"plan:result","value":{"String":"done"}}}"#);
+        step(r#"{"KvDelete":{"key":"plan:scratch"}}"#);
+        step(r#"{"EventAppend":{"event_type":"step","payload":{"Int":2}}}"#);
+
+        let v = execute_json(handle, r#"{"StateDiffBetween":{"from":0,"to":1,"primitive":"kv"}}"#);
+        let diff = &v["StateDiffBetween"];
+        assert_eq!(
+            diff["changed"],
+            serde_json::json!([{"key": "plan:step", "old": {"Int": 1}, "new": {"Int": 2}}]),
+            "got: {v}"
+        );
+        assert_eq!(diff["added"], serde_json::json!([{"key": "plan:result", "new": {"String": "done"}}]));
+        assert_eq!(diff["removed"], serde_json::json!([{"key": "plan:scratch", "old": {"Int": 0}}]));
+
+        let v = execute_json(handle, r#"{"StateDiffBetween":{"from":0,"to":7,"primitive":"kv"}}"#);
+        assert_eq!(v["error"]["InvalidInput"]["sequence"], 7, "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_execute_batch_atomic_rolls_back() {
+        let handle = open_memory_handle();
+        let batch = |json: &str| {
+            let json = CString::new(json).unwrap();
+            let ptr = strata_execute_batch(handle, json.as_ptr());
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+        let failing = r#"[
+            {"KvPut":{"key":"batch:a","value":{"Int":1}}},
+            {"KvFrobnicate":{"key":"batch:a"}},
+            {"KvPut":{"key":"batch:b","value":{"Int":2}}}
+        ]"#;
+
+        let v = batch(&format!(r#"{{"atomic":true,"commands":{failing}}}"#));
+        assert_eq!(v["error"]["BatchFailed"]["index"], 1, "got: {v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"batch:a"}}"#)["MaybeVersioned"].is_null());
+
+        // Best effort: the others still land, and each result is reported.
+        let v = batch(failing);
+        let results = v["ok"].as_array().expect("expected ok with results");
+        assert_eq!(results.len(), 3);
+        assert!(results[1]["error"].is_object(), "got: {v}");
+        assert_eq!(execute_json(handle, r#"{"KvGet":{"key":"batch:b"}}"#)["MaybeVersioned"]["value"]["Int"], 2);
+
+        let v = batch(r#"{"atomic":true,"commands":[{"KvPut":{"key":"batch:c","value":{"Int":3}}}]}"#);
+        assert!(v["ok"][0]["Version"].is_u64(), "got: {v}");
+        strata_close(handle);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_fixed_clock_stamps_audit() {
+        let handle = open_memory_handle();
+        let ptr = strata_set_audit(handle, 8);
+        unsafe { strata_free_string(ptr) };
+
+        strata_set_clock_fixed(1_700_000_000_000);
+        execute_json(handle, r#"{"KvPut":{"key":"clock","value":{"Int":1}}}"#);
+        strata_set_clock_fixed(-1);
+        execute_json(handle, r#"{"KvPut":{"key":"clock","value":{"Int":2}}}"#);
+
+        let ptr = strata_get_audit(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(v["ok"][0]["timestamp"], 1_700_000_000_000u64, "got: {v}");
+        assert!(v["ok"][1]["timestamp"].as_u64().unwrap() > 1_700_000_000_000);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_handle_last_error() {
+        let handle = open_memory_handle();
+        let last_error = move || {
+            let ptr = strata_handle_last_error(handle);
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+        assert!(last_error()["ok"].is_null());
+
+        let command = CString::new(r#"{"VectorCollectionInfo":{"collection":"absent"}}"#).unwrap();
+        let ptr = strata_execute(handle, command.as_ptr());
+        unsafe { strata_free_string(ptr) };
+
+        // Read from another thread: the slot belongs to the handle.
+        let v = std::thread::spawn(last_error).join().unwrap();
+        assert_eq!(v["ok"]["command"], "VectorCollectionInfo", "got: {v}");
+        assert!(v["ok"]["error"]["NotFound"].is_object(), "got: {v}");
+
+        // Later successes leave it in place.
+        execute_json(handle, r#"{"KvPut":{"key":"k","value":{"Int":1}}}"#);
+        assert_eq!(last_error()["ok"]["command"], "VectorCollectionInfo");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_compact_command_form() {
+        let handle = open_memory_handle();
+        let put = execute_json(handle, r#"["KvPut","compact:a",{"Int":1}]"#);
+        assert!(put["Version"].is_u64(), "got: {put}");
+        execute_json(handle, r#"{"KvPut":{"key":"compact:b","value":{"Int":1}}}"#);
+
+        for key in ["compact:a", "compact:b"] {
+            let compact = execute_json(handle, &format!(r#"["KvGet","{key}"]"#));
+            let object = execute_json(handle, &format!(r#"{{"KvGet":{{"key":"{key}"}}}}"#));
+            assert_eq!(compact, object);
+            assert_eq!(compact["MaybeVersioned"]["value"]["Int"], 1);
+        }
+
+        let v = execute_json(handle, r#"["KvGet","compact:a","extra"]"#);
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+        let v = execute_json(handle, r#"["VectorSearch","c"]"#);
+        assert_eq!(v["error"]["InvalidInput"]["command"], "VectorSearch", "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_health_lists_faulted_handles() {
+        let health = || {
+            let ptr = strata_health();
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+        let handle = open_memory_handle();
+        let listed = |v: &serde_json::Value| {
+            v["faulted_handles"].as_array().unwrap().iter().any(|f| f["handle"] == handle)
+        };
+        assert!(!listed(&health()));
+
+        let _ = REGISTRY.run_guarded(handle, |_| -> Result<(), BridgeError> {
+            panic!("simulated health panic")
+        });
+        let v = health();
+        assert!(listed(&v), "got: {v}");
+        assert_eq!(v["ok"], false);
+        assert!(v["handles"].as_u64().unwrap() >= 1);
+        assert!(v["worker_threads"].is_u64());
+        assert!(v["last_panic"].is_string());
+
+        strata_close(handle);
+        assert!(!listed(&health()));
+    }
+
+    #[test]
+    fn test_max_concurrent_commands() {
+        let config = CString::new(r#"{"max_concurrent_commands":1,"block":false}"#).unwrap();
+        let ptr = strata_open_temp(config.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let handle = v["ok"]["handle"].as_u64().expect("expected ok with handle");
+
+        let ptr = strata_get_config(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(v["ok"]["max_concurrent_commands"], 1);
+        assert_eq!(v["ok"]["block"], false);
+
+        // Within the limit, commands run one after another.
+        assert!(execute_json(handle, r#"{"Ping":null}"#)["Pong"].is_object());
+        assert!(execute_json(handle, r#"{"Ping":null}"#)["Pong"].is_object());
+
+        // Hold the only slot from another thread.
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let holder = std::thread::spawn(move || {
+            REGISTRY.run_timed(handle, r#"{"Ping":null}"#, |_| {
+                started_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+                Ok(())
+            })
+        });
+        started_rx.recv().unwrap();
+        let v = execute_json(handle, r#"{"Ping":null}"#);
+        assert_eq!(v["error"]["Busy"]["max_concurrent_commands"], 1, "got: {v}");
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap().unwrap();
+        assert!(execute_json(handle, r#"{"Ping":null}"#)["Pong"].is_object());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_state_coalesce() {
+        let handle = open_memory_handle();
+        let cell = CString::new("pipeline:progress").unwrap();
+        let ptr = strata_set_state_coalesce(handle, cell.as_ptr(), 50);
+        unsafe { strata_free_string(ptr) };
+
+        let set = |handle, i| {
+            let body = serde_json::json!({ "cell": "pipeline:progress", "value": { "Int": i } });
+            let set = serde_json::json!({ "StateSet": body });
+            assert!(execute_json(handle, &set.to_string()).get("error").is_none());
+        };
+        for i in 0..100 {
+            set(handle, i);
+        }
+        // Reads see the latest set, written or not.
+        let v = execute_json(handle, r#"{"StateGet":{"cell":"pipeline:progress"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 99, "got: {v}");
+
+        // Other state commands see held values written first.
+        let v = execute_json(handle, r#"{"StateGetv":{"cell":"pipeline:progress"}}"#);
+        let history = v["VersionHistory"].as_array().expect("expected a version history");
+        assert!(history.len() < 100, "expected fewer writes than sets, got {}", history.len());
+        assert_eq!(history[0]["value"]["Int"], 99);
+
+        let ptr = strata_stats(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let stats = &v["ok"]["coalesced"]["pipeline:progress"];
+        assert_eq!(stats["sets"], 100, "got: {v}");
+        assert_eq!(stats["writes"], history.len());
+        strata_close(handle);
+
+        // Closing writes the value still held.
+        let name = format!("strata-coalesce-{}.strata", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        let path_c = CString::new(dir.to_str().unwrap()).unwrap();
+        let open = || {
+            let ptr = strata_open(path_c.as_ptr(), std::ptr::null());
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v["ok"].as_u64().unwrap()
+        };
+        let handle = open();
+        let ptr = strata_set_state_coalesce(handle, cell.as_ptr(), 60_000);
+        unsafe { strata_free_string(ptr) };
+        // Bridge commands, exports and snapshots see held values written first.
+        set(handle, 5);
+        let v = execute_json(handle, r#"{"MultiGet":{"state":["pipeline:progress"]}}"#);
+        assert_eq!(v["MultiGet"]["state"]["pipeline:progress"]["value"]["Int"], 5, "got: {v}");
+        set(handle, 6);
+        let out = dir.with_extension("state.json");
+        let out_c = CString::new(out.to_str().unwrap()).unwrap();
+        let state = CString::new("state").unwrap();
+        let ptr = strata_export_primitive(handle, state.as_ptr(), out_c.as_ptr());
+        unsafe { strata_free_string(ptr) };
+        let exported = std::fs::read_to_string(&out).unwrap();
+        let _ = std::fs::remove_file(&out);
+        let exported: serde_json::Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(exported["pipeline:progress"]["Int"], 6, "got: {exported}");
+        set(handle, 7);
+        let ptr = strata_snapshot_begin(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let snapshot = v["ok"].as_u64().unwrap();
+        let v = execute_json(snapshot, r#"{"StateGet":{"cell":"pipeline:progress"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 7, "got: {v}");
+        unsafe { strata_free_string(strata_snapshot_end(snapshot)) };
+        set(handle, 7);
+        strata_close(handle);
+        let handle = open();
+        let v = execute_json(handle, r#"{"StateGet":{"cell":"pipeline:progress"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 7, "got: {v}");
+        strata_close(handle);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_handles_for_path() {
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let name = format!("strata-handles-for-path-{}.strata", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&dir);
+        let path_c = CString::new(dir.to_str().unwrap()).unwrap();
+        let first = read(strata_open(path_c.as_ptr(), std::ptr::null()))["ok"].as_u64().unwrap();
+        let second = read(strata_open(path_c.as_ptr(), std::ptr::null()))["ok"].as_u64().unwrap();
+        let other = open_memory_handle();
+
+        // A non-canonical spelling of the same path matches too.
+        let dotted = dir.parent().unwrap().join(".").join(dir.file_name().unwrap());
+        let dotted = CString::new(dotted.to_str().unwrap()).unwrap();
+        for path in [&path_c, &dotted] {
+            let v = read(strata_handles_for_path(path.as_ptr()));
+            assert_eq!(v["ok"], serde_json::json!([first, second]), "got: {v}");
+        }
+
+        strata_close(first);
+        let v = read(strata_handles_for_path(path_c.as_ptr()));
+        assert_eq!(v["ok"], serde_json::json!([second]), "got: {v}");
+        for handle in [second, other] {
+            strata_close(handle);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_disk_free() {
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let v = read(strata_open_temp(std::ptr::null()));
+        let handle = v["ok"]["handle"].as_u64().unwrap();
+        let path = v["ok"]["path"].as_str().unwrap().to_string();
+        let v = read(strata_disk_free(handle));
+        assert!(v["ok"]["free_bytes"].as_u64().unwrap() > 0, "got: {v}");
+        let mount = v["ok"]["mount"].as_str().unwrap();
+        let path = std::path::Path::new(&path).canonicalize().unwrap();
+        assert!(path.starts_with(mount), "{mount} should hold {}", path.display());
+        strata_close(handle);
+
+        let handle = open_memory_handle();
+        assert_eq!(read(strata_disk_free(handle))["ok"], serde_json::json!({ "memory": true }));
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_stats_latency_histogram() {
+        let handle = open_memory_handle();
+        let latency = |handle| {
+            let ptr = strata_stats_latency(handle);
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v["ok"].clone()
+        };
+
+        for _ in 0..20 {
+            assert!(execute_json(handle, r#"{"Ping":null}"#)["Pong"].is_object());
+        }
+        let ping = &latency(handle)["Ping"];
+        assert_eq!(ping["count"], 20, "got: {ping}");
+        let p50 = ping["p50_us"].as_u64().unwrap();
+        let p99 = ping["p99_us"].as_u64().unwrap();
+        assert!(p50 < 1_000_000, "a Ping should not take a second: {ping}");
+        assert!(p50 <= ping["p95_us"].as_u64().unwrap() && p99 <= ping["max_us"].as_u64().unwrap());
+
+        let ptr = strata_stats_reset(handle);
+        unsafe { strata_free_string(ptr) };
+        assert_eq!(latency(handle), serde_json::json!({}));
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_execute_gzip() {
+        use std::io::Write;
+
+        fn gzip(data: &str) -> Vec<u8> {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        }
+        let execute_gzip = |handle, data: &[u8]| {
+            let ptr = strata_execute_gzip(handle, data.as_ptr(), data.len());
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+
+        let handle = open_memory_handle();
+        let entries: Vec<_> = (0..100)
+            .map(|i| serde_json::json!({ "key": format!("bulk:{i}"), "value": { "Int": i } }))
+            .collect();
+        let command = serde_json::json!({ "KvBatchPut": { "entries": entries } }).to_string();
+        let v = execute_gzip(handle, &gzip(&command));
+        assert!(v.get("error").is_none(), "got: {v}");
+        let v = execute_json(handle, r#"{"KvGet":{"key":"bulk:42"}}"#);
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 42, "got: {v}");
+
+        let v = execute_gzip(handle, b"not gzip");
+        assert!(v["error"]["InvalidInput"]["reason"].is_string(), "got: {v}");
+
+        // Snapshot IDs are routed as by strata_execute.
+        let ptr = strata_snapshot_begin(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let snapshot = v["ok"].as_u64().unwrap();
+        execute_json(handle, r#"{"KvPut":{"key":"bulk:42","value":{"Int":-1}}}"#);
+        let v = execute_gzip(snapshot, &gzip(r#"{"KvGet":{"key":"bulk:42"}}"#));
+        assert_eq!(v["MaybeVersioned"]["value"]["Int"], 42, "got: {v}");
+        let v = execute_gzip(snapshot, &gzip(r#"{"KvDelete":{"key":"bulk:42"}}"#));
+        assert!(v["error"]["SnapshotReadOnly"].is_object(), "got: {v}");
+        unsafe { strata_free_string(strata_snapshot_end(snapshot)) };
+        strata_close(handle);
+
+        // The decompressed size is held to max_command_bytes.
+        let config = CString::new(r#"{"max_command_bytes":64}"#).unwrap();
+        let ptr = strata_open_memory_with_config(config.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let handle = v["ok"].as_u64().unwrap();
+        let v = execute_gzip(handle, &gzip(&command));
+        assert_eq!(v["error"]["CommandTooLarge"]["max_command_bytes"], 64, "got: {v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"bulk:42"}}"#)["MaybeVersioned"].is_null());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_default_config_merged_under_open_config() {
+        fn get_config(handle: u64) -> serde_json::Value {
+            let ptr = strata_get_config(handle);
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v["ok"].clone()
+        }
+        fn set_default_config(config: Option<&str>) -> serde_json::Value {
+            let config = config.map(|c| CString::new(c).unwrap());
+            let config_ptr = config.as_ref().map_or(std::ptr::null(), |c| c.as_ptr());
+            let ptr = strata_set_default_config(config_ptr);
+            let v = serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        }
+        // A default read-only flag applies unless the open overrides it. The
+        // process-wide defaults are shared with concurrently running tests,
+        // so this is checked against explicit defaults.
+        let defaults: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(r#"{"read_only":true,"block":true}"#).unwrap();
+        let merged = config::OpenConfig::parse_over(Some(defaults.clone()), None).unwrap();
+        assert!(merged.read_only && merged.block);
+        let open = Some(r#"{"read_only":false}"#);
+        let merged = config::OpenConfig::parse_over(Some(defaults), open).unwrap();
+        assert!(!merged.read_only && merged.block, "other defaults still apply");
+
+        let config = CString::new(r#"{"read_only":true}"#).unwrap();
+        let ptr = strata_open_memory_with_config(config.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let read_only = v["ok"].as_u64().unwrap();
+        let v = execute_json(read_only, r#"{"KvPut":{"key":"k","value":{"Int":1}}}"#);
+        assert_eq!(v["error"]["ReadOnly"]["command"], "KvPut", "got: {v}");
+        let v = execute_json(read_only, r#"{"KvGet":{"key":"k"}}"#);
+        assert!(v.get("error").is_none(), "reads still run, got: {v}");
+        strata_close(read_only);
+
+        // End to end, `block` is the default set: it changes nothing for the
+        // other tests' handles, which set no concurrency limit.
+        let v = set_default_config(Some(r#"{"block":"yes"}"#));
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+        assert_eq!(set_default_config(Some(r#"{"block":true}"#))["ok"], serde_json::Value::Null);
+
+        let defaulted = open_memory_handle();
+        assert_eq!(get_config(defaulted)["block"], true);
+
+        let config = CString::new(r#"{"block":false,"max_concurrent_commands":2}"#).unwrap();
+        let ptr = strata_open_memory_with_config(config.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        let overridden = v["ok"].as_u64().unwrap();
+        let config = get_config(overridden);
+        assert_eq!(config["block"], false, "got: {config}");
+        assert_eq!(config["max_concurrent_commands"], 2);
+
+        set_default_config(None);
+        let cleared = open_memory_handle();
+        assert_eq!(get_config(cleared)["block"], false);
+        // Existing handles keep the options they were opened with.
+        assert_eq!(get_config(defaulted)["block"], true);
+        for handle in [defaulted, overridden, cleared] {
+            strata_close(handle);
+        }
+    }
+
+    #[test]
+    fn test_vector_reindex_after_deletes() {
+        let handle = open_memory_handle();
+        let vectors = [("a", [1.0, 0.0]), ("b", [0.9, 0.1]), ("c", [0.0, 1.0]), ("d", [0.1, 0.9])];
+        for (key, vector) in vectors {
+            execute_json(
+                handle,
+                &serde_json::json!({ "VectorUpsert": {
+                    "collection": "reindex", "key": key, "vector": vector, "metadata": { "key": key },
+                }})
+                .to_string(),
+            );
+        }
+        execute_json(handle, r#"{"VectorDelete":{"collection":"reindex","key":"a"}}"#);
+        execute_json(handle, r#"{"VectorDelete":{"collection":"reindex","key":"c"}}"#);
+
+        let v = execute_json(handle, r#"{"VectorReindex":{"collection":"reindex"}}"#);
+        assert_eq!(v["VectorReindex"]["vectors"], 2, "got: {v}");
+        assert!(v["VectorReindex"]["elapsed_ms"].is_u64());
+
+        let search = |query: [f32; 2]| {
+            let command = serde_json::json!({ "VectorSearch": {
+                "collection": "reindex", "query": query, "k": 1,
+            }});
+            execute_json(handle, &command.to_string())["VectorMatches"][0].clone()
+        };
+        assert_eq!(search([1.0, 0.0])["key"], "b");
+        assert_eq!(search([0.0, 1.0])["key"], "d");
+        assert_eq!(search([0.0, 1.0])["metadata"]["key"], "d");
+
+        let v = execute_json(handle, r#"{"VectorReindex":{"collection":"absent"}}"#);
+        assert_eq!(v["error"]["NotFound"]["collection"], "absent", "got: {v}");
+        strata_close(handle);
+    }
+
+    fn open_capped_handle(eviction: &str) -> u64 {
+        let config = format!(r#"{{"max_bytes":60,"eviction":"{eviction}"}}"#);
+        let config = CString::new(config).unwrap();
+        let ptr = strata_open_memory_with_config(config.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        v["ok"].as_u64().expect("expected ok with handle id")
+    }
+
+    fn cache_stats(handle: u64) -> serde_json::Value {
+        let ptr = strata_stats(handle);
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        v["ok"]["cache"].clone()
+    }
+
+    #[test]
+    fn test_open_memory_with_capacity_bulk_insert() {
+        let config = CString::new(r#"{"max_bytes":1000000}"#).unwrap();
+        for config in [std::ptr::null(), config.as_ptr()] {
+            let ptr = strata_open_memory_with_capacity(10_000, config);
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            let handle = v["ok"].as_u64().expect("expected ok with handle id");
+
+            let entries: Vec<serde_json::Value> = (0..1000)
+                .map(|i| serde_json::json!({ "key": format!("bulk:{i:04}"), "value": { "Int": i } }))
+                .collect();
+            let batch = serde_json::json!({ "KvBatchPut": { "entries": entries } });
+            let v = execute_json(handle, &batch.to_string());
+            assert_eq!(v["BatchResults"].as_array().map(Vec::len), Some(1000), "got: {v}");
+            let v = execute_json(handle, r#"{"KvGet":{"key":"bulk:0999"}}"#);
+            assert_eq!(v["MaybeVersioned"]["value"]["Int"], 999);
+            strata_close(handle);
+        }
+    }
+
+    #[test]
+    fn test_memory_cap_evicts_lru() {
+        let _serial = CACHE_TESTS.lock().unwrap_or_else(|e| e.into_inner());
+        // Each entry is 2 key bytes + 17 value bytes (`{"String":"xxxx"}`).
+        let handle = open_capped_handle("lru");
+        let put = |key: &str| {
+            let cmd = format!(r#"{{"KvPut":{{"key":"{key}","value":{{"String":"xxxx"}}}}}}"#);
+            execute_json(handle, &cmd)
+        };
+        for key in ["k1", "k2", "k3"] {
+            assert!(put(key)["Version"].is_u64());
+        }
+        assert_eq!(cache_stats(handle)["used_bytes"], 57);
+
+        // Reading k1 makes k2 the least recently used.
+        execute_json(handle, r#"{"KvGet":{"key":"k1"}}"#);
+        assert!(put("k4")["Version"].is_u64());
+
+        let stats = cache_stats(handle);
+        assert_eq!(stats["evictions"], 1, "got: {stats}");
+        assert_eq!(stats["used_bytes"], 57);
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"k2"}}"#)["MaybeVersioned"].is_null());
+        for key in ["k1", "k3", "k4"] {
+            let v = execute_json(handle, &format!(r#"{{"KvGet":{{"key":"{key}"}}}}"#));
+            assert!(v["MaybeVersioned"].is_object(), "{key} was evicted");
+        }
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_memory_cap_rejects_when_full() {
+        let handle = open_capped_handle("reject");
+        let put = |key: &str| {
+            let cmd = format!(r#"{{"KvPut":{{"key":"{key}","value":{{"String":"xxxx"}}}}}}"#);
+            execute_json(handle, &cmd)
+        };
+        for key in ["k1", "k2", "k3"] {
+            assert!(put(key)["Version"].is_u64());
+        }
+        let v = put("k4");
+        assert_eq!(v["error"]["CacheFull"]["max_bytes"], 60, "got: {v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"k4"}}"#)["MaybeVersioned"].is_null());
+
+        // Raw byte puts and copies from another handle count against the cap too.
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        let key = CString::new("kb").unwrap();
+        let v = read(strata_kv_put_bytes(handle, key.as_ptr(), b"blob".as_ptr(), 4));
+        assert_eq!(v["error"]["CacheFull"]["requested_bytes"], 6, "got: {v}");
+        let src = open_memory_handle();
+        execute_json(src, r#"{"KvPut":{"key":"kc","value":{"String":"xxxx"}}}"#);
+        let kc = CString::new("kc").unwrap();
+        let v = read(strata_copy_kv(src, handle, kc.as_ptr(), kc.as_ptr()));
+        assert!(v["error"]["CacheFull"].is_object(), "got: {v}");
+        strata_close(src);
+        assert_eq!(cache_stats(handle)["used_bytes"], 57);
+
+        // Overwriting an existing key at the same size still fits, and
+        // deleting frees room.
+        assert!(put("k1")["Version"].is_u64());
+        execute_json(handle, r#"{"KvDelete":{"key":"k2"}}"#);
+        assert!(put("k4")["Version"].is_u64());
+        assert_eq!(cache_stats(handle)["evictions"], 0);
+        strata_close(handle);
+
+        // The cap is for in-memory handles only.
+        let config = CString::new(r#"{"max_bytes":60}"#).unwrap();
+        let ptr = strata_open_temp(config.as_ptr());
+        let v: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { strata_free_string(ptr) };
+        assert!(v["error"]["InvalidInput"].is_object(), "got: {v}");
+    }
+
+    #[test]
+    fn test_memory_cap_accounts_moves_and_batches() {
+        let handle = open_capped_handle("reject");
+        let read = |ptr: *mut c_char| {
+            let v: serde_json::Value =
+                serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+            unsafe { strata_free_string(ptr) };
+            v
+        };
+        for key in ["k1", "k2"] {
+            let cmd = format!(r#"{{"KvPut":{{"key":"{key}","value":{{"String":"xxxx"}}}}}}"#);
+            execute_json(handle, &cmd);
+        }
+
+        // A rename moves the key's bytes, and one that would overflow is refused.
+        let v = execute_json(handle, r#"{"KvRename":{"from":"k1","to":"renamed"}}"#);
+        assert!(v.get("error").is_none(), "got: {v}");
+        assert_eq!(cache_stats(handle)["used_bytes"], 19 + 24);
+        assert_eq!(cache_stats(handle)["entries"], 2);
+        let rename = r#"{"KvRename":{"from":"k2","to":"a-much-longer-key-name-xx"}}"#;
+        let v = execute_json(handle, rename);
+        assert!(v["error"]["CacheFull"].is_object(), "got: {v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"k2"}}"#)["MaybeVersioned"].is_object());
+
+        // KvGetOrPut counts its default when it creates the key.
+        let get_or_put = |key: &str| {
+            let default = r#"{"String":"xxxxxxxxxxxxxxxx"}"#;
+            let cmd = format!(r#"{{"KvGetOrPut":{{"key":"{key}","default":{default}}}}}"#);
+            execute_json(handle, &cmd)
+        };
+        assert_eq!(get_or_put("k2")["KvGetOrPut"]["created"], false);
+        let v = get_or_put("g1");
+        assert!(v["error"]["CacheFull"].is_object(), "got: {v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"g1"}}"#)["MaybeVersioned"].is_null());
+
+        // An atomic batch is refused as a whole, and accounted once applied.
+        let batch = |keys: &[&str]| {
+            let commands: Vec<_> = keys
+                .iter()
+                .map(|key| serde_json::json!({ "KvPut": { "key": key, "value": { "Int": 1 } } }))
+                .collect();
+            let batch = serde_json::json!({ "commands": commands, "atomic": true });
+            let batch = CString::new(batch.to_string()).unwrap();
+            read(strata_execute_batch(handle, batch.as_ptr()))
+        };
+        let v = batch(&["b1", "b2"]);
+        assert!(v["error"]["CacheFull"].is_object(), "got: {v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"b1"}}"#)["MaybeVersioned"].is_null());
+        execute_json(handle, r#"{"KvDelete":{"key":"renamed"}}"#);
+        let v = batch(&["b1", "b2"]);
+        assert!(v["ok"].is_array(), "got: {v}");
+        assert_eq!(cache_stats(handle)["used_bytes"], 19 + 2 * 11);
+
+        // So is a committed transaction.
+        let txn = read(strata_txn_begin(handle, std::ptr::null()))["ok"].as_u64().unwrap();
+        let put = CString::new(r#"{"KvPut":{"key":"t1","value":{"String":"xxxxxxxx"}}}"#).unwrap();
+        read(strata_txn_execute(txn, put.as_ptr()));
+        let v = read(strata_txn_commit(txn));
+        assert!(v["error"]["CacheFull"].is_object(), "got: {v}");
+        assert!(execute_json(handle, r#"{"KvGet":{"key":"t1"}}"#)["MaybeVersioned"].is_null());
+        assert_eq!(cache_stats(handle)["used_bytes"], 19 + 2 * 11);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_verify_on_open_healthy() {
+        let dir = std::env::temp_dir().join(format!("strata-verify-{}.strata", std::process::id()));
+        let path = CString::new(dir.to_str().unwrap()).unwrap();
+        let config = CString::new(r#"{"verify_on_open":true}"#).unwrap();
+        let open = || {
+            let ptr = strata_open(path.as_ptr(), config.as_ptr());
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+
+        let v = open();
+        let handle = v["ok"]["handle"].as_u64().expect("expected ok with handle");
+        execute_json(handle, r#"{"KvPut":{"key":"k","value":{"Int":1}}}"#);
+        execute_json(handle, r#"{"EventAppend":{"event_type":"e","payload":{"Int":1}}}"#);
+        strata_close(handle);
+
+        let v = open();
+        assert_eq!(v["ok"]["verify"]["ok"], true, "got: {v}");
+        assert_eq!(v["ok"]["verify"]["issues"], serde_json::json!([]));
+        strata_close(v["ok"]["handle"].as_u64().unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_backup_on_open_keeps_retention() {
+        let dir = std::env::temp_dir().join(format!("strata-backup-{}.strata", std::process::id()));
+        let path = CString::new(dir.to_str().unwrap()).unwrap();
+        let config = CString::new(r#"{"backup_on_open":1}"#).unwrap();
+        let open = || {
+            let ptr = strata_open(path.as_ptr(), config.as_ptr());
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            let v: serde_json::Value = serde_json::from_str(&result).unwrap();
+            let handle = v["ok"]["handle"].as_u64().expect("expected ok with handle");
+            (handle, v["ok"]["backup"].clone())
+        };
+
+        // Nothing to back up before the database exists.
+        let (handle, backup) = open();
+        assert!(backup.is_null());
+        execute_json(handle, r#"{"KvPut":{"key":"k","value":{"Int":1}}}"#);
+        strata_close(handle);
+
+        for _ in 0..2 {
+            let (handle, backup) = open();
+            assert!(std::path::Path::new(backup.as_str().unwrap()).is_dir());
+            strata_close(handle);
+        }
+        let backups = backup::list(&dir).unwrap();
+        assert_eq!(backups.len(), 1, "got: {backups:?}");
+
+        for path in backups.iter().chain([&dir]) {
+            let _ = std::fs::remove_dir_all(path);
+        }
+    }
+
+    #[test]
+    fn test_multi_get_sections() {
+        let handle = open_sample_handle();
+        let v = execute_json(
+            handle,
+            r#"{"MultiGet":{"kv":["config:app_version","config:missing"],"state":["agent:status"],"events":{"limit":5,"reverse":true}}}"#,
+        );
+        let result = &v["MultiGet"];
+        assert_eq!(result["kv"]["config:app_version"]["value"]["String"], "2.1.0", "got: {v}");
+        assert!(result["kv"]["config:missing"].is_null());
+        assert_eq!(result["state"]["agent:status"]["value"]["String"], "idle");
+
+        let events = result["events"].as_array().unwrap();
+        let sequences: Vec<u64> = events.iter().map(|e| e["sequence"].as_u64().unwrap()).collect();
+        assert_eq!(sequences, vec![19, 18, 17, 16, 15]);
+        assert!(events[0]["event_type"].is_string());
+
+        // Unrequested sections are left out.
+        let v = execute_json(handle, r#"{"MultiGet":{"kv":["user:alice"]}}"#);
+        assert!(v["MultiGet"].get("state").is_none() && v["MultiGet"].get("events").is_none());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_event_stats_counts_kinds() {
+        let handle = open_sample_handle();
+        let v = execute_json(handle, r#"{"EventStats":{}}"#);
+        let stats = &v["EventStats"];
+        assert_eq!(stats["total"], 20, "got: {v}");
+        assert_eq!(stats["min_sequence"], 0);
+        assert_eq!(stats["max_sequence"], 19);
+        let kinds = stats["kinds"].as_array().unwrap();
+        let tool_calls = kinds.iter().find(|k| k["kind"] == "tool_call").unwrap();
+        assert_eq!(tool_calls["count"], 8);
+        let sum: u64 = kinds.iter().map(|k| k["count"].as_u64().unwrap()).sum();
+        assert_eq!(sum, 20);
+        strata_close(handle);
+
+        let handle = open_memory_handle();
+        let v = execute_json(handle, r#"{"EventStats":null}"#);
+        assert_eq!(v["EventStats"]["total"], 0);
+        assert!(v["EventStats"]["min_sequence"].is_null());
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_event_kinds_distinct_sorted() {
+        let handle = open_sample_handle();
+        let v = execute_json(handle, r#"{"EventKinds":{}}"#);
+        let kinds = v["EventKinds"].as_array().expect("expected a list of kinds");
+        assert_eq!(
+            *kinds,
+            ["auth", "decision", "error", "observation", "system", "tool_call"]
+                .map(serde_json::Value::from)
+                .to_vec(),
+        );
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_event_last_by_kind() {
+        let handle = open_sample_handle();
+        let v = execute_json(handle, r#"{"EventLast":{"kind":"error"}}"#);
+        let event = &v["EventLast"];
+        assert_eq!(event["sequence"], 9, "got: {v}");
+        assert_eq!(event["event_type"], "error");
+        assert_eq!(event["value"]["Object"]["code"]["Int"], 429);
+
+        let v = execute_json(handle, r#"{"EventLast":{}}"#);
+        assert_eq!(v["EventLast"]["sequence"], 19);
+        assert_eq!(v["EventLast"]["event_type"], "system");
+
+        let v = execute_json(handle, r#"{"EventLast":{"kind":"absent"}}"#);
+        assert!(v["EventLast"].is_null(), "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_fields_projection() {
+        let handle = open_sample_handle();
+        let v = execute_json(handle, r#"{"KvGet":{"key":"user:alice","fields":["name","role"]}}"#);
+        let user = &v["MaybeVersioned"]["value"]["Object"];
+        assert_eq!(user["name"]["String"], "Alice Chen", "got: {v}");
+        assert_eq!(user["role"]["String"], "admin");
+        assert!(user.get("email").is_none());
+        assert_eq!(user.as_object().unwrap().len(), 2);
+
+        // Projections reach values nested in bridge command outputs too.
+        let v = execute_json(
+            handle,
+            r#"{"MultiGet":{"kv":["user:alice","user:bob"],"fields":["role","nickname"]}}"#,
+        );
+        for user in ["user:alice", "user:bob"] {
+            let fields = &v["MultiGet"]["kv"][user]["value"]["Object"];
+            assert!(fields["role"].is_object(), "got: {v}");
+            assert!(fields.get("email").is_none());
+            assert!(fields.get("nickname").is_none());
+        }
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_deterministic_iteration_sorts_lists() {
+        let handle = open_sample_handle();
+        let ptr = strata_set_deterministic_iteration(handle, true);
+        let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { strata_free_string(ptr) };
+        assert!(result.contains("\"ok\""), "{result}");
+
+        let first = execute_json(handle, r#"{"KvList":{}}"#);
+        let second = execute_json(handle, r#"{"KvList":{}}"#);
+        assert_eq!(first, second);
+
+        let keys: Vec<&str> =
+            first["Keys"].as_array().unwrap().iter().map(|k| k.as_str().unwrap()).collect();
+        assert_eq!(keys.len(), 14);
+        assert!(keys.windows(2).all(|w| w[0] <= w[1]), "{keys:?}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_vector_collection_info() {
+        let handle = open_memory_handle();
+        execute_json(
+            handle,
+            r#"{"VectorCreateCollection":{"collection":"embeddings","dimension":4,"metric":"cosine"}}"#,
+        );
+        for i in 0..5 {
+            let vector = [i as f32, 1.0, 0.0, 0.0];
+            let cmd = serde_json::json!({"VectorUpsert": {"collection": "embeddings", "key": format!("e{i}"), "vector": vector}});
+            execute_json(handle, &cmd.to_string());
+        }
+
+        let v = execute_json(handle, r#"{"VectorCollectionInfo":{"collection":"embeddings"}}"#);
+        assert_eq!(
+            v["VectorCollectionInfo"],
+            serde_json::json!({"dimension": 4, "count": 5, "metric": "cosine"}),
+            "got: {v}"
+        );
+
+        let v = execute_json(handle, r#"{"VectorCollectionInfo":{"collection":"missing"}}"#);
+        assert_eq!(v["error"]["NotFound"]["collection"], "missing", "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_vector_create_collection() {
+        let handle = open_memory_handle();
+        let create = r#"{"VectorCreateCollection":{"name":"docs","dimension":3,"metric":"cosine"}}"#;
+        let v = execute_json(handle, create);
+        assert!(v["Version"].is_u64(), "got: {v}");
+
+        let v = execute_json(handle, r#"{"VectorCollectionInfo":{"collection":"docs"}}"#);
+        assert_eq!(v["VectorCollectionInfo"]["dimension"], 3);
+
+        // Upserts are validated against the declared dimension.
+        let v = execute_json(
+            handle,
+            r#"{"VectorUpsert":{"collection":"docs","key":"a","vector":[1.0,0.0]}}"#,
+        );
+        let reason = v["error"]["Internal"]["reason"].as_str().unwrap_or_default();
+        assert!(reason.contains("DimensionMismatch"), "got: {v}");
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_vector_create_collection_recreate() {
+        let handle = open_memory_handle();
+        let create = r#"{"VectorCreateCollection":{"collection":"docs","dimension":3,"metric":"cosine"}}"#;
+        execute_json(handle, create);
+        execute_json(
+            handle,
+            r#"{"VectorUpsert":{"collection":"docs","key":"a","vector":[1.0,0.0,0.0]}}"#,
+        );
+
+        // Same spec: a no-op that keeps the existing vectors.
+        let v = execute_json(handle, create);
+        assert_eq!(v["Version"], 0, "got: {v}");
+        let v = execute_json(handle, r#"{"VectorCollectionInfo":{"collection":"docs"}}"#);
+        assert_eq!(v["VectorCollectionInfo"]["count"], 1);
+
+        let v = execute_json(
+            handle,
+            r#"{"VectorCreateCollection":{"collection":"docs","dimension":1536,"metric":"cosine"}}"#,
+        );
+        let conflict = &v["error"]["CollectionConflict"];
+        assert_eq!(conflict["existing"]["dimension"], 3, "got: {v}");
+        assert_eq!(conflict["requested"]["dimension"], 1536);
+        strata_close(handle);
+    }
+
+    #[test]
+    fn test_explain_vector_search_reports_index() {
+        let handle = open_memory_handle();
+        execute_json(
+            handle,
+            r#"{"VectorCreateCollection":{"collection":"docs","dimension":3,"metric":"cosine"}}"#,
+        );
+        for (i, v) in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]].iter().enumerate() {
+            execute_json(
+                handle,
+                &serde_json::json!({"VectorUpsert": {"collection": "docs", "key": format!("v{i}"), "vector": v}})
+                    .to_string(),
+            );
+        }
+
+        let explain = |cmd: &str| {
+            let cmd = CString::new(cmd).unwrap();
+            let ptr = strata_explain(handle, cmd.as_ptr());
+            let result = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { strata_free_string(ptr) };
+            serde_json::from_str::<serde_json::Value>(&result).unwrap()
+        };
+
+        let plan = explain(r#"{"VectorSearch":{"collection":"docs","query":[1.0,0.0,0.0],"k":10}}"#);
+        assert_eq!(plan["ok"]["command"], "VectorSearch");
+        assert_eq!(plan["ok"]["uses_index"], true, "{plan}");
+        assert_eq!(plan["ok"]["estimated_rows"], 3);
+
+        // Commands without a plan get a minimal descriptor.
+        let plan = explain(r#"{"Ping":null}"#);
+        assert_eq!(plan["ok"]["command"], "Ping");
+        assert!(plan["ok"]["uses_index"].is_null());
+        strata_close(handle);
     }
 
     /// Debug: open the sample DB and print actual JSON responses.
@@ -201,7 +5586,7 @@ mod tests {
         println!("strata_open => {result}");
 
         let v: serde_json::Value = serde_json::from_str(&result).unwrap();
-        let handle_id = v["ok"].as_u64().expect(&format!("expected ok, got: {result}"));
+        let handle_id = v["ok"].as_u64().unwrap_or_else(|| panic!("expected ok, got: {result}"));
 
         let commands = [
             ("Info", r#"{"Info":null}"#),
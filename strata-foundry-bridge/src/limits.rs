@@ -0,0 +1,120 @@
+//! Per-handle key and value size limits, enforced before writes reach stratadb.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_json::{json, Value};
+
+use crate::error::BridgeError;
+
+/// Tags of commands that write keys or values.
+const WRITE_TAGS: &[&str] = &[
+    "KvPut",
+    "KvBatchPut",
+    "KvRename",
+    "KvGetOrPut",
+    "KvUpdate",
+    "JsonSet",
+    "JsonBatchSet",
+    "JsonMerge",
+    "JsonRename",
+    "EventAppend",
+    "EventBatchAppend",
+    "StateSet",
+    "StateInit",
+    "StateCas",
+    "StateBatchSet",
+    "VectorUpsert",
+    "VectorBatchUpsert",
+];
+
+/// Fields naming the key being written.
+const KEY_FIELDS: &[&str] = &["key", "cell", "to"];
+/// Fields carrying the value being written.
+const VALUE_FIELDS: &[&str] = &["value", "payload", "metadata", "default", "upsert"];
+/// Fields listing patch ops (`KvUpdate`), each writing its own `value`.
+const OPS_FIELD: &str = "ops";
+
+/// A handle's limits. Zero means unlimited.
+#[derive(Default)]
+pub struct ValueLimits {
+    max_key_bytes: AtomicU64,
+    max_value_bytes: AtomicU64,
+}
+
+impl ValueLimits {
+    pub fn set(&self, max_key_bytes: u64, max_value_bytes: u64) {
+        self.max_key_bytes.store(max_key_bytes, Ordering::Relaxed);
+        self.max_value_bytes.store(max_value_bytes, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Limits {
+        Limits {
+            max_key_bytes: self.max_key_bytes.load(Ordering::Relaxed),
+            max_value_bytes: self.max_value_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A point-in-time copy of a handle's limits.
+#[derive(Clone, Copy, Default)]
+pub struct Limits {
+    pub max_key_bytes: u64,
+    pub max_value_bytes: u64,
+}
+
+impl Limits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_key_bytes == 0 && self.max_value_bytes == 0
+    }
+
+    /// Check a parsed `{"Tag": {...}}` command, including each entry of a batch.
+    ///
+    /// A value's size is the length of its JSON encoding as sent; a patch's
+    /// is the total of the values its ops write.
+    pub fn check_command(&self, command: &Value) -> Result<(), BridgeError> {
+        let Some((tag, body)) = command.as_object().and_then(|m| m.iter().next()) else {
+            return Ok(());
+        };
+        if !WRITE_TAGS.contains(&tag.as_str()) {
+            return Ok(());
+        }
+        self.check_fields(body)?;
+        if let Some(entries) = body.get("entries").and_then(Value::as_array) {
+            for entry in entries {
+                self.check_fields(entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_fields(&self, fields: &Value) -> Result<(), BridgeError> {
+        let key = KEY_FIELDS.iter().find_map(|f| fields.get(*f)).and_then(Value::as_str);
+        let key = key.unwrap_or_default();
+        let ops = fields.get(OPS_FIELD).and_then(Value::as_array).into_iter().flatten();
+        let value_bytes = VALUE_FIELDS
+            .iter()
+            .filter_map(|f| fields.get(*f))
+            .chain(ops.filter_map(|op| op.get("value")))
+            .map(|v| v.to_string().len() as u64)
+            .sum();
+        self.check(key, value_bytes)
+    }
+
+    /// Check a single write of `value_bytes` bytes to `key`.
+    pub fn check(&self, key: &str, value_bytes: u64) -> Result<(), BridgeError> {
+        let key_bytes = key.len() as u64;
+        if self.max_key_bytes > 0 && key_bytes > self.max_key_bytes {
+            return Err(BridgeError::Kind(
+                "KeyTooLarge",
+                json!({ "key": key, "bytes": key_bytes, "limit": self.max_key_bytes }),
+            ));
+        }
+        if self.max_value_bytes > 0 && value_bytes > self.max_value_bytes {
+            return Err(BridgeError::Kind(
+                "ValueTooLarge",
+                json!({ "key": key, "bytes": value_bytes, "limit": self.max_value_bytes }),
+            ));
+        }
+        Ok(())
+    }
+}
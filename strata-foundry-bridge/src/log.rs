@@ -0,0 +1,35 @@
+//! Bridge logging — forwards diagnostic messages to a host-registered callback.
+//!
+//! Swift registers a C function pointer with `strata_set_log_callback`; with
+//! no callback registered, messages are dropped.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::RwLock;
+
+// Levels follow the usual ordering: 0 = error, 1 = warn, 2 = info, 3 = debug.
+pub const LEVEL_WARN: i32 = 1;
+
+/// Host log sink: `level` is 0 (error) through 3 (debug), `message` is a
+/// null-terminated UTF-8 string valid only for the duration of the call.
+pub type LogCallback = extern "C" fn(level: i32, message: *const c_char);
+
+static CALLBACK: RwLock<Option<LogCallback>> = RwLock::new(None);
+
+/// Register (or clear, with `None`) the host log callback.
+pub fn set_callback(callback: Option<LogCallback>) {
+    *CALLBACK.write().unwrap_or_else(|e| e.into_inner()) = callback;
+}
+
+/// Send a message to the host callback, if one is registered.
+pub fn log(level: i32, message: &str) {
+    let callback = *CALLBACK.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(callback) = callback {
+        let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+        callback(level, message.as_ptr());
+    }
+}
+
+pub fn warn(message: &str) {
+    log(LEVEL_WARN, message);
+}
@@ -0,0 +1,209 @@
+//! Per-handle key policy (`strata_set_key_policy`).
+//!
+//! A handle given an allow and/or deny regex only runs commands whose keys
+//! and prefixes match the allow pattern and not the deny pattern, so a
+//! tenant's handle stays within its namespace. Patterns are unanchored, as
+//! with `Regex::is_match`: confine a handle to a prefix with `^tenantA:`.
+//!
+//! A prefix is checked like a key, so a scan must name a prefix the policy
+//! allows; scans without one, and reads that span every key, are refused.
+//! A prefix may still hold keys the policy refuses, such as `tenantA:secret`
+//! under a `:secret$` deny pattern: they are dropped from listings and
+//! search matches, and a summary of the prefix that would read one fails.
+
+use regex::Regex;
+use serde_json::{json, Value};
+use stratadb::Strata;
+
+use crate::commands;
+use crate::error::BridgeError;
+
+/// Fields naming a key or key prefix.
+const KEY_FIELDS: &[&str] = &["key", "prefix", "cell", "schema_key", "key_a", "key_b"];
+/// Fields naming a key only in the command given with them.
+const COMMAND_KEY_FIELDS: &[(&str, &str)] = &[("VectorSearchById", "id")];
+/// Fields listing keys (`kv` and `state` are `MultiGet`'s).
+const KEY_LIST_FIELDS: &[&str] = &["keys", "targets", "kv", "state"];
+/// Commands reading every key under `prefix`, or every key without one.
+const PREFIX_SCANS: &[&str] = &[
+    "KvList",
+    "StateList",
+    "JsonList",
+    "JsonCatalog",
+    "KvAggregate",
+    "KvSample",
+    "KvInferSchema",
+];
+/// Commands summarizing the values under `prefix`, with no keys to filter.
+const SUMMARY_SCANS: &[&str] = &["KvAggregate", "KvInferSchema"];
+/// Output tags whose payload lists keys, as strings or `{"key"}` rows
+/// (`JsonListResult` under its `keys`).
+const LISTING_OUTPUTS: &[&str] = &[
+    "Keys",
+    "JsonListResult",
+    "KvSample",
+    "JsonCatalog",
+    "VectorMatches",
+    "VectorSearchById",
+];
+/// Keys listed per page while checking a summary scan.
+const SUMMARY_PAGE_SIZE: u64 = 1000;
+/// Commands reading across every key, which no policy can confine.
+const UNSCOPED: &[&str] = &[
+    "KvNamespaces",
+    "Digest",
+    "ChangesSince",
+    "StateDiffBetween",
+    "Search",
+    "BranchDiff",
+    "BranchExport",
+];
+/// Fields naming keys only in commands that move a key (`KvRename`, `JsonRename`).
+const MOVE_FIELDS: &[&str] = &["from", "to"];
+
+pub struct KeyPolicy {
+    allow: Option<Regex>,
+    deny: Option<Regex>,
+}
+
+impl KeyPolicy {
+    /// A policy from the two patterns, `None` if both are empty.
+    pub fn new(allow: &str, deny: &str) -> Result<Option<Self>, BridgeError> {
+        let compile = |pattern: &str| -> Result<Option<Regex>, BridgeError> {
+            if pattern.is_empty() {
+                return Ok(None);
+            }
+            Regex::new(pattern).map(Some).map_err(|e| {
+                BridgeError::Kind("InvalidInput", json!({ "reason": format!("invalid regex: {e}") }))
+            })
+        };
+        let (allow, deny) = (compile(allow)?, compile(deny)?);
+        Ok((allow.is_some() || deny.is_some()).then_some(Self { allow, deny }))
+    }
+
+    /// Check one key, failing with `{"Forbidden": {"key"}}`.
+    pub fn check(&self, key: &str) -> Result<(), BridgeError> {
+        let allowed = self.allow.as_ref().is_none_or(|allow| allow.is_match(key));
+        let denied = self.deny.as_ref().is_some_and(|deny| deny.is_match(key));
+        if !allowed || denied {
+            return Err(BridgeError::Kind("Forbidden", json!({ "key": key })));
+        }
+        Ok(())
+    }
+
+    /// Check every key a parsed `{"Tag": {...}}` command names, including
+    /// each entry of a batch. Scans without a prefix and `UNSCOPED` commands
+    /// fail with `{"Forbidden": {"command"}}`.
+    pub fn check_command(&self, command: &Value) -> Result<(), BridgeError> {
+        let (tag, body) = match command {
+            Value::Object(map) => match map.iter().next() {
+                Some((tag, body)) => (tag.as_str(), body),
+                None => return Ok(()),
+            },
+            // Bare form: `"KvList"`.
+            Value::String(tag) => (tag.as_str(), &Value::Null),
+            _ => return Ok(()),
+        };
+        let prefix = body.get("prefix").and_then(Value::as_str).unwrap_or_default();
+        if UNSCOPED.contains(&tag) || (PREFIX_SCANS.contains(&tag) && prefix.is_empty()) {
+            return Err(unscoped(tag));
+        }
+        self.check_fields(body, tag.ends_with("Rename"))?;
+        let named = COMMAND_KEY_FIELDS.iter().filter(|(command, _)| *command == tag);
+        for key in named.filter_map(|(_, field)| body.get(*field)?.as_str()) {
+            self.check(key)?;
+        }
+        if let Some(entries) = body.get("entries").and_then(Value::as_array) {
+            for entry in entries {
+                self.check_fields(entry, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the keys the policy refuses from an output listing keys
+    /// (`LISTING_OUTPUTS`).
+    pub fn filter_output(&self, output: &mut Value) {
+        let Some((tag, payload)) = output.as_object_mut().and_then(|m| m.iter_mut().next()) else {
+            return;
+        };
+        let rows = match tag.as_str() {
+            "JsonListResult" => payload.get_mut("keys"),
+            tag if LISTING_OUTPUTS.contains(&tag) => Some(payload),
+            _ => None,
+        };
+        if let Some(Value::Array(rows)) = rows {
+            rows.retain(|row| self.allows_row(row));
+        }
+    }
+
+    /// Check every key a `SUMMARY_SCANS` command would read, failing with
+    /// `{"Forbidden": {"key"}}` on the first the policy refuses.
+    pub fn check_summary(&self, strata: &Strata, command: &Value) -> Result<(), BridgeError> {
+        let Some(body) = summary_body(command) else {
+            return Ok(());
+        };
+        let mut list = json!({ "prefix": body.get("prefix"), "limit": SUMMARY_PAGE_SIZE });
+        for field in ["branch", "space"] {
+            if let Some(value) = body.get(field) {
+                list[field] = value.clone();
+            }
+        }
+        let mut executor = strata.executor();
+        loop {
+            let output = commands::call(&mut executor, json!({ "KvList": list.clone() }))?;
+            let keys = output["Keys"].as_array().cloned().unwrap_or_default();
+            for key in keys.iter().filter_map(Value::as_str) {
+                self.check(key)?;
+            }
+            match keys.last() {
+                Some(last) if keys.len() as u64 == SUMMARY_PAGE_SIZE => {
+                    list["cursor"] = last.clone();
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// `filter_output` on output JSON, parsed only if it is a listing.
+    pub fn filter_output_json(&self, output: String) -> String {
+        let listing = commands::peek_tag(&output).is_some_and(|tag| LISTING_OUTPUTS.contains(&tag));
+        match serde_json::from_str::<Value>(&output) {
+            Ok(mut value) if listing => {
+                self.filter_output(&mut value);
+                value.to_string()
+            }
+            _ => output,
+        }
+    }
+
+    /// Whether a listed row, a key or a `{"key"}` object, passes the policy.
+    pub fn allows_row(&self, row: &Value) -> bool {
+        let key = row.as_str().or_else(|| row.get("key")?.as_str());
+        key.is_none_or(|key| self.check(key).is_ok())
+    }
+
+    fn check_fields(&self, fields: &Value, moves: bool) -> Result<(), BridgeError> {
+        let moved = if moves { MOVE_FIELDS } else { &[] };
+        let keys = KEY_FIELDS.iter().chain(moved).filter_map(|f| fields.get(*f));
+        let listed = KEY_LIST_FIELDS.iter().filter_map(|f| fields.get(*f)?.as_array());
+        for key in keys.chain(listed.flatten()) {
+            if let Some(key) = key.as_str() {
+                self.check(key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The body of a `SUMMARY_SCANS` command, `None` for any other command.
+pub fn summary_body(command: &Value) -> Option<&Value> {
+    let (tag, body) = command.as_object()?.iter().next()?;
+    SUMMARY_SCANS.contains(&tag.as_str()).then_some(body)
+}
+
+/// `{"Forbidden": {"command"}}` for `operation`, which would read or write
+/// keys outside any policy.
+pub fn unscoped(operation: &str) -> BridgeError {
+    BridgeError::Kind("Forbidden", json!({ "command": operation }))
+}
@@ -0,0 +1,46 @@
+//! Memory pressure relief (`strata_notify_memory_pressure`).
+//!
+//! The bridge cannot observe the platform's memory warnings itself, so the
+//! host forwards them. Each notification drops every handle's read cache
+//! and, at higher levels, evicts part of the in-memory handles opened with
+//! `"eviction": "lru"`. A host callback, if registered, is told what was freed.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::sync::RwLock;
+
+use serde_json::Value;
+
+/// Host pressure sink: gets the level handled and a null-terminated JSON
+/// summary of what was freed, valid only for the duration of the call, and
+/// the `user_data` given when registering.
+pub type MemoryPressureCallback =
+    extern "C" fn(level: i32, summary_json: *const c_char, user_data: *mut c_void);
+
+/// The callback and its `user_data` pointer, passed back untouched.
+static CALLBACK: RwLock<Option<(MemoryPressureCallback, usize)>> = RwLock::new(None);
+
+/// Register (or clear, with `None`) the host pressure callback.
+pub fn set_callback(callback: Option<MemoryPressureCallback>, user_data: *mut c_void) {
+    let callback = callback.map(|callback| (callback, user_data as usize));
+    *CALLBACK.write().unwrap_or_else(|e| e.into_inner()) = callback;
+}
+
+/// The share of an LRU cache's bytes to evict at `level`: none at 0
+/// (normal), a quarter at 1 (warning), half at 2 (critical) and above.
+pub fn eviction_percent(level: i32) -> u64 {
+    match level {
+        i32::MIN..=0 => 0,
+        1 => 25,
+        _ => 50,
+    }
+}
+
+/// Tell the host callback, if one is registered, what a notification freed.
+pub fn report(level: i32, summary: &Value) {
+    let callback = *CALLBACK.read().unwrap_or_else(|e| e.into_inner());
+    if let Some((callback, user_data)) = callback {
+        let summary = CString::new(summary.to_string()).unwrap_or_default();
+        callback(level, summary.as_ptr(), user_data as *mut c_void);
+    }
+}
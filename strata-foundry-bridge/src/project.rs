@@ -0,0 +1,67 @@
+//! Field projection for read commands (`"fields": [...]`).
+//!
+//! A read command may carry `"fields": ["name", "role"]`. The bridge removes
+//! it before stratadb sees the command, then strips every object value in
+//! the output (`{"value": {"Object": {...}}}`) down to those top-level keys.
+//! Requested fields a value lacks are simply absent.
+
+use serde_json::{Map, Value};
+
+use crate::audit;
+use crate::error::BridgeError;
+
+/// Split `"fields"` out of a read command. Returns the command without it
+/// and the requested fields, or `None` if it has no projection.
+pub fn split(command_json: &str) -> Result<Option<(String, Vec<String>)>, BridgeError> {
+    // Only pay for a parse when the command could have a projection.
+    if !command_json.contains("\"fields\"") {
+        return Ok(None);
+    }
+    let mut command: Value =
+        serde_json::from_str(command_json).map_err(|e| format!("invalid command JSON: {e}"))?;
+    let Some((tag, body)) = command.as_object_mut().and_then(|m| m.iter_mut().next()) else {
+        return Ok(None);
+    };
+    if audit::is_mutation(tag) {
+        return Ok(None);
+    }
+    let Some(fields) = body.as_object_mut().and_then(|body| body.remove("fields")) else {
+        return Ok(None);
+    };
+    let fields: Vec<String> = serde_json::from_value(fields).map_err(|_| {
+        BridgeError::Kind(
+            "InvalidInput",
+            serde_json::json!({ "reason": "fields must be an array of strings" }),
+        )
+    })?;
+    Ok(Some((command.to_string(), fields)))
+}
+
+/// Project every object value in `output` onto `fields`.
+pub fn apply(output: String, fields: &[String]) -> String {
+    let Ok(mut value) = serde_json::from_str::<Value>(&output) else {
+        return output;
+    };
+    project(&mut value, fields);
+    value.to_string()
+}
+
+fn project(value: &mut Value, fields: &[String]) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Object(tagged)) = map.get_mut("value") {
+                if let Some(Value::Object(object)) = tagged.get_mut("Object") {
+                    retain_fields(object, fields);
+                    return;
+                }
+            }
+            map.values_mut().for_each(|v| project(v, fields));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| project(v, fields)),
+        _ => {}
+    }
+}
+
+fn retain_fields(object: &mut Map<String, Value>, fields: &[String]) {
+    object.retain(|key, _| fields.iter().any(|field| field == key));
+}
@@ -0,0 +1,70 @@
+//! Readahead hints for the `access_pattern` open option.
+//!
+//! stratadb opens its own files and takes no I/O hints, and per-descriptor
+//! advice such as `POSIX_FADV_SEQUENTIAL` would not reach its descriptors.
+//! What the bridge can do is prefetch: for `"sequential"` it asks the OS to
+//! read every file of the database into the page cache ahead of the scan
+//! (`posix_fadvise(WILLNEED)` on Linux and Android, `F_RDADVISE` on Apple
+//! platforms). `"random"` prefetches nothing, leaving point reads to the OS
+//! default.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::config::AccessPattern;
+
+/// Apply `pattern` to the database directory at `dir`. Returns the number
+/// of files prefetched.
+pub fn apply(dir: &Path, pattern: AccessPattern) -> io::Result<u64> {
+    match pattern {
+        AccessPattern::Sequential => prefetch_dir(dir),
+        AccessPattern::Random => Ok(0),
+    }
+}
+
+fn prefetch_dir(dir: &Path) -> io::Result<u64> {
+    let mut files = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            files += prefetch_dir(&entry.path())?;
+        } else if file_type.is_file() {
+            let file = File::open(entry.path())?;
+            prefetch(&file, file.metadata()?.len())?;
+            files += 1;
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn prefetch(file: &File, _len: u64) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // A zero length advises the whole file.
+    match unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_WILLNEED) } {
+        0 => Ok(()),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn prefetch(file: &File, len: u64) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let advice = libc::radvisory {
+        ra_offset: 0,
+        ra_count: len.min(libc::c_int::MAX as u64) as libc::c_int,
+    };
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_RDADVISE, &advice) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_vendor = "apple")))]
+fn prefetch(_file: &File, _len: u64) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,179 @@
+//! Per-handle cache of read outputs (`strata_set_read_cache`).
+//!
+//! Outputs of `KvGet`, `KvList`, `JsonGet`, `JsonList`, `StateGet` and
+//! `StateList` are kept keyed by their exact command JSON, so a repeated read
+//! is answered without running anything. A write to a primitive drops every
+//! cached read of that primitive; writes the bridge cannot attribute to one
+//! (branch and space commands, transactions, atomic batches) drop them all.
+//! Each drop bumps a generation, and a read only fills the cache if no drop
+//! happened while it ran, so a racing write never leaves a stale output.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use crate::audit;
+
+/// Reads whose outputs may be cached.
+const CACHEABLE_TAGS: &[&str] =
+    &["KvGet", "KvList", "JsonGet", "JsonList", "StateGet", "StateList"];
+
+/// The primitive a command tag reads or writes: `"kv"`, `"json"` or `"state"`.
+fn primitive(tag: &str) -> Option<&'static str> {
+    [("Kv", "kv"), ("Json", "json"), ("State", "state")]
+        .into_iter()
+        .find_map(|(prefix, primitive)| tag.starts_with(prefix).then_some(primitive))
+}
+
+pub fn is_cacheable(tag: &str) -> bool {
+    CACHEABLE_TAGS.contains(&tag)
+}
+
+struct Entry {
+    primitive: &'static str,
+    output: String,
+    /// The tick this entry was last used at.
+    used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    max_entries: usize,
+    entries: HashMap<String, Entry>,
+    /// Commands by last use, oldest first.
+    by_use: BTreeMap<u64, String>,
+    tick: u64,
+    generation: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Inner {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn drop_where(&mut self, mut dropped: impl FnMut(&Entry) -> bool) {
+        self.generation += 1;
+        let by_use = &mut self.by_use;
+        self.entries.retain(|_, entry| {
+            let keep = !dropped(entry);
+            if !keep {
+                by_use.remove(&entry.used);
+            }
+            keep
+        });
+    }
+}
+
+/// A handle's read cache; disabled until given a size.
+#[derive(Default)]
+pub struct ReadCache {
+    inner: Mutex<Inner>,
+}
+
+impl ReadCache {
+    /// Keep up to `max_entries` outputs, evicting the least recently used;
+    /// zero disables the cache and empties it.
+    pub fn resize(&self, max_entries: usize) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.max_entries = max_entries;
+        while inner.entries.len() > max_entries {
+            let Some((_, command)) = inner.by_use.pop_first() else {
+                break;
+            };
+            inner.entries.remove(&command);
+        }
+    }
+
+    pub fn max_entries(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).max_entries
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries() > 0
+    }
+
+    /// The cached output of `command_json`, if any, and otherwise the
+    /// generation to pass to `insert` once the read has run.
+    pub fn get(&self, command_json: &str) -> Result<String, u64> {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let inner = &mut *guard;
+        let tick = inner.next_tick();
+        match inner.entries.get_mut(command_json) {
+            Some(entry) => {
+                inner.by_use.remove(&entry.used);
+                inner.by_use.insert(tick, command_json.to_string());
+                entry.used = tick;
+                inner.hits += 1;
+                Ok(entry.output.clone())
+            }
+            None => {
+                inner.misses += 1;
+                Err(inner.generation)
+            }
+        }
+    }
+
+    /// Cache the output of a read of `tag` that started at `generation`.
+    pub fn insert(&self, tag: &str, command_json: &str, output: &str, generation: u64) {
+        let Some(primitive) = primitive(tag) else {
+            return;
+        };
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.max_entries == 0 || inner.generation != generation {
+            return;
+        }
+        if inner.entries.len() >= inner.max_entries && !inner.entries.contains_key(command_json) {
+            if let Some((_, oldest)) = inner.by_use.pop_first() {
+                inner.entries.remove(&oldest);
+            }
+        }
+        let used = inner.next_tick();
+        inner.by_use.insert(used, command_json.to_string());
+        let entry = Entry { primitive, output: output.to_string(), used };
+        if let Some(old) = inner.entries.insert(command_json.to_string(), entry) {
+            inner.by_use.remove(&old.used);
+        }
+    }
+
+    /// Drop the reads a successful command tagged `tag` may have changed.
+    pub fn invalidate(&self, tag: &str) {
+        if !audit::is_mutation(tag) || tag.starts_with("Event") || tag.starts_with("Vector") {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match primitive(tag) {
+            Some(written) => inner.drop_where(|entry| entry.primitive == written),
+            None => inner.drop_where(|_| true),
+        }
+    }
+
+    /// Drop every cached read.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).drop_where(|_| true);
+    }
+
+    /// Drop every cached read and release the memory they held, keeping
+    /// the cache enabled. Returns how many were dropped.
+    pub fn release(&self) -> usize {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let dropped = inner.entries.len();
+        inner.drop_where(|_| true);
+        inner.entries.shrink_to_fit();
+        dropped
+    }
+
+    /// `{"max_entries", "entries", "hits", "misses"}` for `strata_stats`.
+    pub fn stats(&self) -> Value {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        json!({
+            "max_entries": inner.max_entries,
+            "entries": inner.entries.len(),
+            "hits": inner.hits,
+            "misses": inner.misses,
+        })
+    }
+}
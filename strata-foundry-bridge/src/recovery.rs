@@ -0,0 +1,110 @@
+//! Open-time recovery reporting.
+//!
+//! stratadb replays its write-ahead log inside `Strata::open` and does not
+//! expose per-entry progress, replay counts, or the last good sequence on
+//! failure. What the bridge can observe is the WAL left on disk before the
+//! open and how long the open took, so that is what gets reported.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::path::Path;
+use std::time::Instant;
+
+use serde_json::{json, Value};
+
+/// Host progress sink: receives a null-terminated JSON event
+/// (`{"phase": "recovering" | "complete" | "failed", ...}`) that is only
+/// valid for the duration of the call.
+pub type RecoveryCallback = extern "C" fn(event_json: *const c_char);
+
+/// WAL files found under a database directory before it is opened.
+#[derive(Default)]
+pub struct WalFootprint {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+impl WalFootprint {
+    /// Count files under `dir` whose name (or a parent directory's name
+    /// within `dir`) mentions "wal".
+    pub fn scan(dir: &Path) -> Self {
+        let mut footprint = Self::default();
+        footprint.visit(dir, false);
+        footprint
+    }
+
+    fn visit(&mut self, dir: &Path, in_wal_dir: bool) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let is_wal = in_wal_dir
+                || entry.file_name().to_string_lossy().to_ascii_lowercase().contains("wal");
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                self.visit(&entry.path(), is_wal);
+            } else if is_wal {
+                self.files += 1;
+                self.bytes += meta.len();
+            }
+        }
+    }
+}
+
+/// Tracks one open: reports progress to the callback and builds the summary.
+pub struct Recovery {
+    callback: Option<RecoveryCallback>,
+    started: Instant,
+    wal: WalFootprint,
+}
+
+impl Recovery {
+    /// Measure the WAL at `path` and report the `recovering` phase.
+    pub fn start(path: &Path, callback: Option<RecoveryCallback>) -> Self {
+        let wal = WalFootprint::scan(path);
+        let recovery = Self {
+            callback,
+            started: Instant::now(),
+            wal,
+        };
+        recovery.emit(json!({
+            "phase": "recovering",
+            "path": path.to_string_lossy(),
+            "wal_files": recovery.wal.files,
+            "wal_bytes": recovery.wal.bytes,
+        }));
+        recovery
+    }
+
+    /// Report the `complete` phase and return the summary for the open result.
+    pub fn complete(self) -> Value {
+        let summary = self.summary();
+        self.emit(json!({ "phase": "complete", "recovery": summary }));
+        summary
+    }
+
+    /// Report the `failed` phase and return the fields for a `RecoveryFailed` error.
+    pub fn fail(self, reason: &str) -> Value {
+        let mut fields = self.summary();
+        fields["reason"] = json!(reason);
+        self.emit(json!({ "phase": "failed", "error": fields }));
+        fields
+    }
+
+    fn summary(&self) -> Value {
+        json!({
+            "duration_ms": self.started.elapsed().as_millis() as u64,
+            "wal_files": self.wal.files,
+            "wal_bytes": self.wal.bytes,
+        })
+    }
+
+    fn emit(&self, event: Value) {
+        if let Some(callback) = self.callback {
+            let event = CString::new(event.to_string()).unwrap_or_default();
+            callback(event.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Optional bookkeeping of the strings handed to Swift, so that
+//! `strata_free_string` can refuse pointers the bridge never allocated.
+//!
+//! Off by default: it costs a lock and a set operation per returned string.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use crate::log;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Addresses of strings handed out while safe mode was on and not yet freed.
+static LIVE: LazyLock<Mutex<HashSet<usize>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Turn safe mode on or off. Turning it off forgets every tracked string.
+///
+/// Strings handed out while safe mode is off are not tracked, so freeing
+/// them once it is on is refused (and leaks them); enable it at startup.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Release);
+    if !enabled {
+        live().clear();
+    }
+}
+
+/// Track a string about to be handed out.
+pub fn register(ptr: *const u8) {
+    if ENABLED.load(Ordering::Acquire) {
+        live().insert(ptr as usize);
+    }
+}
+
+/// Whether `ptr` may be freed. In safe mode an unknown pointer is logged and
+/// refused; otherwise every pointer is allowed.
+pub fn release(ptr: *const u8) -> bool {
+    if !ENABLED.load(Ordering::Acquire) {
+        return true;
+    }
+    if live().remove(&(ptr as usize)) {
+        return true;
+    }
+    log::warn(&format!(
+        "strata_free_string: ignoring pointer {ptr:p} not allocated by the bridge (or already freed)"
+    ));
+    false
+}
+
+fn live() -> std::sync::MutexGuard<'static, HashSet<usize>> {
+    LIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,668 @@
+//! Command descriptors — a hand-maintained table of command tags and their fields.
+//!
+//! Used for inline help and autocomplete in the Foundry command console.
+//! This mirrors the stratadb `Command` wire format; it is documentation, not
+//! a validator, so keep it in sync when commands are added.
+
+use serde_json::json;
+
+/// A single field of a command payload.
+pub struct FieldDescriptor {
+    pub name: &'static str,
+    pub ty: &'static str,
+    pub required: bool,
+}
+
+/// A command tag with a one-line summary and its payload fields.
+pub struct CommandDescriptor {
+    pub tag: &'static str,
+    pub summary: &'static str,
+    pub fields: &'static [FieldDescriptor],
+}
+
+impl CommandDescriptor {
+    /// Render as `{"tag": ..., "fields": [{"name","type","required"}], "summary": ...}`.
+    pub fn to_json(&self) -> serde_json::Value {
+        let fields: Vec<_> = self
+            .fields
+            .iter()
+            .map(|f| json!({"name": f.name, "type": f.ty, "required": f.required}))
+            .collect();
+        json!({"tag": self.tag, "fields": fields, "summary": self.summary})
+    }
+}
+
+const fn req(name: &'static str, ty: &'static str) -> FieldDescriptor {
+    FieldDescriptor {
+        name,
+        ty,
+        required: true,
+    }
+}
+
+const fn opt(name: &'static str, ty: &'static str) -> FieldDescriptor {
+    FieldDescriptor {
+        name,
+        ty,
+        required: false,
+    }
+}
+
+const BRANCH: FieldDescriptor = opt("branch", "string");
+const SPACE: FieldDescriptor = opt("space", "string");
+const AS_OF: FieldDescriptor = opt("as_of", "u64");
+
+/// Look up the descriptor for a command tag (case-sensitive, e.g. `"KvGet"`).
+pub fn describe(tag: &str) -> Option<&'static CommandDescriptor> {
+    COMMANDS.iter().find(|c| c.tag == tag)
+}
+
+/// All known command descriptors, grouped by primitive.
+pub static COMMANDS: &[CommandDescriptor] = &[
+    // KV
+    CommandDescriptor {
+        tag: "KvPut",
+        summary: "Write a value under a key.",
+        fields: &[BRANCH, SPACE, req("key", "string"), req("value", "Value")],
+    },
+    CommandDescriptor {
+        tag: "KvGet",
+        summary: "Read the current value of a key.",
+        fields: &[BRANCH, SPACE, req("key", "string"), AS_OF],
+    },
+    CommandDescriptor {
+        tag: "KvDelete",
+        summary: "Delete a key.",
+        fields: &[BRANCH, SPACE, req("key", "string")],
+    },
+    CommandDescriptor {
+        tag: "KvList",
+        summary: "List keys, optionally under a prefix.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            opt("prefix", "string"),
+            opt("cursor", "string"),
+            opt("limit", "u64"),
+            AS_OF,
+        ],
+    },
+    CommandDescriptor {
+        tag: "KvBatchPut",
+        summary: "Write many key/value pairs.",
+        fields: &[BRANCH, SPACE, req("entries", "[{key, value}]")],
+    },
+    CommandDescriptor {
+        tag: "KvRename",
+        summary: "Atomically move a value to a new key (bridge command).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("from", "string"),
+            req("to", "string"),
+            opt("overwrite", "bool"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "KvSwap",
+        summary: "Atomically exchange two keys' values (bridge command).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("key_a", "string"),
+            req("key_b", "string"),
+            opt("allow_missing", "bool"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "KvUpdate",
+        summary: "Atomically apply a JSON Patch to a value (bridge command).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("key", "string"),
+            req("ops", "[{op, path, value}]"),
+            opt("upsert", "Value"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "KvGetOrPut",
+        summary: "Get a value, atomically writing a default if absent (bridge command).",
+        fields: &[BRANCH, SPACE, req("key", "string"), req("default", "Value")],
+    },
+    CommandDescriptor {
+        tag: "KvGetMeta",
+        summary: "Get a value with its version and last-modified time.",
+        fields: &[BRANCH, SPACE, req("key", "string"), AS_OF],
+    },
+    CommandDescriptor {
+        tag: "KvSample",
+        summary: "Up to n randomly chosen entries, optionally within a prefix (bridge command).",
+        fields: &[BRANCH, SPACE, req("n", "u64"), opt("prefix", "string")],
+    },
+    CommandDescriptor {
+        tag: "KvAggregate",
+        summary: "Sum, average, min, max or count numeric values under a prefix (bridge command).",
+        fields: &[BRANCH, SPACE, opt("prefix", "string"), req("op", "string")],
+    },
+    CommandDescriptor {
+        tag: "KvInferSchema",
+        summary: "Infer field names, types and optionality of sampled values (bridge command).",
+        fields: &[BRANCH, SPACE, opt("prefix", "string"), opt("sample", "u64")],
+    },
+    CommandDescriptor {
+        tag: "KvNamespaces",
+        summary: "Count keys per prefix before the first separator (bridge command).",
+        fields: &[BRANCH, SPACE, opt("separator", "string")],
+    },
+    CommandDescriptor {
+        tag: "KvGetv",
+        summary: "Read the version history of a key.",
+        fields: &[BRANCH, SPACE, req("key", "string"), AS_OF],
+    },
+    // JSON
+    CommandDescriptor {
+        tag: "JsonSet",
+        summary: "Set a JSON document (or a path within it).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("key", "string"),
+            req("path", "string"),
+            req("value", "Value"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "JsonGet",
+        summary: "Read a JSON document at a path, or at each of `paths` in one call.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("key", "string"),
+            opt("path", "string"),
+            opt("paths", "[string]"),
+            AS_OF,
+        ],
+    },
+    CommandDescriptor {
+        tag: "JsonRename",
+        summary: "Atomically move a JSON document to a new key (bridge command).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("from", "string"),
+            req("to", "string"),
+            opt("overwrite", "bool"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "JsonMerge",
+        summary: "Merge an object into a JSON document (bridge command).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("key", "string"),
+            opt("path", "string"),
+            req("value", "Value"),
+            opt("deep", "bool"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "JsonCanonicalize",
+        summary: "Rewrite a JSON document with sorted keys and normalized numbers.",
+        fields: &[BRANCH, SPACE, req("key", "string")],
+    },
+    CommandDescriptor {
+        tag: "JsonCatalog",
+        summary: "List documents with their size and top-level field names.",
+        fields: &[BRANCH, SPACE, opt("prefix", "string"), opt("limit", "u64")],
+    },
+    CommandDescriptor {
+        tag: "JsonValidate",
+        summary: "Validate JSON documents against a JSON Schema document (bridge command).",
+        fields: &[BRANCH, SPACE, req("schema_key", "string"), req("targets", "string[]")],
+    },
+    CommandDescriptor {
+        tag: "JsonGetInline",
+        summary: "Read a JSON document as plain JSON rather than tagged values (bridge command).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("key", "string"),
+            opt("path", "string"),
+            AS_OF,
+        ],
+    },
+    CommandDescriptor {
+        tag: "JsonDelete",
+        summary: "Delete a JSON document path.",
+        fields: &[BRANCH, SPACE, req("key", "string"), req("path", "string")],
+    },
+    CommandDescriptor {
+        tag: "JsonGetv",
+        summary: "Read the version history of a JSON document.",
+        fields: &[BRANCH, SPACE, req("key", "string"), AS_OF],
+    },
+    CommandDescriptor {
+        tag: "JsonBatchSet",
+        summary: "Set many JSON documents.",
+        fields: &[BRANCH, SPACE, req("entries", "[{key, path, value}]")],
+    },
+    CommandDescriptor {
+        tag: "JsonList",
+        summary: "List JSON document keys.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            opt("prefix", "string"),
+            opt("cursor", "string"),
+            req("limit", "u64"),
+            AS_OF,
+        ],
+    },
+    // Event
+    CommandDescriptor {
+        tag: "EventAppend",
+        summary: "Append an event to the log.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("event_type", "string"),
+            req("payload", "Value"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "EventBatchAppend",
+        summary: "Append many events.",
+        fields: &[BRANCH, SPACE, req("entries", "[{event_type, payload}]")],
+    },
+    CommandDescriptor {
+        tag: "EventGet",
+        summary: "Read the event at a sequence number.",
+        fields: &[BRANCH, SPACE, req("sequence", "u64"), AS_OF],
+    },
+    CommandDescriptor {
+        tag: "EventGetByType",
+        summary: "Read events of one type.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("event_type", "string"),
+            opt("limit", "u64"),
+            opt("after_sequence", "u64"),
+            AS_OF,
+        ],
+    },
+    CommandDescriptor {
+        tag: "EventLen",
+        summary: "Count events in the log.",
+        fields: &[BRANCH, SPACE],
+    },
+    CommandDescriptor {
+        tag: "EventStats",
+        summary: "Count events per kind, with the total and sequence range.",
+        fields: &[BRANCH, SPACE],
+    },
+    CommandDescriptor {
+        tag: "EventKinds",
+        summary: "List the distinct event kinds in the log, sorted.",
+        fields: &[BRANCH, SPACE],
+    },
+    CommandDescriptor {
+        tag: "EventLast",
+        summary: "Get the most recent event, optionally of one kind.",
+        fields: &[BRANCH, SPACE, opt("kind", "string")],
+    },
+    // State
+    CommandDescriptor {
+        tag: "StateSet",
+        summary: "Set a state cell.",
+        fields: &[BRANCH, SPACE, req("cell", "string"), req("value", "Value")],
+    },
+    CommandDescriptor {
+        tag: "StateBatchSet",
+        summary: "Set many state cells.",
+        fields: &[BRANCH, SPACE, req("entries", "[{cell, value}]")],
+    },
+    CommandDescriptor {
+        tag: "StateGet",
+        summary: "Read a state cell.",
+        fields: &[BRANCH, SPACE, req("cell", "string"), AS_OF],
+    },
+    CommandDescriptor {
+        tag: "StateCas",
+        summary: "Compare-and-swap a state cell on its counter.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("cell", "string"),
+            opt("expected_counter", "u64"),
+            req("value", "Value"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "StateGetv",
+        summary: "Read the version history of a state cell.",
+        fields: &[BRANCH, SPACE, req("cell", "string"), AS_OF],
+    },
+    CommandDescriptor {
+        tag: "StateInit",
+        summary: "Initialize a state cell if it does not exist.",
+        fields: &[BRANCH, SPACE, req("cell", "string"), req("value", "Value")],
+    },
+    CommandDescriptor {
+        tag: "StateDelete",
+        summary: "Delete a state cell.",
+        fields: &[BRANCH, SPACE, req("cell", "string")],
+    },
+    CommandDescriptor {
+        tag: "StateList",
+        summary: "List state cells, optionally under a prefix.",
+        fields: &[BRANCH, SPACE, opt("prefix", "string"), AS_OF],
+    },
+    // Vector
+    CommandDescriptor {
+        tag: "VectorUpsert",
+        summary: "Insert or replace a vector.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("collection", "string"),
+            req("key", "string"),
+            req("vector", "[f32]"),
+            opt("metadata", "Value"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "VectorGet",
+        summary: "Read a vector by key.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("collection", "string"),
+            req("key", "string"),
+            AS_OF,
+        ],
+    },
+    CommandDescriptor {
+        tag: "VectorDelete",
+        summary: "Delete a vector.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("collection", "string"),
+            req("key", "string"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "VectorSearch",
+        summary: "Find the k nearest vectors to a query.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("collection", "string"),
+            req("query", "[f32]"),
+            req("k", "u64"),
+            opt("filter", "[MetadataFilter]"),
+            opt("metric", "string"),
+            AS_OF,
+        ],
+    },
+    CommandDescriptor {
+        tag: "VectorSearchById",
+        summary: "Find the k nearest vectors to a stored vector (bridge command).",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("collection", "string"),
+            req("id", "string"),
+            req("k", "u64"),
+            opt("exclude_self", "bool"),
+            opt("filter", "[MetadataFilter]"),
+            opt("metric", "string"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "VectorCreateCollection",
+        summary: "Create a vector collection; re-creating one with the same spec is a no-op.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("collection", "string"),
+            req("dimension", "u64"),
+            req("metric", "string"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "VectorDeleteCollection",
+        summary: "Delete a vector collection.",
+        fields: &[BRANCH, SPACE, req("collection", "string")],
+    },
+    CommandDescriptor {
+        tag: "VectorListCollections",
+        summary: "List vector collections.",
+        fields: &[BRANCH, SPACE],
+    },
+    CommandDescriptor {
+        tag: "VectorCollectionStats",
+        summary: "Report statistics for a vector collection.",
+        fields: &[BRANCH, SPACE, req("collection", "string")],
+    },
+    CommandDescriptor {
+        tag: "VectorCollectionInfo",
+        summary: "Report a vector collection's dimension, count and metric.",
+        fields: &[BRANCH, SPACE, req("collection", "string")],
+    },
+    CommandDescriptor {
+        tag: "VectorReindex",
+        summary: "Rebuild a vector collection's index by rewriting its vectors.",
+        fields: &[BRANCH, SPACE, req("collection", "string")],
+    },
+    CommandDescriptor {
+        tag: "VectorBatchUpsert",
+        summary: "Insert or replace many vectors.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("collection", "string"),
+            req("entries", "[{key, vector, metadata}]"),
+        ],
+    },
+    // Branch
+    CommandDescriptor {
+        tag: "BranchCreate",
+        summary: "Create a branch.",
+        fields: &[opt("branch_id", "string"), opt("metadata", "Value")],
+    },
+    CommandDescriptor {
+        tag: "BranchGet",
+        summary: "Read branch info.",
+        fields: &[req("branch", "string")],
+    },
+    CommandDescriptor {
+        tag: "BranchList",
+        summary: "List branches.",
+        fields: &[
+            opt("state", "string"),
+            opt("limit", "u64"),
+            opt("offset", "u64"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "BranchExists",
+        summary: "Check whether a branch exists.",
+        fields: &[req("branch", "string")],
+    },
+    CommandDescriptor {
+        tag: "BranchDelete",
+        summary: "Delete a branch.",
+        fields: &[req("branch", "string")],
+    },
+    CommandDescriptor {
+        tag: "BranchFork",
+        summary: "Fork a branch.",
+        fields: &[req("source", "string"), req("destination", "string")],
+    },
+    CommandDescriptor {
+        tag: "BranchTree",
+        summary: "List branches as a tree rooted at the default branch (bridge command).",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "BranchDiff",
+        summary: "Diff two branches.",
+        fields: &[req("branch_a", "string"), req("branch_b", "string")],
+    },
+    CommandDescriptor {
+        tag: "BranchMerge",
+        summary: "Merge one branch into another.",
+        fields: &[
+            req("source", "string"),
+            req("target", "string"),
+            req("strategy", "string"),
+        ],
+    },
+    // Transactions
+    CommandDescriptor {
+        tag: "TxnBegin",
+        summary: "Begin a transaction.",
+        fields: &[BRANCH, opt("options", "{read_only}")],
+    },
+    CommandDescriptor {
+        tag: "TxnCommit",
+        summary: "Commit the active transaction.",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "TxnRollback",
+        summary: "Roll back the active transaction.",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "TxnInfo",
+        summary: "Describe the active transaction.",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "TxnIsActive",
+        summary: "Check for an active transaction.",
+        fields: &[],
+    },
+    // Retention
+    CommandDescriptor {
+        tag: "RetentionApply",
+        summary: "Apply the retention policy.",
+        fields: &[BRANCH],
+    },
+    CommandDescriptor {
+        tag: "RetentionStats",
+        summary: "Report retention statistics.",
+        fields: &[BRANCH],
+    },
+    CommandDescriptor {
+        tag: "RetentionPreview",
+        summary: "Preview what retention would remove.",
+        fields: &[BRANCH],
+    },
+    // Database
+    CommandDescriptor {
+        tag: "Ping",
+        summary: "Check the database responds.",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "Info",
+        summary: "Report database information.",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "Flush",
+        summary: "Flush pending writes to disk.",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "MultiGet",
+        summary: "Read KV keys, state cells and recent events in one call.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            opt("kv", "[string]"),
+            opt("state", "[string]"),
+            opt("events", "{limit, reverse}"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "StateDiffBetween",
+        summary: "Diff a primitive (kv, state or json) between two event sequences.",
+        fields: &[
+            BRANCH,
+            SPACE,
+            req("from", "u64"),
+            req("to", "u64"),
+            req("primitive", "string"),
+        ],
+    },
+    CommandDescriptor {
+        tag: "ChangesSince",
+        summary: "List keys written or deleted after an event sequence, per primitive.",
+        fields: &[BRANCH, SPACE, req("sequence", "u64"), opt("primitives", "string[]")],
+    },
+    CommandDescriptor {
+        tag: "Digest",
+        summary: "Fingerprint the content of kv, json, state and events (bridge command).",
+        fields: &[BRANCH, SPACE, opt("primitives", "string[]")],
+    },
+    CommandDescriptor {
+        tag: "Compact",
+        summary: "Compact storage.",
+        fields: &[],
+    },
+    CommandDescriptor {
+        tag: "TimeRange",
+        summary: "Report the oldest and latest timestamps.",
+        fields: &[BRANCH],
+    },
+    // Bundles
+    CommandDescriptor {
+        tag: "BranchExport",
+        summary: "Export a branch to a bundle file.",
+        fields: &[req("branch_id", "string"), req("path", "string")],
+    },
+    CommandDescriptor {
+        tag: "BranchImport",
+        summary: "Import a branch from a bundle file.",
+        fields: &[req("path", "string")],
+    },
+    CommandDescriptor {
+        tag: "BranchBundleValidate",
+        summary: "Validate a bundle file.",
+        fields: &[req("path", "string")],
+    },
+    // Spaces
+    CommandDescriptor {
+        tag: "SpaceList",
+        summary: "List spaces in a branch.",
+        fields: &[BRANCH],
+    },
+    CommandDescriptor {
+        tag: "SpaceCreate",
+        summary: "Create a space.",
+        fields: &[BRANCH, req("space", "string")],
+    },
+    CommandDescriptor {
+        tag: "SpaceDelete",
+        summary: "Delete a space.",
+        fields: &[BRANCH, req("space", "string"), opt("force", "bool")],
+    },
+    CommandDescriptor {
+        tag: "SpaceExists",
+        summary: "Check whether a space exists.",
+        fields: &[BRANCH, req("space", "string")],
+    },
+    // Search
+    CommandDescriptor {
+        tag: "Search",
+        summary: "Hybrid search across primitives.",
+        fields: &[BRANCH, SPACE, req("search", "SearchQuery")],
+    },
+];
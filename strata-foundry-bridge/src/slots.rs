@@ -0,0 +1,50 @@
+//! Per-handle bound on in-flight commands (`max_concurrent_commands`).
+
+use std::sync::{Condvar, Mutex};
+
+use serde_json::json;
+
+use crate::error::BridgeError;
+
+/// Counts a handle's in-flight commands against its limit.
+#[derive(Default)]
+pub struct CommandSlots {
+    in_flight: Mutex<u32>,
+    freed: Condvar,
+}
+
+/// A taken slot, given back when dropped.
+pub struct Slot<'a>(Option<&'a CommandSlots>);
+
+impl CommandSlots {
+    /// Take a slot for one command. `max` of `None` or zero is unlimited.
+    ///
+    /// At the limit, waits for a slot if `block` is set and otherwise fails
+    /// with `Busy`.
+    pub fn acquire(&self, max: Option<u32>, block: bool) -> Result<Slot<'_>, BridgeError> {
+        let Some(max) = max.filter(|&max| max > 0) else {
+            return Ok(Slot(None));
+        };
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        while *in_flight >= max {
+            if !block {
+                return Err(BridgeError::Kind(
+                    "Busy",
+                    json!({ "in_flight": *in_flight, "max_concurrent_commands": max }),
+                ));
+            }
+            in_flight = self.freed.wait(in_flight).unwrap_or_else(|e| e.into_inner());
+        }
+        *in_flight += 1;
+        Ok(Slot(Some(self)))
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        if let Some(slots) = self.0 {
+            *slots.in_flight.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+            slots.freed.notify_one();
+        }
+    }
+}
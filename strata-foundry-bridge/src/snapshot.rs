@@ -0,0 +1,99 @@
+//! Read snapshots: a pinned, repeatable view of a handle's database.
+//!
+//! Each snapshot owns a stratadb `Session` with a read-only transaction
+//! begun when the snapshot is taken, so every read through it sees the
+//! database as of that moment. Snapshot IDs come from the handle ID space,
+//! which lets `strata_execute` take them in place of a handle.
+
+use std::sync::Mutex;
+
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use stratadb::Session;
+
+use crate::audit;
+use crate::commands;
+use crate::error::BridgeError;
+use crate::handle::HandleRegistry;
+
+struct Snapshot {
+    handle: u64,
+    session: Session,
+}
+
+/// Registry of open snapshots, keyed by snapshot ID.
+pub struct SnapshotRegistry {
+    snapshots: DashMap<u64, Mutex<Snapshot>>,
+}
+
+impl SnapshotRegistry {
+    pub fn new() -> Self {
+        Self { snapshots: DashMap::new() }
+    }
+
+    /// Pin a read view of `handle`'s database. Returns the snapshot ID.
+    pub fn begin(&self, registry: &HandleRegistry, handle: u64) -> Result<u64, BridgeError> {
+        registry.flush_coalesced(handle)?;
+        let mut session = registry.run_guarded(handle, |strata| Ok(strata.session()))?;
+        let begin = json!({ "TxnBegin": { "branch": null, "options": { "read_only": true } } });
+        commands::call(&mut session, begin)?;
+
+        let id = registry.reserve_id();
+        self.snapshots.insert(id, Mutex::new(Snapshot { handle, session }));
+        Ok(id)
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.snapshots.contains_key(&id)
+    }
+
+    /// Run a stratadb read command against a snapshot. Returns its output JSON.
+    ///
+    /// Writes, transaction control and bridge commands fail with
+    /// `SnapshotReadOnly`, and keys outside the handle's key policy with
+    /// `Forbidden`.
+    pub fn execute(
+        &self,
+        registry: &HandleRegistry,
+        id: u64,
+        command_json: &str,
+    ) -> Result<Value, BridgeError> {
+        let snapshot = self.snapshots.get(&id).ok_or("invalid snapshot")?;
+        let mut snapshot = snapshot.lock().unwrap_or_else(|e| e.into_inner());
+        let command: Value = serde_json::from_str(command_json)
+            .map_err(|e| format!("invalid command JSON: {e}"))?;
+        let tag = command.as_object().and_then(|m| m.keys().next()).map_or("", String::as_str);
+        if audit::is_mutation(tag) || tag.starts_with("Txn") || commands::is_bridge_command(tag) {
+            return Err(BridgeError::Kind(
+                "SnapshotReadOnly",
+                json!({ "snapshot": id, "command": tag }),
+            ));
+        }
+        let handle = snapshot.handle;
+        registry.check_key_policy(handle, &command)?;
+        let mut output = registry
+            .run_guarded(handle, |_| commands::call(&mut snapshot.session, command.clone()))?;
+        registry.filter_keys(handle, &mut output);
+        Ok(output)
+    }
+
+    /// Release a snapshot.
+    pub fn end(&self, id: u64) -> Result<(), BridgeError> {
+        let (_, snapshot) = self.snapshots.remove(&id).ok_or("invalid snapshot")?;
+        let mut snapshot = snapshot.into_inner().unwrap_or_else(|e| e.into_inner());
+        commands::call(&mut snapshot.session, json!({ "TxnRollback": null })).map(drop)
+    }
+
+    /// Release every snapshot of `handle`, as it closes.
+    pub fn close_handle(&self, handle: u64) {
+        let ids: Vec<u64> = self
+            .snapshots
+            .iter()
+            .filter(|s| s.lock().unwrap_or_else(|e| e.into_inner()).handle == handle)
+            .map(|s| *s.key())
+            .collect();
+        for id in ids {
+            let _ = self.end(id);
+        }
+    }
+}
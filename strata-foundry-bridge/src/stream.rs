@@ -0,0 +1,235 @@
+//! Pull-based result streams.
+//!
+//! A stream wraps a list-returning command and hands its rows to Swift one at
+//! a time, so the consumer drives the pace. `KvList` and `JsonList` are paged
+//! through their cursors so a large store is never materialized at once;
+//! other commands are executed once and their rows buffered. Only the reads
+//! in `STREAMABLE_TAGS` may be streamed: rows are fetched on a bare executor,
+//! outside the limits, audit and hooks that guard writes. Each page is still
+//! checked against the handle's key policy, and rows naming keys it refuses
+//! are dropped.
+//!
+//! `strata_execute_stream_msgpack` drains a stream into a host callback
+//! instead, one MessagePack-encoded row per call.
+
+use std::collections::VecDeque;
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use dashmap::DashMap;
+use serde_json::{json, Value};
+
+use crate::commands;
+use crate::error::BridgeError;
+use crate::handle::HandleRegistry;
+
+/// Host row sink for `strata_execute_stream_msgpack`: receives one row as a
+/// MessagePack buffer of `len` bytes, valid only for the duration of the
+/// call, and the caller's `user_data`. Returning nonzero stops the stream.
+pub type RowCallback = extern "C" fn(row: *const u8, len: usize, user_data: *mut c_void) -> i32;
+
+/// Read and list commands a stream may wrap.
+const STREAMABLE_TAGS: &[&str] = &[
+    "KvGet",
+    "KvList",
+    "JsonGet",
+    "JsonList",
+    "StateGet",
+    "StateList",
+    "EventGet",
+    "EventGetByType",
+    "EventLen",
+    "VectorGet",
+    "VectorSearch",
+    "VectorListCollections",
+    "BranchList",
+    "SpaceList",
+    "TimeRange",
+    "Search",
+];
+
+/// Rows fetched per page for cursor-paged commands.
+const PAGE_SIZE: u64 = 256;
+
+struct Stream {
+    handle: u64,
+    tag: String,
+    body: Value,
+    buffer: VecDeque<Value>,
+    cursor: Option<String>,
+    /// Rows still allowed by the command's own `limit`, if it had one.
+    remaining: Option<u64>,
+    exhausted: bool,
+}
+
+impl Stream {
+    /// Pop the next row, fetching another page if the buffer is empty.
+    /// Returns `None` once the command has no more rows.
+    fn next(&mut self, registry: &HandleRegistry) -> Result<Option<Value>, BridgeError> {
+        while self.buffer.is_empty() && !self.exhausted {
+            self.fetch(registry)?;
+        }
+        Ok(self.buffer.pop_front())
+    }
+
+    fn fetch(&mut self, registry: &HandleRegistry) -> Result<(), BridgeError> {
+        let paged = matches!(self.tag.as_str(), "KvList" | "JsonList");
+        let mut body = self.body.clone();
+        let page = self.remaining.map_or(PAGE_SIZE, |r| r.min(PAGE_SIZE));
+        if paged {
+            body["limit"] = json!(page);
+            if let Some(cursor) = &self.cursor {
+                body["cursor"] = json!(cursor);
+            }
+        }
+
+        let command = json!({ &self.tag: body });
+        registry.check_key_policy(self.handle, &command)?;
+        let output = registry.run_guarded(self.handle, |strata| {
+            commands::call(&mut strata.executor(), command)
+        })?;
+
+        let (mut rows, next_cursor) = rows_of(output);
+        let fetched = rows.len() as u64;
+        match self.tag.as_str() {
+            // KvList cursors are exclusive: resume after the last key seen.
+            "KvList" => {
+                self.cursor = rows.last().and_then(Value::as_str).map(String::from);
+                self.exhausted = fetched < page;
+            }
+            "JsonList" => {
+                self.exhausted = next_cursor.is_none() || fetched == 0;
+                self.cursor = next_cursor;
+            }
+            _ => self.exhausted = true,
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(fetched);
+            self.exhausted |= *remaining == 0;
+        }
+        // Filtered after paging, which follows the unfiltered keys.
+        if let Some(policy) = registry.key_policy(self.handle) {
+            rows.retain(|row| policy.allows_row(row));
+        }
+        self.buffer.extend(rows);
+        Ok(())
+    }
+}
+
+/// Split a command's output into rows, along with any continuation cursor.
+///
+/// List payloads yield their elements; anything else is a single row.
+fn rows_of(output: Value) -> (Vec<Value>, Option<String>) {
+    let payload = match output {
+        Value::Object(map) if map.len() == 1 => map.into_iter().next().map(|(_, v)| v),
+        other => Some(other),
+    };
+    match payload {
+        Some(Value::Array(rows)) => (rows, None),
+        Some(Value::Object(mut result)) if result.contains_key("keys") => {
+            let cursor = result.get("cursor").and_then(Value::as_str).map(String::from);
+            match result.remove("keys") {
+                Some(Value::Array(keys)) => (keys, cursor),
+                _ => (Vec::new(), None),
+            }
+        }
+        Some(Value::Null) | None => (Vec::new(), None),
+        Some(row) => (vec![row], None),
+    }
+}
+
+/// Registry of open streams, keyed by stream ID.
+pub struct StreamRegistry {
+    next_id: AtomicU64,
+    streams: DashMap<u64, Mutex<Stream>>,
+}
+
+impl StreamRegistry {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            streams: DashMap::new(),
+        }
+    }
+
+    /// Open a stream over `command_json` on `handle`. Nothing is executed
+    /// until the first `next`. A command outside `STREAMABLE_TAGS` fails with
+    /// `InvalidInput`, and one the handle's key policy refuses with
+    /// `Forbidden`.
+    pub fn open(
+        &self,
+        registry: &HandleRegistry,
+        handle: u64,
+        command_json: &str,
+    ) -> Result<u64, BridgeError> {
+        registry.uptime_ms(handle).ok_or("invalid handle")?;
+
+        let value: Value = serde_json::from_str(command_json)
+            .map_err(|e| format!("invalid command JSON: {e}"))?;
+        let (tag, body) = match value {
+            Value::Object(map) if map.len() == 1 => map.into_iter().next().unwrap_or_default(),
+            Value::String(tag) => (tag, Value::Null),
+            _ => return Err("invalid command JSON: expected a single tagged command".into()),
+        };
+        if !STREAMABLE_TAGS.contains(&tag.as_str()) {
+            let reason = format!("{tag} cannot be streamed; streams only run reads");
+            return Err(BridgeError::Kind("InvalidInput", json!({ "reason": reason })));
+        }
+        let body = if body.is_null() { json!({}) } else { body };
+        registry.check_key_policy(handle, &json!({ &tag: &body }))?;
+        let remaining = body.get("limit").and_then(Value::as_u64);
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let stream = Stream {
+            handle,
+            tag,
+            body,
+            buffer: VecDeque::new(),
+            cursor: None,
+            remaining,
+            exhausted: remaining == Some(0),
+        };
+        self.streams.insert(id, Mutex::new(stream));
+        Ok(id)
+    }
+
+    /// The next row of a stream, or `None` once it is drained.
+    pub fn next(&self, registry: &HandleRegistry, id: u64) -> Result<Option<Value>, BridgeError> {
+        let stream = self.streams.get(&id).ok_or("invalid stream")?;
+        let mut stream = stream.lock().unwrap_or_else(|e| e.into_inner());
+        stream.next(registry)
+    }
+
+    pub fn close(&self, id: u64) {
+        self.streams.remove(&id);
+    }
+
+    /// Stream every row of `command_json` on `handle` to `callback` as
+    /// MessagePack, on the calling thread. Returns the rows delivered and
+    /// whether the callback stopped the stream early.
+    pub fn drain_msgpack(
+        &self,
+        registry: &HandleRegistry,
+        handle: u64,
+        command_json: &str,
+        callback: RowCallback,
+        user_data: *mut c_void,
+    ) -> Result<(u64, bool), BridgeError> {
+        let id = self.open(registry, handle, command_json)?;
+        let result = (|| {
+            let mut rows = 0;
+            while let Some(row) = self.next(registry, id)? {
+                let buffer = rmp_serde::to_vec_named(&row)
+                    .map_err(|e| BridgeError::from(format!("failed to encode row: {e}")))?;
+                rows += 1;
+                if callback(buffer.as_ptr(), buffer.len(), user_data) != 0 {
+                    return Ok((rows, true));
+                }
+            }
+            Ok((rows, false))
+        })();
+        self.close(id);
+        result
+    }
+}
@@ -0,0 +1,59 @@
+//! Named bridge threads.
+//!
+//! Threads the bridge spawns are named `<prefix>-<role>-<n>` (e.g.
+//! `strata-worker-0`) so they are identifiable in Instruments and other
+//! profilers. Swift can change the prefix with `strata_set_thread_name_prefix`;
+//! it applies to threads spawned afterwards.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::thread::JoinHandle;
+
+const DEFAULT_PREFIX: &str = "strata";
+
+static PREFIX: RwLock<String> = RwLock::new(String::new());
+static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Decrements `RUNNING` when a spawned thread ends, even by panicking.
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Set the thread name prefix; `None` or an empty prefix restores `"strata"`.
+pub fn set_prefix(prefix: Option<&str>) {
+    *PREFIX.write().unwrap_or_else(|e| e.into_inner()) = prefix.unwrap_or_default().to_string();
+}
+
+/// The name for the next thread with the given role.
+fn next_name(role: &str) -> String {
+    let prefix = PREFIX.read().unwrap_or_else(|e| e.into_inner());
+    let prefix = if prefix.is_empty() { DEFAULT_PREFIX } else { prefix.as_str() };
+    format!("{prefix}-{role}-{}", NEXT_INDEX.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Spawn a named thread. Every thread the bridge starts goes through here.
+pub fn spawn<F, T>(role: &str, f: F) -> std::io::Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    RUNNING.fetch_add(1, Ordering::Relaxed);
+    let result = std::thread::Builder::new().name(next_name(role)).spawn(move || {
+        let _running = Running;
+        f()
+    });
+    if result.is_err() {
+        RUNNING.fetch_sub(1, Ordering::Relaxed);
+    }
+    result
+}
+
+/// How many threads spawned by the bridge are still running.
+pub fn running() -> usize {
+    RUNNING.load(Ordering::Relaxed)
+}
@@ -0,0 +1,62 @@
+//! Tolerant command JSON (`strata_set_tolerant_json`).
+//!
+//! For hand-written commands, a tolerant handle accepts `//` line comments
+//! and trailing commas before `}` or `]`. They are stripped before anything
+//! else sees the command, so the rest of the bridge only ever parses strict
+//! JSON. String contents are left untouched.
+
+/// `json` without `//` comments and trailing commas.
+pub fn strip(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                out.push(c);
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    drop_trailing_commas(&out)
+}
+
+/// `json` (free of comments) without commas directly before `}` or `]`.
+fn drop_trailing_commas(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in json.char_indices() {
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = json[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,198 @@
+//! Explicit transactions held open across FFI calls, with savepoints.
+//!
+//! Each transaction owns a stratadb `Session` with an active transaction.
+//! stratadb has no savepoints, so the bridge keeps a log of the writes made
+//! in the transaction: rolling back to a savepoint aborts the stratadb
+//! transaction, begins a new one and replays the writes made before it.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use stratadb::{Session, Strata};
+
+use crate::audit;
+use crate::commands;
+use crate::error::BridgeError;
+use crate::handle::HandleRegistry;
+
+struct Txn {
+    handle: u64,
+    branch: Option<String>,
+    session: Session,
+    /// Writes made so far, replayed when rolling back to a savepoint.
+    writes: Vec<Value>,
+    /// Savepoints in creation order: (savepoint ID, writes made before it).
+    savepoints: Vec<(u64, usize)>,
+    next_savepoint: u64,
+}
+
+impl Txn {
+    fn begin(&mut self) -> Result<(), BridgeError> {
+        commands::call(&mut self.session, json!({ "TxnBegin": { "branch": self.branch } }))
+            .map(drop)
+    }
+}
+
+/// Registry of open transactions, keyed by transaction ID.
+pub struct TxnRegistry {
+    next_id: AtomicU64,
+    txns: DashMap<u64, Mutex<Txn>>,
+}
+
+impl TxnRegistry {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            txns: DashMap::new(),
+        }
+    }
+
+    /// Begin a transaction on `handle`, optionally on `branch`.
+    pub fn begin(
+        &self,
+        registry: &HandleRegistry,
+        handle: u64,
+        branch: Option<&str>,
+    ) -> Result<u64, BridgeError> {
+        registry.flush_coalesced(handle)?;
+        let session = registry.run_guarded(handle, |strata| Ok(strata.session()))?;
+        let mut txn = Txn {
+            handle,
+            branch: branch.map(String::from),
+            session,
+            writes: Vec::new(),
+            savepoints: Vec::new(),
+            next_savepoint: 1,
+        };
+        txn.begin()?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.txns.insert(id, Mutex::new(txn));
+        Ok(id)
+    }
+
+    /// Run a stratadb command inside a transaction. Returns its output JSON.
+    pub fn execute(
+        &self,
+        registry: &HandleRegistry,
+        id: u64,
+        command_json: &str,
+    ) -> Result<Value, BridgeError> {
+        let txn = self.txns.get(&id).ok_or("invalid transaction")?;
+        let mut txn = txn.lock().unwrap_or_else(|e| e.into_inner());
+        let command: Value = serde_json::from_str(command_json)
+            .map_err(|e| format!("invalid command JSON: {e}"))?;
+        registry.limits(txn.handle).check_command(&command)?;
+        registry.check_key_policy(txn.handle, &command)?;
+        if let Some(tag) = command_tag(&command) {
+            registry.check_writable(txn.handle, tag)?;
+        }
+
+        let is_write = command_tag(&command).is_some_and(audit::is_mutation);
+        let mut output = registry.run_guarded(txn.handle, |_| {
+            commands::call(&mut txn.session, command.clone())
+        })?;
+        registry.filter_keys(txn.handle, &mut output);
+        if is_write {
+            txn.writes.push(command);
+        }
+        Ok(output)
+    }
+
+    /// Mark the current point in a transaction. Returns the savepoint ID.
+    pub fn savepoint(&self, id: u64) -> Result<u64, BridgeError> {
+        let txn = self.txns.get(&id).ok_or("invalid transaction")?;
+        let mut txn = txn.lock().unwrap_or_else(|e| e.into_inner());
+        let savepoint = txn.next_savepoint;
+        txn.next_savepoint += 1;
+        let writes = txn.writes.len();
+        txn.savepoints.push((savepoint, writes));
+        Ok(savepoint)
+    }
+
+    /// Undo the writes made after `savepoint`, keeping the transaction open.
+    ///
+    /// The savepoint itself survives and can be rolled back to again;
+    /// savepoints created after it are released.
+    pub fn rollback_to(
+        &self,
+        registry: &HandleRegistry,
+        id: u64,
+        savepoint: u64,
+    ) -> Result<(), BridgeError> {
+        let txn = self.txns.get(&id).ok_or("invalid transaction")?;
+        let mut txn = txn.lock().unwrap_or_else(|e| e.into_inner());
+        let position = txn.savepoints.iter().position(|(sp, _)| *sp == savepoint).ok_or_else(|| {
+            BridgeError::Kind(
+                "InvalidInput",
+                json!({ "reason": "unknown savepoint", "savepoint": savepoint, "txn": id }),
+            )
+        })?;
+        let writes = txn.savepoints[position].1;
+        txn.savepoints.truncate(position + 1);
+        txn.writes.truncate(writes);
+
+        registry.run_guarded(txn.handle, |_| {
+            commands::call(&mut txn.session, json!({ "TxnRollback": null }))?;
+            txn.begin()?;
+            for write in txn.writes.clone() {
+                commands::call(&mut txn.session, write)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Commit a transaction and forget it.
+    ///
+    /// Returns the handle and the writes that were committed.
+    pub fn commit(
+        &self,
+        registry: &HandleRegistry,
+        id: u64,
+    ) -> Result<(u64, Vec<Value>), BridgeError> {
+        let (_, txn) = self.txns.remove(&id).ok_or("invalid transaction")?;
+        let mut txn = txn.into_inner().unwrap_or_else(|e| e.into_inner());
+        let (handle, branch) = (txn.handle, txn.branch.take());
+        let writes = std::mem::take(&mut txn.writes);
+        let session = &mut txn.session;
+        let result = registry.run_guarded(handle, |strata| {
+            let mut commit = |_: &Strata| commands::call(session, json!({ "TxnCommit": null }));
+            match registry.memory_cap(handle) {
+                Some(cap) => cap.run_commands(strata, branch.as_deref(), &writes, commit),
+                None => commit(strata),
+            }
+        });
+        if let Err(e) = result {
+            // A commit the memory cap refused never reached stratadb.
+            let _ = commands::call(session, json!({ "TxnRollback": null }));
+            return Err(e);
+        }
+        Ok((handle, writes))
+    }
+
+    /// Roll back a transaction and forget it.
+    pub fn rollback(&self, id: u64) -> Result<(), BridgeError> {
+        let (_, txn) = self.txns.remove(&id).ok_or("invalid transaction")?;
+        let mut txn = txn.into_inner().unwrap_or_else(|e| e.into_inner());
+        commands::call(&mut txn.session, json!({ "TxnRollback": null })).map(drop)
+    }
+
+    /// Roll back every transaction open on `handle`, as it closes.
+    pub fn close_handle(&self, handle: u64) {
+        let ids: Vec<u64> = self
+            .txns
+            .iter()
+            .filter(|txn| txn.lock().unwrap_or_else(|e| e.into_inner()).handle == handle)
+            .map(|txn| *txn.key())
+            .collect();
+        for id in ids {
+            let _ = self.rollback(id);
+        }
+    }
+}
+
+fn command_tag(command: &Value) -> Option<&str> {
+    command.as_object()?.keys().next().map(String::as_str)
+}
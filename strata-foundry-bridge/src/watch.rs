@@ -0,0 +1,135 @@
+//! KV change subscriptions by key prefix.
+//!
+//! stratadb has no change feed, so the bridge reports the KV writes that pass
+//! through it: after a write command succeeds on a handle, each subscription
+//! on that handle whose prefix matches a changed key gets a callback.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use dashmap::DashMap;
+use serde_json::{json, Value};
+
+/// Host change sink: receives a null-terminated JSON event
+/// (`{"key": "...", "event": "put" | "delete"}`), valid only for the duration
+/// of the call, and the `user_data` given when subscribing.
+pub type WatchCallback = extern "C" fn(event_json: *const c_char, user_data: *mut c_void);
+
+struct Subscription {
+    handle: u64,
+    prefix: String,
+    callback: WatchCallback,
+    /// The host's `user_data` pointer, passed back untouched.
+    user_data: usize,
+}
+
+/// Registry of prefix subscriptions, keyed by subscription ID.
+pub struct WatchRegistry {
+    next_id: AtomicU64,
+    subscriptions: DashMap<u64, Subscription>,
+}
+
+impl WatchRegistry {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            subscriptions: DashMap::new(),
+        }
+    }
+
+    /// Subscribe to changes of keys starting with `prefix` on `handle`.
+    pub fn subscribe(
+        &self,
+        handle: u64,
+        prefix: &str,
+        callback: WatchCallback,
+        user_data: *mut c_void,
+    ) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let subscription = Subscription {
+            handle,
+            prefix: prefix.to_string(),
+            callback,
+            user_data: user_data as usize,
+        };
+        self.subscriptions.insert(id, subscription);
+        id
+    }
+
+    /// Remove a subscription. Returns whether it existed.
+    pub fn unsubscribe(&self, id: u64) -> bool {
+        self.subscriptions.remove(&id).is_some()
+    }
+
+    /// Drop every subscription on `handle`, as it closes.
+    pub fn close_handle(&self, handle: u64) {
+        self.subscriptions.retain(|_, sub| sub.handle != handle);
+    }
+
+    pub fn is_watched(&self, handle: u64) -> bool {
+        self.subscriptions.iter().any(|sub| sub.handle == handle)
+    }
+
+    /// Report the KV changes made by a successful `{"Tag": {...}}` command.
+    pub fn notify_command(&self, handle: u64, command: &Value) {
+        for (key, event) in kv_changes(command) {
+            self.notify(handle, key, event);
+        }
+    }
+
+    /// Report one change of `key` on `handle`; `event` is `"put"` or `"delete"`.
+    pub fn notify(&self, handle: u64, key: &str, event: &str) {
+        // Collect first so a callback can unsubscribe without deadlocking.
+        let targets: Vec<(WatchCallback, usize)> = self
+            .subscriptions
+            .iter()
+            .filter(|sub| sub.handle == handle && key.starts_with(sub.prefix.as_str()))
+            .map(|sub| (sub.callback, sub.user_data))
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let event = json!({ "key": key, "event": event }).to_string();
+        let event = CString::new(event).unwrap_or_default();
+        for (callback, user_data) in targets {
+            callback(event.as_ptr(), user_data as *mut c_void);
+        }
+    }
+}
+
+/// The KV keys a command writes, with `"put"` or `"delete"`.
+fn kv_changes(command: &Value) -> Vec<(&str, &'static str)> {
+    let Some((tag, body)) = command.as_object().and_then(|m| m.iter().next()) else {
+        return Vec::new();
+    };
+    let field = |name: &str| body.get(name).and_then(Value::as_str);
+    match tag.as_str() {
+        "KvPut" => field("key").map(|key| vec![(key, "put")]).unwrap_or_default(),
+        "KvDelete" => field("key").map(|key| vec![(key, "delete")]).unwrap_or_default(),
+        "KvBatchPut" => body
+            .get("entries")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.get("key").and_then(Value::as_str))
+            .map(|key| (key, "put"))
+            .collect(),
+        "KvRename" => [(field("from"), "delete"), (field("to"), "put")]
+            .into_iter()
+            .filter_map(|(key, event)| Some((key?, event)))
+            .collect(),
+        // A key swapped with an absent one (`allow_missing`) is deleted, but
+        // only the output says which, so both are reported as puts.
+        "KvSwap" => [field("key_a"), field("key_b")]
+            .into_iter()
+            .flatten()
+            .map(|key| (key, "put"))
+            .collect(),
+        // Reported even when the key existed and nothing was written.
+        "KvGetOrPut" => field("key").map(|key| vec![(key, "put")]).unwrap_or_default(),
+        "KvUpdate" => field("key").map(|key| vec![(key, "put")]).unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}